@@ -0,0 +1,79 @@
+#!/usr/bin/env ruby
+# frozen_string_literal: true
+
+# メモリマップによるファイル読み込みのベンチマーク
+#
+# RbCsv.read（メモリマップ）と、ファイル全体を文字列に読み込んでからパースする方法
+# （従来の read_to_string 相当）、IOからのストリーミング読み込みを比較する。
+# ピークメモリ（VmHWM）は計測ごとに子プロセスを fork して測定する（Linuxのみ）。
+#
+# 使い方:
+#   cd examples/benchmarks
+#   ruby mmap_benchmark.rb [レコード数]
+
+require 'benchmark'
+require_relative '../../lib/rbcsv'
+
+# ベンチマーク設定
+RECORDS = (ARGV[0] || 1_000_000).to_i
+MMAP_CSV_FILE = 'mmap_sample.csv'
+
+puts "=" * 60
+puts "RbCsv メモリマップ読み込み ベンチマーク"
+puts "=" * 60
+puts "Ruby version: #{RUBY_VERSION}"
+puts "RbCsv version: #{RbCsv::VERSION}"
+puts "Date: #{Time.now.strftime('%Y-%m-%d %H:%M:%S')}"
+puts
+
+def create_mmap_sample_file
+  puts "テストデータを作成中..."
+  File.open(MMAP_CSV_FILE, "w") do |file|
+    file.puts "id,name,age,score,department,salary"
+    RECORDS.times do |i|
+      file.puts "#{i + 1},User#{i + 1},#{20 + i % 45},#{(60 + i % 400 / 10.0).round(1)}," \
+                "#{%w[Engineering Sales Marketing HR][i % 4]},#{40_000 + i % 80_000}"
+    end
+  end
+  puts "ファイル作成完了: #{MMAP_CSV_FILE} (#{File.size(MMAP_CSV_FILE)} bytes)"
+  puts
+end
+
+# 子プロセスで処理を実行し、処理時間とピークメモリ（KB）を返す
+def measure
+  reader, writer = IO.pipe
+  pid = fork do
+    reader.close
+    time = Benchmark.realtime { yield }
+    peak = File.read("/proc/self/status")[/VmHWM:\s+(\d+)/, 1].to_i
+    writer.puts "#{time} #{peak}"
+    writer.close
+    exit!(0)
+  end
+  writer.close
+  time, peak = reader.read.split.map(&:to_f)
+  Process.wait(pid)
+  [time, peak]
+end
+
+CASES = {
+  "RbCsv.read (mmap)" => -> { RbCsv.read(MMAP_CSV_FILE) },
+  "RbCsv.parse(File.read) (buffered)" => -> { RbCsv.parse(File.read(MMAP_CSV_FILE)) },
+  "RbCsv.read(File.open) (IO stream)" => -> { File.open(MMAP_CSV_FILE) { |io| RbCsv.read(io) } },
+  "RbCsv.read_typed (mmap)" => -> { RbCsv.read_typed(MMAP_CSV_FILE) },
+  "RbCsv.parse_typed(File.read) (buffered)" => -> { RbCsv.parse_typed(File.read(MMAP_CSV_FILE)) },
+}.freeze
+
+begin
+  create_mmap_sample_file
+
+  puts "📁 ファイル読み込み (#{RECORDS}レコード)"
+  puts "-" * 70
+  puts format("%-42s %10s %14s", "", "real (s)", "peak RSS (MB)")
+  CASES.each do |label, block|
+    time, peak = measure(&block)
+    puts format("%-42s %10.4f %14.1f", label, time, peak / 1024.0)
+  end
+ensure
+  File.delete(MMAP_CSV_FILE) if File.exist?(MMAP_CSV_FILE)
+end
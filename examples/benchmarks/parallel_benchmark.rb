@@ -0,0 +1,84 @@
+#!/usr/bin/env ruby
+# frozen_string_literal: true
+
+# threads: オプションによる並列パースのベンチマーク
+#
+# 使い方:
+#   cd examples/benchmarks
+#   ruby parallel_benchmark.rb [レコード数]
+
+require 'benchmark'
+require 'etc'
+require_relative '../../lib/rbcsv'
+
+# ベンチマーク設定
+RECORDS = (ARGV[0] || 1_000_000).to_i
+ITERATIONS = 3
+PARALLEL_CSV_FILE = 'parallel_sample.csv'
+THREAD_COUNTS = [1, 2, 4, 8, 16].select { |n| n <= Etc.nprocessors }
+
+puts "=" * 60
+puts "RbCsv 並列パース ベンチマーク"
+puts "=" * 60
+puts "Ruby version: #{RUBY_VERSION}"
+puts "RbCsv version: #{RbCsv::VERSION}"
+puts "CPU: #{Etc.nprocessors} cores"
+puts "Date: #{Time.now.strftime('%Y-%m-%d %H:%M:%S')}"
+puts
+
+def create_parallel_sample_file
+  puts "テストデータを作成中..."
+  departments = %w[Engineering Sales Marketing HR]
+  File.open(PARALLEL_CSV_FILE, "w") do |file|
+    file.puts "id,name,age,score,department,salary,note"
+    RECORDS.times do |i|
+      # クォート内の改行・カンマを含む行も混ぜる
+      note = i % 10 == 0 ? "\"multi\nline, note #{i}\"" : "note #{i}"
+      file.puts "#{i + 1},User#{i + 1},#{20 + i % 45},#{(60 + i % 400 / 10.0).round(1)}," \
+                "#{departments[i % 4]},#{40_000 + i % 80_000},#{note}"
+    end
+  end
+  puts "ファイル作成完了: #{PARALLEL_CSV_FILE} (#{File.size(PARALLEL_CSV_FILE)} bytes)"
+  puts
+end
+
+begin
+  create_parallel_sample_file
+  content = File.read(PARALLEL_CSV_FILE)
+  expected = RbCsv.read(PARALLEL_CSV_FILE)
+
+  puts "📁 read / read_typed (#{ITERATIONS}回実行, #{RECORDS}レコード)"
+  puts "-" * 50
+  Benchmark.bm(40) do |x|
+    THREAD_COUNTS.each do |threads|
+      x.report("RbCsv.read (threads: #{threads})") do
+        ITERATIONS.times { RbCsv.read(PARALLEL_CSV_FILE, threads: threads) }
+      end
+    end
+    THREAD_COUNTS.each do |threads|
+      x.report("RbCsv.read_typed (threads: #{threads})") do
+        ITERATIONS.times { RbCsv.read_typed(PARALLEL_CSV_FILE, threads: threads) }
+      end
+    end
+  end
+  puts
+
+  puts "🚀 parse (#{ITERATIONS}回実行, #{content.bytesize} bytes)"
+  puts "-" * 50
+  Benchmark.bm(40) do |x|
+    THREAD_COUNTS.each do |threads|
+      x.report("RbCsv.parse (threads: #{threads})") do
+        ITERATIONS.times { RbCsv.parse(content, threads: threads) }
+      end
+    end
+  end
+  puts
+
+  # 結果が1スレッドの場合と一致することを確認
+  THREAD_COUNTS.each do |threads|
+    same = RbCsv.read(PARALLEL_CSV_FILE, threads: threads) == expected
+    puts "threads: #{threads} 結果一致: #{same ? '✅' : '❌'}"
+  end
+ensure
+  File.delete(PARALLEL_CSV_FILE) if File.exist?(PARALLEL_CSV_FILE)
+end
@@ -0,0 +1,366 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Read;
+use crate::converter::{resolve_columns, ColumnKey};
+use crate::dedup::KeyValue;
+use crate::error::CsvError;
+use crate::filter::RowSelector;
+use crate::parser::{CsvParseOptions, RecordReader};
+use crate::sort::compare_values;
+use crate::value::CsvValue;
+
+/// 集計関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    /// 空でない値の数
+    Count,
+    /// 数値の合計（すべて整数なら整数）
+    Sum,
+    /// 数値の平均
+    Mean,
+    /// 最小値・最大値（数値は数値として、数値は文字列より前として比較する）
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    /// Rubyのオプション名（`:count`・`:sum`・`:mean`・`:min`・`:max`）から変換する
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "count" => Some(AggregateFunction::Count),
+            "sum" => Some(AggregateFunction::Sum),
+            "mean" => Some(AggregateFunction::Mean),
+            "min" => Some(AggregateFunction::Min),
+            "max" => Some(AggregateFunction::Max),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "count",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Mean => "mean",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+        }
+    }
+}
+
+/// 1列に対する集計（`aggregations:` の1項目）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregation {
+    pub column: ColumnKey,
+    pub function: AggregateFunction,
+}
+
+/// 集計の設定
+#[derive(Debug, Clone, Default)]
+pub struct AggregateOptions {
+    /// グループ化する列（空の場合はすべての行を1つのグループとして集計する）
+    pub group_by: Vec<ColumnKey>,
+    pub aggregations: Vec<Aggregation>,
+}
+
+/// 集計結果の表
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateTable {
+    /// 列名（グループ化した列の名前、`<列名>_<関数名>`）
+    pub columns: Vec<String>,
+    /// グループごとの値（グループが最初に現れた順）
+    pub rows: Vec<Vec<CsvValue>>,
+}
+
+/// CSVを1行ずつ読み込み、グループごとに集計する
+///
+/// 保持するのはグループごとの集計値だけのため、グループの数が少なければ大きなファイルでも一定のメモリで処理できる。
+/// 集計する値は `CsvValue` に型変換し、空の値は数えない。`sum`・`mean` で数値でない値があればエラーにする。
+/// グループ化する列の値は `typed` が真の場合に型変換する（`1` と `1.0` は同じグループ）。
+///
+/// # Arguments
+/// * `input` - CSVの読み込み元
+/// * `parse` - 書式・ヘッダー・trim・型変換・絞り込みの設定（`headers` が真の場合は1行目を列名として扱う）
+/// * `options` - 集計の設定
+pub fn aggregate_reader<R: Read>(
+    input: R,
+    parse: &CsvParseOptions,
+    options: &AggregateOptions,
+) -> Result<AggregateTable, CsvError> {
+    let opened = RecordReader::open(input, parse)?;
+    let header = opened.as_ref().and_then(|(_, header)| header.as_ref());
+    let columns = options
+        .group_by
+        .iter()
+        .map(|key| column_name(key, header))
+        .chain(options.aggregations.iter().map(|aggregation| {
+            format!("{}_{}", column_name(&aggregation.column, header), aggregation.function.name())
+        }))
+        .collect::<Vec<String>>();
+    // 同じ名前の列があると、Hashにした結果で後の値が前の値を上書きしてしまう
+    let duplicate = columns.iter().enumerate().find(|&(index, name)| columns[..index].contains(name));
+    if let Some((_, name)) = duplicate {
+        return Err(CsvError::invalid_argument(format!("duplicate aggregate column: {}", name)));
+    }
+    // 空の入力（ヘッダー行もない）は列名だけを返す
+    let Some((mut records, header)) = opened else {
+        return Ok(AggregateTable { columns, rows: Vec::new() });
+    };
+
+    let mut selector = RowSelector::new(parse.filter.clone());
+    if let Some(header) = &header {
+        selector.select(0, header)?;
+    }
+    let group_columns = resolve_columns(&options.group_by, header.as_ref(), &parse.header_options, "Aggregate")?;
+    let value_columns = resolve_columns(
+        options.aggregations.iter().map(|aggregation| &aggregation.column),
+        header.as_ref(),
+        &parse.header_options,
+        "Aggregate",
+    )?;
+
+    let mut record = csv::StringRecord::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut positions: HashMap<Vec<KeyValue>, usize> = HashMap::new();
+
+    while let Some(line_num) = records.read(&mut record)? {
+        if !selector.select(line_num, &record)? {
+            continue;
+        }
+
+        let key: Vec<KeyValue> = group_columns
+            .iter()
+            .map(|&index| KeyValue::from_field(record.get(index), parse.typed))
+            .collect();
+        let position = match positions.get(&key) {
+            Some(&position) => position,
+            None => {
+                let values = group_columns
+                    .iter()
+                    .map(|&index| match record.get(index) {
+                        Some(field) if parse.typed => CsvValue::from_str(field),
+                        Some(field) => CsvValue::String(field.to_string()),
+                        None => CsvValue::Nil,
+                    })
+                    .collect();
+                groups.push(Group::new(values, &options.aggregations));
+                positions.insert(key, groups.len() - 1);
+                groups.len() - 1
+            }
+        };
+
+        let group = &mut groups[position];
+        for ((accumulator, &index), aggregation) in group.accumulators.iter_mut().zip(&value_columns).zip(&options.aggregations) {
+            let field = record.get(index).unwrap_or("");
+            if field.is_empty() {
+                continue;
+            }
+            accumulator.add(CsvValue::from_str(field)).map_err(|value| {
+                CsvError::invalid_data(format!(
+                    "Cannot aggregate non-numeric value {:?} with {} in column {} at line {}",
+                    value,
+                    aggregation.function.name(),
+                    column_name(&aggregation.column, header.as_ref()),
+                    line_num + 1
+                ))
+            })?;
+        }
+    }
+
+    // グループ化しない場合は行がなくても1行（件数0）を返す
+    if options.group_by.is_empty() && groups.is_empty() {
+        groups.push(Group::new(Vec::new(), &options.aggregations));
+    }
+
+    let rows = groups
+        .into_iter()
+        .map(|group| {
+            let mut row = group.key;
+            row.extend(group.accumulators.into_iter().map(Accumulator::finish));
+            row
+        })
+        .collect();
+
+    Ok(AggregateTable { columns, rows })
+}
+
+/// 1グループ分の集計値
+struct Group {
+    key: Vec<CsvValue>,
+    accumulators: Vec<Accumulator>,
+}
+
+impl Group {
+    fn new(key: Vec<CsvValue>, aggregations: &[Aggregation]) -> Self {
+        let accumulators = aggregations
+            .iter()
+            .map(|aggregation| match aggregation.function {
+                AggregateFunction::Count => Accumulator::Count(0),
+                AggregateFunction::Sum => Accumulator::Sum(Sum::Integer(0)),
+                AggregateFunction::Mean => Accumulator::Mean { sum: 0.0, count: 0 },
+                AggregateFunction::Min => Accumulator::Extreme { value: None, keep: Ordering::Less },
+                AggregateFunction::Max => Accumulator::Extreme { value: None, keep: Ordering::Greater },
+            })
+            .collect();
+        Self { key, accumulators }
+    }
+}
+
+/// 合計（整数の範囲を超えた場合と浮動小数点数を含む場合は浮動小数点数にする）
+#[derive(Debug, Clone, Copy)]
+enum Sum {
+    Integer(i64),
+    Float(f64),
+}
+
+#[derive(Debug, Clone)]
+enum Accumulator {
+    Count(usize),
+    Sum(Sum),
+    Mean { sum: f64, count: usize },
+    /// 最小値（`keep` がLess）・最大値（`keep` がGreater）
+    Extreme { value: Option<CsvValue>, keep: Ordering },
+}
+
+impl Accumulator {
+    /// 空でない値を加える（数値が必要な集計で数値でない場合は値の文字列を返す）
+    fn add(&mut self, value: CsvValue) -> Result<(), String> {
+        match self {
+            Accumulator::Count(count) => *count += 1,
+            Accumulator::Sum(sum) => {
+                *sum = match (*sum, value) {
+                    (Sum::Integer(a), CsvValue::Integer(b)) => match a.checked_add(b) {
+                        Some(total) => Sum::Integer(total),
+                        None => Sum::Float(a as f64 + b as f64),
+                    },
+                    (Sum::Integer(a), CsvValue::Float(b)) => Sum::Float(a as f64 + b),
+                    (Sum::Float(a), CsvValue::Integer(b)) => Sum::Float(a + b as f64),
+                    (Sum::Float(a), CsvValue::Float(b)) => Sum::Float(a + b),
+                    (_, value) => return Err(value.to_string()),
+                };
+            }
+            Accumulator::Mean { sum, count } => {
+                *sum += match value {
+                    CsvValue::Integer(i) => i as f64,
+                    CsvValue::Float(f) => f,
+                    value => return Err(value.to_string()),
+                };
+                *count += 1;
+            }
+            Accumulator::Extreme { value: current, keep } => {
+                if current.as_ref().is_none_or(|current| compare_values(&value, current) == *keep) {
+                    *current = Some(value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> CsvValue {
+        match self {
+            Accumulator::Count(count) => CsvValue::Integer(count as i64),
+            Accumulator::Sum(Sum::Integer(sum)) => CsvValue::Integer(sum),
+            Accumulator::Sum(Sum::Float(sum)) => CsvValue::Float(sum),
+            Accumulator::Mean { count: 0, .. } => CsvValue::Nil,
+            Accumulator::Mean { sum, count } => CsvValue::Float(sum / count as f64),
+            Accumulator::Extreme { value, .. } => value.unwrap_or(CsvValue::Nil),
+        }
+    }
+}
+
+/// 結果の列名（ヘッダー名。ヘッダーがない場合は `$0`・`$1` ...）
+fn column_name(key: &ColumnKey, header: Option<&csv::StringRecord>) -> String {
+    match key {
+        ColumnKey::Name(name) => name.clone(),
+        ColumnKey::Index(index) => match header.and_then(|header| header.get(*index)) {
+            Some(name) => name.to_string(),
+            None => format!("${}", index),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregation(column: &str, function: AggregateFunction) -> Aggregation {
+        Aggregation { column: ColumnKey::Name(column.to_string()), function }
+    }
+
+    #[test]
+    fn test_aggregate_by_group() {
+        let input = "category,amount,id\nA,10,1\nB,2.5,2\nA,5,3\nB,,4\nA,1,5\n";
+        let parse = CsvParseOptions { headers: true, typed: true, ..Default::default() };
+        let options = AggregateOptions {
+            group_by: vec![ColumnKey::Name("category".to_string())],
+            aggregations: vec![
+                aggregation("amount", AggregateFunction::Sum),
+                aggregation("amount", AggregateFunction::Mean),
+                aggregation("amount", AggregateFunction::Max),
+                aggregation("id", AggregateFunction::Count),
+            ],
+        };
+        let table = aggregate_reader(input.as_bytes(), &parse, &options).unwrap();
+
+        assert_eq!(table.columns, vec!["category", "amount_sum", "amount_mean", "amount_max", "id_count"]);
+        assert_eq!(
+            table.rows,
+            vec![
+                vec![
+                    CsvValue::String("A".to_string()),
+                    CsvValue::Integer(16),
+                    CsvValue::Float(16.0 / 3.0),
+                    CsvValue::Integer(10),
+                    CsvValue::Integer(3),
+                ],
+                vec![
+                    CsvValue::String("B".to_string()),
+                    CsvValue::Float(2.5),
+                    CsvValue::Float(2.5),
+                    CsvValue::Float(2.5),
+                    CsvValue::Integer(2),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_without_groups_and_headers() {
+        let parse = CsvParseOptions::default();
+        let options = AggregateOptions {
+            group_by: Vec::new(),
+            aggregations: vec![
+                Aggregation { column: ColumnKey::Index(0), function: AggregateFunction::Min },
+                Aggregation { column: ColumnKey::Index(0), function: AggregateFunction::Count },
+            ],
+        };
+        let table = aggregate_reader("b\n3\na\n".as_bytes(), &parse, &options).unwrap();
+        assert_eq!(table.columns, vec!["$0_min", "$0_count"]);
+        assert_eq!(table.rows, vec![vec![CsvValue::Integer(3), CsvValue::Integer(3)]]);
+
+        let table = aggregate_reader("".as_bytes(), &parse, &options).unwrap();
+        assert_eq!(table.rows, vec![vec![CsvValue::Nil, CsvValue::Integer(0)]]);
+    }
+
+    #[test]
+    fn test_aggregate_rejects_non_numeric_values() {
+        let parse = CsvParseOptions { headers: true, ..Default::default() };
+        let options = AggregateOptions { group_by: Vec::new(), aggregations: vec![aggregation("amount", AggregateFunction::Sum)] };
+        let error = aggregate_reader("amount\n1\nabc\n".as_bytes(), &parse, &options).unwrap_err();
+        assert!(error.to_string().contains("Cannot aggregate non-numeric value \"abc\" with sum in column amount at line 3"));
+    }
+
+    #[test]
+    fn test_aggregate_rejects_duplicate_columns() {
+        let parse = CsvParseOptions { headers: true, ..Default::default() };
+        let options = AggregateOptions {
+            group_by: Vec::new(),
+            aggregations: vec![
+                Aggregation { column: ColumnKey::Index(0), function: AggregateFunction::Count },
+                aggregation("id", AggregateFunction::Count),
+            ],
+        };
+        let error = aggregate_reader("id\n1\n".as_bytes(), &parse, &options).unwrap_err();
+        assert!(matches!(error.kind(), crate::error::ErrorKind::InvalidArgument));
+        assert_eq!(error.to_string(), "duplicate aggregate column: id_count");
+    }
+}
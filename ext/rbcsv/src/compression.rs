@@ -0,0 +1,171 @@
+use crate::error::CsvError;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// 圧縮形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Compression::None),
+            "gzip" | "gz" => Some(Compression::Gzip),
+            "zstd" | "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// 拡張子（.gz / .zst）から圧縮形式を判定する
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// 先頭のマジックバイトから圧縮形式を判定する
+    pub fn detect(head: &[u8]) -> Self {
+        if head.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// 入力が圧縮されていれば展開するリーダーで包む
+/// 形式は先頭のマジックバイトで判定するため、拡張子のないファイルやIOでも展開できる
+pub fn decompress<'a, R: Read + Send + 'a>(mut input: R) -> io::Result<Box<dyn Read + Send + 'a>> {
+    // 判定に使った先頭のバイトは読み戻せないため、残りの入力の前につなぎ直す
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    (&mut input).take(ZSTD_MAGIC.len() as u64).read_to_end(&mut head)?;
+    let compression = Compression::detect(&head);
+    let input = io::Cursor::new(head).chain(input);
+
+    Ok(match compression {
+        Compression::Gzip => Box::new(MultiGzDecoder::new(input)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(input)?),
+        Compression::None => Box::new(input),
+    })
+}
+
+/// CSVファイルを開く（圧縮されていれば展開する）
+pub fn open_csv_reader(file_path: &str) -> Result<Box<dyn Read + Send>, CsvError> {
+    let file = open_csv_file(file_path)?;
+    decompress(file).map_err(|e| CsvError::io(format!("Failed to read file '{}': {}", file_path, e)))
+}
+
+/// 読み込み用にファイルを開く
+/// 名前付きパイプや `/dev/stdin` 等の特殊ファイルも開けるが、ディレクトリはエラーにする
+pub fn open_csv_file(file_path: &str) -> Result<fs::File, CsvError> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(CsvError::io(format!("File not found: {}", file_path)));
+    }
+
+    if path.is_dir() {
+        return Err(CsvError::io(format!("Path is not a file: {}", file_path)));
+    }
+
+    fs::File::open(path).map_err(|e| CsvError::io(format!("Failed to open file '{}': {}", file_path, e)))
+}
+
+/// 圧縮形式に応じて書き込み先を包むライター
+pub enum CompressedWriter<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn new(inner: W, compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::None => CompressedWriter::Plain(inner),
+            Compression::Gzip => CompressedWriter::Gzip(GzEncoder::new(inner, flate2::Compression::default())),
+            Compression::Zstd => CompressedWriter::Zstd(zstd::stream::write::Encoder::new(inner, 0)?),
+        })
+    }
+
+    pub fn get_ref(&self) -> &W {
+        match self {
+            CompressedWriter::Plain(inner) => inner,
+            CompressedWriter::Gzip(encoder) => encoder.get_ref(),
+            CompressedWriter::Zstd(encoder) => encoder.get_ref(),
+        }
+    }
+
+    /// 圧縮ストリームを終端して書き込み先を返す
+    /// 終端しないと圧縮ファイルが壊れるため、書き込みの最後に必ず呼び出す
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            CompressedWriter::Plain(mut inner) => {
+                inner.flush()?;
+                Ok(inner)
+            }
+            CompressedWriter::Gzip(encoder) => encoder.finish(),
+            CompressedWriter::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedWriter::Plain(inner) => inner.write(buf),
+            CompressedWriter::Gzip(encoder) => encoder.write(buf),
+            CompressedWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedWriter::Plain(inner) => inner.flush(),
+            CompressedWriter::Gzip(encoder) => encoder.flush(),
+            CompressedWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(compression: Compression) -> String {
+        let mut writer = CompressedWriter::new(Vec::new(), compression).unwrap();
+        writer.write_all(b"a,b\n1,2\n").unwrap();
+        let bytes = writer.finish().unwrap();
+        assert_eq!(Compression::detect(&bytes), compression);
+
+        let mut content = String::new();
+        decompress(bytes.as_slice()).unwrap().read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        assert_eq!(roundtrip(Compression::None), "a,b\n1,2\n");
+        assert_eq!(roundtrip(Compression::Gzip), "a,b\n1,2\n");
+        assert_eq!(roundtrip(Compression::Zstd), "a,b\n1,2\n");
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Compression::from_path("data.csv.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("data.csv.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("data.csv"), Compression::None);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::error::CsvError;
+use crate::header::HeaderOptions;
+use crate::value::CsvValue;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// コンバーターを適用する列の指定
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnKey {
+    /// 列番号（0始まり）
+    Index(usize),
+    /// ヘッダー名（1行目をヘッダーとして解決する）
+    Name(String),
+}
+
+impl ColumnKey {
+    /// ヘッダー名または列番号から列番号に解決する（`headers` はヘッダー行の各列の名前。ヘッダーがない場合はNone）
+    ///
+    /// ヘッダー名は元のヘッダー行の名前が一致する列に解決し、なければ `header_options` の
+    /// `header_converters:` で正規化した名前が一致する列に解決する（列の指定はすべてこの規則による）。
+    /// `context` はエラーのメッセージに使う処理の名前（`"Sort"` 等）
+    pub fn resolve<'h, H>(&self, headers: Option<H>, header_options: &HeaderOptions, context: &str) -> Result<usize, CsvError>
+    where
+        H: IntoIterator<Item = &'h str> + Clone,
+    {
+        match self {
+            ColumnKey::Index(index) => Ok(*index),
+            ColumnKey::Name(name) => {
+                let Some(headers) = headers else {
+                    return Err(CsvError::invalid_data(format!(
+                        "{} column {} requires a header row (use column indexes without headers)",
+                        context, name
+                    )));
+                };
+                let normalized = || {
+                    let converted = !header_options.converters.is_empty();
+                    converted.then(|| headers.clone().into_iter().position(|header| header_options.normalize(header) == *name))?
+                };
+                headers.clone().into_iter().position(|header| header == name).or_else(normalized).ok_or_else(|| {
+                    CsvError::invalid_data(format!("{} column not found in headers: {}", context, name))
+                })
+            }
+        }
+    }
+}
+
+/// 複数の列を列番号に解決する（`ColumnKey::resolve` を参照）
+pub fn resolve_columns<'a, 'h, H>(
+    keys: impl IntoIterator<Item = &'a ColumnKey>,
+    headers: Option<H>,
+    header_options: &HeaderOptions,
+    context: &str,
+) -> Result<Vec<usize>, CsvError>
+where
+    H: IntoIterator<Item = &'h str> + Clone,
+{
+    keys.into_iter().map(|key| key.resolve(headers.clone(), header_options, context)).collect()
+}
+
+/// フィールドの変換方法
+#[derive(Debug, Clone)]
+pub enum Converter {
+    /// 正規表現にマッチした部分（キャプチャがあれば最初のキャプチャ）を型変換する
+    Regex(Regex),
+    /// 対応表に一致した値に置き換える
+    Lookup(HashMap<String, CsvValue>),
+    /// 接頭辞を取り除いてから型変換する
+    StripPrefix(String),
+    /// Ruby側のProcで変換する（値はProc一覧のインデックス）
+    Custom(usize),
+}
+
+impl Converter {
+    /// フィールドを変換する
+    /// 正規表現・対応表・接頭辞に該当しない値は通常の型認識にフォールバックする
+    pub fn convert(&self, field: &str) -> CsvValue {
+        match self {
+            Converter::Regex(re) => match re.captures(field) {
+                Some(caps) => {
+                    let matched = caps.get(1).or_else(|| caps.get(0)).map_or(field, |m| m.as_str());
+                    CsvValue::from_str(matched)
+                }
+                None => CsvValue::from_str(field),
+            },
+            Converter::Lookup(table) => table
+                .get(field)
+                .cloned()
+                .unwrap_or_else(|| CsvValue::from_str(field)),
+            Converter::StripPrefix(prefix) => {
+                CsvValue::from_str(field.strip_prefix(prefix.as_str()).unwrap_or(field))
+            }
+            Converter::Custom(index) => CsvValue::Deferred {
+                converter: *index,
+                raw: field.to_string(),
+            },
+        }
+    }
+}
+
+/// 列ごとのコンバーター設定
+#[derive(Debug, Clone, Default)]
+pub struct ColumnConverters {
+    entries: Vec<(ColumnKey, Converter)>,
+}
+
+impl ColumnConverters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// コンバーターを登録する（同じ列に複数登録した場合は後勝ち）
+    pub fn add(&mut self, key: ColumnKey, converter: Converter) {
+        self.entries.push((key, converter));
+    }
+
+    /// ヘッダー名で指定されたコンバーターがあるか
+    pub fn uses_header_names(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|(key, _)| matches!(key, ColumnKey::Name(_)))
+    }
+
+    /// 列番号ごとのコンバーターを解決する
+    /// ヘッダー名の指定がある場合は `headers` から列番号を引く（`ColumnKey::resolve` を参照）
+    pub fn resolve(
+        &self,
+        headers: Option<&csv::StringRecord>,
+        header_options: &HeaderOptions,
+    ) -> Result<HashMap<usize, &Converter>, CsvError> {
+        let mut resolved = HashMap::new();
+
+        for (key, converter) in &self.entries {
+            resolved.insert(key.resolve(headers, header_options, "Converter")?, converter);
+        }
+
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::HeaderConverter;
+
+    #[test]
+    fn test_resolve_columns() {
+        let headers = csv::StringRecord::from(vec!["id", "name"]);
+        let keys = [ColumnKey::Name("name".to_string()), ColumnKey::Index(0)];
+        let options = HeaderOptions::default();
+        assert_eq!(resolve_columns(&keys, Some(&headers), &options, "Sort").unwrap(), vec![1, 0]);
+
+        let missing = ColumnKey::Name("age".to_string());
+        let err = missing.resolve(Some(&headers), &options, "Dedup").unwrap_err();
+        assert!(err.to_string().contains("Dedup column not found in headers: age"));
+        let err = missing.resolve(None::<&csv::StringRecord>, &options, "Aggregate").unwrap_err();
+        assert!(err.to_string().contains("Aggregate column age requires a header row"));
+    }
+
+    #[test]
+    fn test_resolve_raw_then_normalized_names() {
+        let headers = csv::StringRecord::from(vec!["Unit Price", "unit_price_2", "Amount"]);
+        let options = HeaderOptions { converters: vec![HeaderConverter::SnakeCase], ..Default::default() };
+        let key = |name: &str| ColumnKey::Name(name.to_string());
+
+        assert_eq!(key("Unit Price").resolve(Some(&headers), &options, "Sort").unwrap(), 0);
+        assert_eq!(key("unit_price").resolve(Some(&headers), &options, "Sort").unwrap(), 0);
+        assert_eq!(key("amount").resolve(Some(&headers), &options, "Sort").unwrap(), 2);
+        assert!(key("amount").resolve(Some(&headers), &HeaderOptions::default(), "Sort").is_err());
+    }
+
+    #[test]
+    fn test_regex_converter_uses_first_capture() {
+        let converter = Converter::Regex(Regex::new(r"^(\d+)kg$").unwrap());
+        assert_eq!(converter.convert("12kg"), CsvValue::Integer(12));
+        assert_eq!(converter.convert("heavy"), CsvValue::String("heavy".to_string()));
+    }
+
+    #[test]
+    fn test_lookup_converter_falls_back_to_typing() {
+        let mut table = HashMap::new();
+        table.insert("Y".to_string(), CsvValue::Boolean(true));
+        table.insert("N".to_string(), CsvValue::Boolean(false));
+        let converter = Converter::Lookup(table);
+
+        assert_eq!(converter.convert("Y"), CsvValue::Boolean(true));
+        assert_eq!(converter.convert("N"), CsvValue::Boolean(false));
+        assert_eq!(converter.convert("1"), CsvValue::Integer(1));
+    }
+
+    #[test]
+    fn test_strip_prefix_converter() {
+        let converter = Converter::StripPrefix("$".to_string());
+        assert_eq!(converter.convert("$10.5"), CsvValue::Float(10.5));
+        assert_eq!(converter.convert("10"), CsvValue::Integer(10));
+    }
+
+    #[test]
+    fn test_resolve_by_header_name() {
+        let mut converters = ColumnConverters::new();
+        converters.add(ColumnKey::Name("price".to_string()), Converter::StripPrefix("$".to_string()));
+        let headers = csv::StringRecord::from(vec!["name", "price"]);
+
+        let resolved = converters.resolve(Some(&headers), &HeaderOptions::default()).unwrap();
+        assert!(resolved.contains_key(&1));
+
+        let missing = ColumnConverters {
+            entries: vec![(ColumnKey::Name("amount".to_string()), Converter::Custom(0))],
+        };
+        let err = missing.resolve(Some(&headers), &HeaderOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("Converter column not found in headers: amount"));
+    }
+}
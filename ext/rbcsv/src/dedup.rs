@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use crate::converter::{resolve_columns, ColumnKey};
+use crate::error::CsvError;
+use crate::parser::{dialect_writer, CsvParseOptions, RecordReader};
+use crate::value::CsvValue;
+
+/// 重複したレコードのどれを残すか（`keep:`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Keep {
+    /// 最初に現れたレコードを残す
+    #[default]
+    First,
+    /// 最後に現れたレコードを残す（出力はそのレコードの位置の順）
+    Last,
+}
+
+impl Keep {
+    /// Rubyのオプション名（`:first`・`:last`）から変換する
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "first" => Some(Keep::First),
+            "last" => Some(Keep::Last),
+            _ => None,
+        }
+    }
+}
+
+/// 重複の除去の設定
+#[derive(Debug, Clone, Default)]
+pub struct DedupOptions {
+    /// 重複を判定する列（空の場合はすべての列）
+    pub keys: Vec<ColumnKey>,
+    pub keep: Keep,
+}
+
+/// 重複の除去の結果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// 書き込んだデータ行の数（ヘッダー行を除く）
+    pub rows: usize,
+    /// 取り除いた行の数
+    pub duplicates: usize,
+}
+
+/// HashMap・HashSetのキーにするフィールドの値（`typed` が真の場合は数値として比較する）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KeyValue {
+    Integer(i64),
+    /// 整数でない浮動小数点数（ビット列で比較する）
+    Float(u64),
+    String(String),
+    /// 列がない
+    Missing,
+}
+
+impl KeyValue {
+    /// フィールドをキーの値に変換する（`field` がNoneの場合は列がない）
+    pub fn from_field(field: Option<&str>, typed: bool) -> Self {
+        match field {
+            Some(field) if typed => match CsvValue::from_str(field) {
+                CsvValue::Integer(i) => KeyValue::Integer(i),
+                // 1.0 と 1 のように値が等しい数値は同じキーにする
+                CsvValue::Float(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => KeyValue::Integer(f as i64),
+                CsvValue::Float(f) => KeyValue::Float(f.to_bits()),
+                _ => KeyValue::String(field.to_string()),
+            },
+            Some(field) => KeyValue::String(field.to_string()),
+            None => KeyValue::Missing,
+        }
+    }
+}
+
+/// CSVを1行ずつ読み込み、キーの列が重複する行を取り除いて書き込む
+///
+/// `Keep::First` の場合はキーの集合だけを保持して読み込みながら書き込む。
+/// `Keep::Last` の場合は残すレコードを最後まで保持してから、元の順序で書き込む。
+///
+/// # Arguments
+/// * `input` - CSVの読み込み元
+/// * `output` - 書き込み先
+/// * `parse` - 書式・ヘッダー・trim・型変換の設定（`headers` が真の場合は1行目をヘッダーとしてそのまま書き込む）
+/// * `options` - 重複の除去の設定
+pub fn dedup_reader<R: Read, W: Write>(
+    input: R,
+    output: W,
+    parse: &CsvParseOptions,
+    options: &DedupOptions,
+) -> Result<DedupStats, CsvError> {
+    let mut writer = dialect_writer(output, &parse.dialect);
+    let mut stats = DedupStats::default();
+    // 空の入力（ヘッダー行もない）は何も書き込まない
+    let Some((mut records, header)) = RecordReader::open(input, parse)? else {
+        return Ok(stats);
+    };
+    if let Some(header) = &header {
+        writer.write_record(header)?;
+    }
+    // キーの指定がない場合はすべての列を比較する
+    let columns = if options.keys.is_empty() {
+        None
+    } else {
+        Some(resolve_columns(&options.keys, header.as_ref(), &parse.header_options, "Dedup")?)
+    };
+    let mut record = csv::StringRecord::new();
+    let mut seen = HashSet::new();
+    // `Keep::Last` で残すレコード（後から重複が見つかったものはNoneにする）とキーごとの位置
+    let mut kept: Vec<Option<csv::StringRecord>> = Vec::new();
+    let mut positions = HashMap::new();
+
+    while records.read(&mut record)?.is_some() {
+        let key = record_key(&columns, &record, parse.typed);
+        match options.keep {
+            Keep::First => {
+                if seen.insert(key) {
+                    writer.write_record(&record)?;
+                    stats.rows += 1;
+                } else {
+                    stats.duplicates += 1;
+                }
+            }
+            Keep::Last => {
+                if let Some(position) = positions.insert(key, kept.len()) {
+                    kept[position] = None;
+                    stats.duplicates += 1;
+                }
+                kept.push(Some(record.clone()));
+            }
+        }
+    }
+
+    for record in kept.iter().flatten() {
+        writer.write_record(record)?;
+        stats.rows += 1;
+    }
+
+    writer.flush().map_err(|e| CsvError::io(e.to_string()))?;
+    Ok(stats)
+}
+
+/// レコードのキーを取り出す（`columns` がNoneの場合はすべての列）
+fn record_key(columns: &Option<Vec<usize>>, record: &csv::StringRecord, typed: bool) -> Vec<KeyValue> {
+    match columns {
+        Some(columns) => columns.iter().map(|&index| KeyValue::from_field(record.get(index), typed)).collect(),
+        None => record.iter().map(|field| KeyValue::from_field(Some(field), typed)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dedup(input: &str, parse: &CsvParseOptions, options: &DedupOptions) -> (String, DedupStats) {
+        let mut output = Vec::new();
+        let stats = dedup_reader(input.as_bytes(), &mut output, parse, options).unwrap();
+        (String::from_utf8(output).unwrap(), stats)
+    }
+
+    #[test]
+    fn test_dedup_keep_first_and_last() {
+        let input = "id,name\n1,a\n2,b\n1,c\n3,d\n2,e\n";
+        let parse = CsvParseOptions { headers: true, ..Default::default() };
+        let options = DedupOptions { keys: vec![ColumnKey::Name("id".to_string())], keep: Keep::First };
+        let (output, stats) = dedup(input, &parse, &options);
+        assert_eq!(output, "id,name\n1,a\n2,b\n3,d\n");
+        assert_eq!(stats, DedupStats { rows: 3, duplicates: 2 });
+
+        let options = DedupOptions { keep: Keep::Last, ..options };
+        let (output, stats) = dedup(input, &parse, &options);
+        assert_eq!(output, "id,name\n1,c\n3,d\n2,e\n");
+        assert_eq!(stats, DedupStats { rows: 3, duplicates: 2 });
+    }
+
+    #[test]
+    fn test_dedup_typed_trimmed_and_whole_rows() {
+        let input = "1, x\n1.0,x\n01,x \n";
+        let parse = CsvParseOptions::default();
+        let (output, stats) = dedup(input, &parse, &DedupOptions::default());
+        assert_eq!(output, input);
+        assert_eq!(stats.duplicates, 0);
+
+        let parse = CsvParseOptions { typed: true, trim: crate::trim::TrimConfig::all(), ..Default::default() };
+        let (output, stats) = dedup(input, &parse, &DedupOptions::default());
+        assert_eq!(output, "1,x\n");
+        assert_eq!(stats, DedupStats { rows: 1, duplicates: 2 });
+    }
+
+    #[test]
+    fn test_dedup_unknown_column() {
+        let parse = CsvParseOptions { headers: true, ..Default::default() };
+        let options = DedupOptions { keys: vec![ColumnKey::Name("missing".to_string())], ..Default::default() };
+        let error = dedup_reader("a,b\n".as_bytes(), Vec::new(), &parse, &options).unwrap_err();
+        assert!(error.to_string().contains("Dedup column not found in headers: missing"));
+    }
+}
@@ -23,6 +23,12 @@ pub enum ErrorKind {
     WritePermission,
     // 無効なデータエラー
     InvalidData,
+    // ヘッダー行の不一致
+    HeaderMismatch,
+    // Rubyからの割り込みによる中断
+    Interrupted,
+    // オプションの指定の誤り（入力を読むまで判定できないもの。RubyではArgumentErrorにする）
+    InvalidArgument,
     // その他のエラー
     #[allow(dead_code)]
     Other,
@@ -38,6 +44,9 @@ impl fmt::Display for CsvError {
             ErrorKind::EmptyData => write!(f, "Empty Data: {}", self.message),
             ErrorKind::WritePermission => write!(f, "Write Permission Error: {}", self.message),
             ErrorKind::InvalidData => write!(f, "Invalid Data Error: {}", self.message),
+            ErrorKind::HeaderMismatch => write!(f, "Header Mismatch: {}", self.message),
+            ErrorKind::Interrupted => write!(f, "Interrupted: {}", self.message),
+            ErrorKind::InvalidArgument => write!(f, "{}", self.message),
             ErrorKind::Other => write!(f, "Error: {}", self.message),
         }
     }
@@ -53,6 +62,10 @@ impl CsvError {
         }
     }
 
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
     pub fn io(message: impl Into<String>) -> Self {
         Self::new(ErrorKind::Io, message)
     }
@@ -84,6 +97,18 @@ impl CsvError {
     pub fn invalid_data(message: impl Into<String>) -> Self {
         Self::new(ErrorKind::InvalidData, message)
     }
+
+    pub fn header_mismatch(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::HeaderMismatch, message)
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidArgument, message)
+    }
+
+    pub fn interrupted() -> Self {
+        Self::new(ErrorKind::Interrupted, "CSV processing was interrupted")
+    }
 }
 
 // csv crate error to CsvError conversion
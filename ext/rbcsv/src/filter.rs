@@ -0,0 +1,658 @@
+use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use regex::Regex;
+use crate::converter::{ColumnKey, Converter};
+use crate::error::CsvError;
+use crate::header::HeaderOptions;
+use crate::value::CsvValue;
+
+/// 比較演算子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn test(self, ordering: Option<Ordering>) -> bool {
+        match self {
+            CompareOp::Eq => ordering == Some(Ordering::Equal),
+            CompareOp::Ne => ordering != Some(Ordering::Equal),
+            CompareOp::Lt => ordering == Some(Ordering::Less),
+            CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            CompareOp::Gt => ordering == Some(Ordering::Greater),
+            CompareOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+/// 比較の対象（列または定数）
+#[derive(Debug, Clone)]
+enum Operand<C> {
+    Column(C),
+    /// 数値（`CsvValue::Integer`・`CsvValue::Float`）
+    Number(CsvValue),
+    Text(String),
+    Nil,
+}
+
+/// 式の構文木（`C` は列の指定。コンパイル時は `ColumnKey`、解決後は列番号）
+#[derive(Debug, Clone)]
+enum Expr<C> {
+    Or(Box<Expr<C>>, Box<Expr<C>>),
+    And(Box<Expr<C>>, Box<Expr<C>>),
+    Not(Box<Expr<C>>),
+    Compare(Operand<C>, CompareOp, Operand<C>),
+    /// `=~`（`negated` なら `!~`）
+    Match { operand: Operand<C>, regex: Regex, negated: bool },
+}
+
+/// 行の絞り込み条件（`where:`）
+///
+/// `status == 'active' && amount > 100` のような式をコンパイルして保持する。
+/// 列名はヘッダー行を読むまで解決しないため、`resolve` で列番号に置き換えてから評価する。
+#[derive(Debug, Clone)]
+pub struct RowFilter {
+    expr: Expr<ColumnKey>,
+    /// 先頭行がヘッダー行か（ヘッダー行は絞り込まず、列名の解決に使う）
+    pub header_row: bool,
+    /// 列名を `header_converters:` で正規化した名前でも解決する
+    pub header_options: HeaderOptions,
+}
+
+impl RowFilter {
+    /// 式をコンパイルする
+    ///
+    /// 列は名前（`amount`、空白等を含む場合は `` `unit price` ``）または `$0` 始まりの列番号で指定する。
+    /// 使用できる演算子は `==` `!=` `<` `<=` `>` `>=` `=~` `!~` `&&` `||` `!` と括弧で、
+    /// 定数は数値・文字列（`'...'`・`"..."`）・正規表現（`/.../`、フラグは `i`・`m`・`s`・`x`）・`nil`。
+    ///
+    /// # Returns
+    /// * `Result<RowFilter, String>` - 絞り込み条件または構文エラーのメッセージ
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let mut parser = Parser { lexer: Lexer::new(source) };
+        let expr = parser.parse_or()?;
+        if let Some((position, token)) = parser.lexer.next_token()? {
+            return Err(format!("unexpected {} at position {}", token.describe(), position));
+        }
+
+        Ok(Self {
+            expr,
+            header_row: false,
+            header_options: HeaderOptions::default(),
+        })
+    }
+
+    /// 列名を列番号に解決する（`headers` はヘッダー行。ヘッダーがない場合はNone）
+    pub fn resolve(&self, headers: Option<&csv::StringRecord>) -> Result<Predicate, CsvError> {
+        let resolve_column = |key: &ColumnKey| key.resolve(headers, &self.header_options, "Filter");
+
+        Ok(Predicate { expr: self.expr.map_columns(&resolve_column)? })
+    }
+}
+
+/// 列番号に解決した絞り込み条件
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    expr: Expr<usize>,
+}
+
+impl Predicate {
+    /// レコードが条件を満たすか
+    pub fn matches(&self, record: &csv::StringRecord) -> bool {
+        self.expr.eval(record)
+    }
+
+    /// コンバーターで変換した値でレコードが条件を満たすか（`typed` の読み込み用）
+    ///
+    /// コンバーターの対象列は `typed_record` と同じく変換した値で評価する。
+    /// Procのコンバーターは変換前の値で評価する（Rubyの呼び出しは絞り込みの後に行うため）。
+    pub fn matches_converted<C: Borrow<Converter>>(
+        &self,
+        record: &csv::StringRecord,
+        column_converters: &HashMap<usize, C>,
+        trim_fields: bool,
+    ) -> bool {
+        if column_converters.is_empty() {
+            return self.expr.eval(record);
+        }
+        self.expr.eval(&ConvertedRow { record, column_converters, trim_fields })
+    }
+}
+
+/// 読み込みのループで行を絞り込む
+///
+/// 先頭行で列名を解決し（ヘッダー行は常に残す）、以降の行を条件で評価する。
+/// 条件がない場合はすべての行を残す。
+#[derive(Debug, Clone, Default)]
+pub struct RowSelector {
+    filter: Option<RowFilter>,
+    predicate: Option<Predicate>,
+}
+
+impl RowSelector {
+    pub fn new(filter: Option<RowFilter>) -> Self {
+        Self { filter, predicate: None }
+    }
+
+    /// 行を残すか（`line_num` は0始まりの行番号）
+    pub fn select(&mut self, line_num: usize, record: &csv::StringRecord) -> Result<bool, CsvError> {
+        if self.keeps_unfiltered(line_num, record)? {
+            return Ok(true);
+        }
+        Ok(self.predicate.as_ref().is_none_or(|predicate| predicate.matches(record)))
+    }
+
+    /// コンバーターで変換した値で行を残すか（`Predicate::matches_converted` を参照）
+    pub fn select_converted<C: Borrow<Converter>>(
+        &mut self,
+        line_num: usize,
+        record: &csv::StringRecord,
+        column_converters: &HashMap<usize, C>,
+        trim_fields: bool,
+    ) -> Result<bool, CsvError> {
+        if self.keeps_unfiltered(line_num, record)? {
+            return Ok(true);
+        }
+        Ok(self
+            .predicate
+            .as_ref()
+            .is_none_or(|predicate| predicate.matches_converted(record, column_converters, trim_fields)))
+    }
+
+    /// 先頭行で列名を解決する。条件で評価せずに残す行（条件がない場合・ヘッダー行）なら真を返す
+    fn keeps_unfiltered(&mut self, line_num: usize, record: &csv::StringRecord) -> Result<bool, CsvError> {
+        let Some(filter) = &self.filter else {
+            return Ok(true);
+        };
+        if line_num != 0 {
+            return Ok(false);
+        }
+        self.predicate = Some(filter.resolve(filter.header_row.then_some(record))?);
+        Ok(filter.header_row)
+    }
+
+    /// 解決済みの条件（並列パースで各スレッドに渡す）
+    pub fn predicate(&self) -> Option<&Predicate> {
+        self.predicate.as_ref()
+    }
+}
+
+impl Expr<ColumnKey> {
+    fn map_columns(&self, resolve: &impl Fn(&ColumnKey) -> Result<usize, CsvError>) -> Result<Expr<usize>, CsvError> {
+        let operand = |operand: &Operand<ColumnKey>| -> Result<Operand<usize>, CsvError> {
+            Ok(match operand {
+                Operand::Column(key) => Operand::Column(resolve(key)?),
+                Operand::Number(value) => Operand::Number(value.clone()),
+                Operand::Text(text) => Operand::Text(text.clone()),
+                Operand::Nil => Operand::Nil,
+            })
+        };
+
+        Ok(match self {
+            Expr::Or(left, right) => Expr::Or(Box::new(left.map_columns(resolve)?), Box::new(right.map_columns(resolve)?)),
+            Expr::And(left, right) => Expr::And(Box::new(left.map_columns(resolve)?), Box::new(right.map_columns(resolve)?)),
+            Expr::Not(inner) => Expr::Not(Box::new(inner.map_columns(resolve)?)),
+            Expr::Compare(left, op, right) => Expr::Compare(operand(left)?, *op, operand(right)?),
+            Expr::Match { operand: target, regex, negated } => Expr::Match {
+                operand: operand(target)?,
+                regex: regex.clone(),
+                negated: *negated,
+            },
+        })
+    }
+}
+
+/// 評価中の値
+enum Value<'a> {
+    Nil,
+    /// 数値の定数またはコンバーターで数値に変換したフィールド
+    Number(CsvValue),
+    Text(&'a str),
+    /// 空でないフィールド
+    Field(Cow<'a, str>),
+}
+
+/// 条件を評価する行（列番号から値を取り出す）
+trait Row {
+    /// 列の値（空のフィールドと存在しない列はnil）
+    fn column(&self, index: usize) -> Value<'_>;
+}
+
+impl Row for csv::StringRecord {
+    fn column(&self, index: usize) -> Value<'_> {
+        match self.get(index) {
+            Some("") | None => Value::Nil,
+            Some(field) => Value::Field(Cow::Borrowed(field)),
+        }
+    }
+}
+
+/// コンバーターの対象列を変換した値で評価する行
+struct ConvertedRow<'a, C> {
+    record: &'a csv::StringRecord,
+    column_converters: &'a HashMap<usize, C>,
+    trim_fields: bool,
+}
+
+impl<C: Borrow<Converter>> Row for ConvertedRow<'_, C> {
+    fn column(&self, index: usize) -> Value<'_> {
+        let (Some(converter), Some(field)) = (self.column_converters.get(&index), self.record.get(index)) else {
+            return self.record.column(index);
+        };
+        let field = if self.trim_fields { field.trim() } else { field };
+        match converter.borrow().convert(field) {
+            number @ (CsvValue::Integer(_) | CsvValue::Float(_)) => Value::Number(number),
+            CsvValue::Deferred { .. } => self.record.column(index),
+            value => match value.to_string() {
+                text if text.is_empty() => Value::Nil,
+                text => Value::Field(Cow::Owned(text)),
+            },
+        }
+    }
+}
+
+impl Expr<usize> {
+    fn eval(&self, row: &impl Row) -> bool {
+        match self {
+            Expr::Or(left, right) => left.eval(row) || right.eval(row),
+            Expr::And(left, right) => left.eval(row) && right.eval(row),
+            Expr::Not(inner) => !inner.eval(row),
+            Expr::Compare(left, op, right) => op.test(compare(&value(left, row), &value(right, row))),
+            Expr::Match { operand, regex, negated } => {
+                let matched = match value(operand, row) {
+                    Value::Nil | Value::Number(_) => false,
+                    Value::Text(text) => regex.is_match(text),
+                    Value::Field(text) => regex.is_match(&text),
+                };
+                matched != *negated
+            }
+        }
+    }
+}
+
+/// 比較の対象の値を取り出す
+fn value<'a>(operand: &'a Operand<usize>, row: &'a impl Row) -> Value<'a> {
+    match operand {
+        Operand::Column(index) => row.column(*index),
+        Operand::Number(number) => Value::Number(number.clone()),
+        Operand::Text(text) => Value::Text(text),
+        Operand::Nil => Value::Nil,
+    }
+}
+
+/// 2つの値を比較する（比較できない組み合わせはNone）
+///
+/// 文字列の定数との比較はフィールドを文字列として比較し、それ以外は `CsvValue::from_str` で
+/// 型認識した数値どうしで比較する（数値でないフィールドどうしは文字列として比較する）。
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Nil, Value::Nil) => Some(Ordering::Equal),
+        (Value::Nil, _) | (_, Value::Nil) => None,
+        (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+        (Value::Text(a), Value::Field(b)) => Some((*a).cmp(b.as_ref())),
+        (Value::Field(a), Value::Text(b)) => Some(a.as_ref().cmp(*b)),
+        (Value::Text(_), Value::Number(_)) | (Value::Number(_), Value::Text(_)) => None,
+        (Value::Field(a), Value::Field(b)) => match (CsvValue::from_str(a), CsvValue::from_str(b)) {
+            (a_value @ (CsvValue::Integer(_) | CsvValue::Float(_)), b_value @ (CsvValue::Integer(_) | CsvValue::Float(_))) => {
+                compare_numbers(&a_value, &b_value)
+            }
+            _ => Some(a.cmp(b)),
+        },
+        (Value::Field(a), Value::Number(b)) => compare_numbers(&CsvValue::from_str(a), b),
+        (Value::Number(a), Value::Field(b)) => compare_numbers(a, &CsvValue::from_str(b)),
+        (Value::Number(a), Value::Number(b)) => compare_numbers(a, b),
+    }
+}
+
+/// 数値どうしを比較する（整数どうしは精度を落とさずに比較する）
+fn compare_numbers(left: &CsvValue, right: &CsvValue) -> Option<Ordering> {
+    match (left, right) {
+        (CsvValue::Integer(a), CsvValue::Integer(b)) => Some(a.cmp(b)),
+        (CsvValue::Integer(a), CsvValue::Float(b)) => (*a as f64).partial_cmp(b),
+        (CsvValue::Float(a), CsvValue::Integer(b)) => a.partial_cmp(&(*b as f64)),
+        (CsvValue::Float(a), CsvValue::Float(b)) => a.partial_cmp(b),
+        _ => None,
+    }
+}
+
+/// 字句
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Column(ColumnKey),
+    Number(CsvValue),
+    Text(String),
+    Regex(String),
+    Nil,
+    Compare(CompareOp),
+    Match(bool),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Column(ColumnKey::Name(name)) => format!("column {}", name),
+            Token::Column(ColumnKey::Index(index)) => format!("column ${}", index),
+            Token::Number(number) => format!("number {}", number),
+            Token::Text(text) => format!("string {:?}", text),
+            Token::Regex(pattern) => format!("regex /{}/", pattern),
+            Token::Nil => "nil".to_string(),
+            Token::Compare(_) | Token::Match(_) => "comparison operator".to_string(),
+            Token::And => "&&".to_string(),
+            Token::Or => "||".to_string(),
+            Token::Not => "!".to_string(),
+            Token::LParen => "(".to_string(),
+            Token::RParen => ")".to_string(),
+        }
+    }
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// 先読みした字句
+    peeked: Option<Option<(usize, Token)>>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { source, chars: source.char_indices().peekable(), peeked: None }
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, String> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.read_token()?);
+        }
+        Ok(self.peeked.as_ref().and_then(|token| token.as_ref().map(|(_, token)| token)))
+    }
+
+    fn next_token(&mut self) -> Result<Option<(usize, Token)>, String> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.read_token(),
+        }
+    }
+
+    fn read_token(&mut self) -> Result<Option<(usize, Token)>, String> {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let Some((start, c)) = self.chars.next() else {
+            return Ok(None);
+        };
+
+        let token = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '&' if self.eat('&') => Token::And,
+            '|' if self.eat('|') => Token::Or,
+            '=' if self.eat('=') => Token::Compare(CompareOp::Eq),
+            '=' if self.eat('~') => Token::Match(false),
+            '!' if self.eat('=') => Token::Compare(CompareOp::Ne),
+            '!' if self.eat('~') => Token::Match(true),
+            '!' => Token::Not,
+            '<' if self.eat('=') => Token::Compare(CompareOp::Le),
+            '<' => Token::Compare(CompareOp::Lt),
+            '>' if self.eat('=') => Token::Compare(CompareOp::Ge),
+            '>' => Token::Compare(CompareOp::Gt),
+            '\'' | '"' => Token::Text(self.quoted(start, c)?),
+            '`' => Token::Column(ColumnKey::Name(self.quoted(start, c)?)),
+            '/' => {
+                let pattern = self.quoted(start, c)?;
+                // 閉じ `/` の後のフラグ（`/abc/i` 等）はインラインフラグにする
+                let end = self.position();
+                match self.take_while(end, |c| c.is_ascii_alphabetic()) {
+                    "" => Token::Regex(pattern),
+                    flags if flags.chars().all(|flag| matches!(flag, 'i' | 'm' | 's' | 'x')) => {
+                        Token::Regex(format!("(?{}){}", flags, pattern))
+                    }
+                    flags => return Err(format!("unknown regex flags {} at position {}", flags, start)),
+                }
+            }
+            '$' => {
+                let digits = self.take_while(start + 1, |c| c.is_ascii_digit());
+                let index = digits.parse().map_err(|_| format!("expected a column number after $ at position {}", start))?;
+                Token::Column(ColumnKey::Index(index))
+            }
+            c if c.is_ascii_digit() || (matches!(c, '-' | '.') && self.chars.peek().is_some_and(|(_, c)| c.is_ascii_digit())) => {
+                let end = self.take_while(start + c.len_utf8(), |c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-')).len();
+                let text = &self.source[start..start + c.len_utf8() + end];
+                match CsvValue::from_str(text) {
+                    number @ (CsvValue::Integer(_) | CsvValue::Float(_)) => Token::Number(number),
+                    _ => return Err(format!("invalid number {} at position {}", text, start)),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let rest = self.take_while(start + c.len_utf8(), |c| c.is_alphanumeric() || c == '_');
+                let name = &self.source[start..start + c.len_utf8() + rest.len()];
+                match name {
+                    "nil" => Token::Nil,
+                    _ => Token::Column(ColumnKey::Name(name.to_string())),
+                }
+            }
+            c => return Err(format!("unexpected character {:?} at position {}", c, start)),
+        };
+
+        Ok(Some((start, token)))
+    }
+
+    /// 次に読む文字の位置
+    fn position(&mut self) -> usize {
+        self.chars.peek().map_or(self.source.len(), |&(position, _)| position)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.chars.next_if(|&(_, c)| c == expected).is_some()
+    }
+
+    /// 条件を満たす間読み進め、読んだ部分を返す（`from` は読み始める位置）
+    fn take_while(&mut self, from: usize, predicate: impl Fn(char) -> bool) -> &'a str {
+        let mut end = from;
+        while let Some((position, c)) = self.chars.next_if(|&(_, c)| predicate(c)) {
+            end = position + c.len_utf8();
+        }
+        &self.source[from..end]
+    }
+
+    /// `quote` で閉じるまで読む（`\` の直後の文字はそのまま取り込む。正規表現ではエスケープを残す）
+    fn quoted(&mut self, start: usize, quote: char) -> Result<String, String> {
+        let mut text = String::new();
+        while let Some((_, c)) = self.chars.next() {
+            match c {
+                c if c == quote => return Ok(text),
+                '\\' => {
+                    let Some((_, escaped)) = self.chars.next() else { break };
+                    if quote == '/' && escaped != '/' {
+                        text.push('\\');
+                    }
+                    text.push(escaped);
+                }
+                c => text.push(c),
+            }
+        }
+        Err(format!("unterminated {} at position {}", quote, start))
+    }
+}
+
+/// 再帰下降パーサー（優先順位は `!` > 比較 > `&&` > `||`）
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+}
+
+impl Parser<'_> {
+    fn parse_or(&mut self) -> Result<Expr<ColumnKey>, String> {
+        let mut expr = self.parse_and()?;
+        while self.lexer.peek()? == Some(&Token::Or) {
+            self.lexer.next_token()?;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr<ColumnKey>, String> {
+        let mut expr = self.parse_unary()?;
+        while self.lexer.peek()? == Some(&Token::And) {
+            self.lexer.next_token()?;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr<ColumnKey>, String> {
+        match self.lexer.peek()? {
+            Some(Token::Not) => {
+                self.lexer.next_token()?;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.lexer.next_token()?;
+                let expr = self.parse_or()?;
+                match self.lexer.next_token()? {
+                    Some((_, Token::RParen)) => Ok(expr),
+                    Some((position, token)) => Err(format!("expected ) but found {} at position {}", token.describe(), position)),
+                    None => Err("expected ) but reached the end".to_string()),
+                }
+            }
+            _ => self.parse_comparison(),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr<ColumnKey>, String> {
+        let left = self.parse_operand()?;
+        match self.lexer.next_token()? {
+            Some((_, Token::Compare(op))) => Ok(Expr::Compare(left, op, self.parse_operand()?)),
+            Some((_, Token::Match(negated))) => match self.lexer.next_token()? {
+                Some((position, Token::Regex(pattern) | Token::Text(pattern))) => {
+                    let regex = Regex::new(&pattern).map_err(|e| format!("invalid regex at position {}: {}", position, e))?;
+                    Ok(Expr::Match { operand: left, regex, negated })
+                }
+                Some((position, token)) => Err(format!("expected a regex but found {} at position {}", token.describe(), position)),
+                None => Err("expected a regex but reached the end".to_string()),
+            },
+            Some((position, token)) => Err(format!("expected a comparison operator but found {} at position {}", token.describe(), position)),
+            None => Err("expected a comparison operator but reached the end".to_string()),
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand<ColumnKey>, String> {
+        match self.lexer.next_token()? {
+            Some((_, Token::Column(key))) => Ok(Operand::Column(key)),
+            Some((_, Token::Number(number))) => Ok(Operand::Number(number)),
+            Some((_, Token::Text(text))) => Ok(Operand::Text(text)),
+            Some((_, Token::Nil)) => Ok(Operand::Nil),
+            Some((position, token)) => Err(format!("expected a column or a value but found {} at position {}", token.describe(), position)),
+            None => Err("expected a column or a value but reached the end".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fields: &[&str]) -> csv::StringRecord {
+        csv::StringRecord::from(fields.to_vec())
+    }
+
+    fn matches(source: &str, row: &[&str]) -> bool {
+        let headers = record(&["status", "amount", "name", "unit price"]);
+        RowFilter::compile(source).unwrap().resolve(Some(&headers)).unwrap().matches(&record(row))
+    }
+
+    #[test]
+    fn test_compare_typed_fields() {
+        let row = ["active", "150", "Alice", "9.5"];
+        assert!(matches("status == 'active' && amount > 100", &row));
+        assert!(!matches("status == 'active' && amount > 200", &row));
+        assert!(matches("amount >= 150 && `unit price` < 10", &row));
+        // 数値として比較する（文字列の順序では "150" < "20"）
+        assert!(matches("amount > 20", &row));
+        assert!(matches("amount == 150.0", &row));
+        // 文字列の定数とはフィールドの文字列で比較する
+        assert!(!matches("amount == '150.0'", &row));
+        assert!(matches("$2 == \"Alice\" || $0 == 'x'", &row));
+        assert!(matches("!(status != 'active') && name =~ /^al/i", &["active", "1", "alice", ""]));
+        assert!(matches("name !~ /b/", &row));
+    }
+
+    #[test]
+    fn test_nil_and_missing_fields() {
+        assert!(matches("name == nil", &["active", "1", "", ""]));
+        assert!(!matches("amount > 0", &["active", "", "x", ""]));
+        assert!(matches("amount != 0", &["active", "", "x", ""]));
+        assert!(!matches("amount > 0", &["active", "abc", "x", ""]));
+        assert!(matches("`unit price` == nil", &["active", "1"]));
+    }
+
+    #[test]
+    fn test_matches_converted_values() {
+        let headers = record(&["status", "amount", "name", "unit price"]);
+        let predicate = RowFilter::compile("amount > 100 && name == 'ALICE'").unwrap().resolve(Some(&headers)).unwrap();
+        let row = record(&["active", "$150", "ALICE", ""]);
+        let mut converters = HashMap::new();
+        converters.insert(1, Converter::StripPrefix("$".to_string()));
+        assert!(!predicate.matches(&row));
+        assert!(predicate.matches_converted(&row, &converters, false));
+
+        // Procのコンバーターは変換前の値で評価する
+        converters.insert(2, Converter::Custom(0));
+        assert!(predicate.matches_converted(&row, &converters, false));
+        let mut table = HashMap::new();
+        table.insert("ALICE".to_string(), CsvValue::Nil);
+        converters.insert(2, Converter::Lookup(table));
+        assert!(!predicate.matches_converted(&row, &converters, false));
+    }
+
+    #[test]
+    fn test_precedence() {
+        // `&&` は `||` より優先される
+        assert!(matches("status == 'x' && amount > 0 || name == 'Bob'", &["active", "1", "Bob", ""]));
+        assert!(!matches("status == 'x' && (amount > 0 || name == 'Bob')", &["active", "1", "Bob", ""]));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(RowFilter::compile("amount >").unwrap_err().contains("reached the end"));
+        assert!(RowFilter::compile("amount 100").unwrap_err().contains("position 7"));
+        assert!(RowFilter::compile("name == 'x").unwrap_err().contains("unterminated"));
+        assert!(RowFilter::compile("(a == 1").unwrap_err().contains("expected )"));
+        assert!(RowFilter::compile("a == 1 b").is_err());
+        assert!(RowFilter::compile("a =~ /(/").unwrap_err().contains("invalid regex"));
+        assert!(RowFilter::compile("a ? 1").unwrap_err().contains("unexpected character"));
+        assert!(RowFilter::compile("a =~ /x/q").unwrap_err().contains("unknown regex flags"));
+    }
+
+    #[test]
+    fn test_resolve_columns() {
+        let filter = RowFilter::compile("missing == 1").unwrap();
+        let err = filter.resolve(Some(&record(&["a"]))).unwrap_err();
+        assert!(err.to_string().contains("Filter column not found in headers: missing"));
+        assert!(filter.resolve(None).is_err());
+        assert!(RowFilter::compile("$1 == 1").unwrap().resolve(None).is_ok());
+    }
+
+    #[test]
+    fn test_row_selector() {
+        let mut filter = RowFilter::compile("n > 1").unwrap();
+        filter.header_row = true;
+        let mut selector = RowSelector::new(Some(filter));
+        assert!(selector.select(0, &record(&["n"])).unwrap());
+        assert!(!selector.select(1, &record(&["1"])).unwrap());
+        assert!(selector.select(2, &record(&["2"])).unwrap());
+
+        let mut selector = RowSelector::new(Some(RowFilter::compile("$0 > 1").unwrap()));
+        assert!(!selector.select(0, &record(&["1"])).unwrap());
+        assert!(selector.select(1, &record(&["2"])).unwrap());
+        assert!(RowSelector::default().select(0, &record(&["x"])).unwrap());
+    }
+}
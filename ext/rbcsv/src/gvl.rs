@@ -0,0 +1,137 @@
+use magnus::{Error as MagnusError, Ruby};
+use std::cell::Cell;
+use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::error::CsvError;
+use crate::ruby_api::csv_error_to_ruby;
+
+thread_local! {
+    /// `without_gvl` 実行中の中断フラグ（実行中でなければnull）
+    static INTERRUPT: Cell<*const AtomicBool> = const { Cell::new(ptr::null()) };
+}
+
+/// 中断が要求されていればエラーを返す
+///
+/// パース処理のループから定期的に呼び出す。`without_gvl` の外では常にOkを返す。
+pub fn check_interrupt() -> Result<(), CsvError> {
+    let interrupted = INTERRUPT.with(|flag| {
+        let flag = flag.get();
+        // 中断フラグは `without_gvl` のスタック上にあり、実行中は有効
+        !flag.is_null() && unsafe { (*flag).load(Ordering::Relaxed) }
+    });
+
+    if interrupted {
+        return Err(CsvError::interrupted());
+    }
+    Ok(())
+}
+
+/// 中断フラグを別スレッドに引き継ぐためのハンドル
+///
+/// `without_gvl` の中で起動したワーカースレッドからも `check_interrupt` を使えるようにする。
+#[derive(Clone, Copy)]
+pub struct InterruptHandle(*const AtomicBool);
+
+// フラグは `without_gvl` が戻るまで有効で、ワーカースレッドはそれまでに終了する（スコープ付きスレッドで使う）
+unsafe impl Send for InterruptHandle {}
+unsafe impl Sync for InterruptHandle {}
+
+impl InterruptHandle {
+    /// 現在のスレッドの中断フラグを取得する
+    pub fn current() -> Self {
+        Self(INTERRUPT.with(|flag| flag.get()))
+    }
+
+    /// 中断フラグを現在のスレッドに設定して処理を実行する
+    pub fn enter<T>(self, func: impl FnOnce() -> T) -> T {
+        let previous = INTERRUPT.with(|flag| flag.replace(self.0));
+        let result = func();
+        INTERRUPT.with(|flag| flag.set(previous));
+        result
+    }
+}
+
+struct Call<F, T> {
+    func: Option<F>,
+    result: Option<std::thread::Result<T>>,
+    interrupt: *const AtomicBool,
+}
+
+unsafe extern "C" fn trampoline<F, T>(data: *mut c_void) -> *mut c_void
+where
+    F: FnOnce() -> T,
+{
+    let call = &mut *(data as *mut Call<F, T>);
+    let func = call.func.take().expect("called once");
+
+    // パニックをCの関数境界の外に出さないよう、GVLを再取得してから再開する
+    call.result = Some(InterruptHandle(call.interrupt).enter(|| panic::catch_unwind(AssertUnwindSafe(func))));
+
+    ptr::null_mut()
+}
+
+/// Thread#kill や Timeout 等でRubyから中断を要求されたときに、別スレッドから呼び出される
+unsafe extern "C" fn unblock(data: *mut c_void) {
+    (*(data as *const AtomicBool)).store(true, Ordering::Relaxed);
+}
+
+/// GVLを解放して処理を実行する
+///
+/// 処理の間は他のRubyスレッドが動けるため、マルチスレッドのサーバーでも並行してパースできる。
+/// 処理の中ではRubyのオブジェクトやAPIを使ってはいけない（結果のRubyオブジェクトへの変換はGVLの再取得後に行う）。
+/// Rubyから中断が要求された場合は `check_interrupt` がエラーを返すようになり、GVLの再取得後に
+/// 保留中の割り込み（例外）を発生させる。
+pub fn without_gvl<F, T>(ruby: &Ruby, func: F) -> Result<T, MagnusError>
+where
+    F: FnOnce() -> Result<T, CsvError>,
+{
+    let interrupt = AtomicBool::new(false);
+    let mut call: Call<F, Result<T, CsvError>> = Call {
+        func: Some(func),
+        result: None,
+        interrupt: &interrupt,
+    };
+
+    unsafe {
+        rb_sys::rb_thread_call_without_gvl(
+            Some(trampoline::<F, Result<T, CsvError>>),
+            &mut call as *mut Call<F, Result<T, CsvError>> as *mut c_void,
+            Some(unblock),
+            &interrupt as *const AtomicBool as *mut c_void,
+        );
+    }
+
+    let result: Result<T, CsvError> = match call.result.take() {
+        Some(Ok(result)) => result,
+        Some(Err(panic)) => panic::resume_unwind(panic),
+        None => unreachable!("rb_thread_call_without_gvl returned without calling the function"),
+    };
+
+    if interrupt.load(Ordering::Relaxed) {
+        // 保留中の割り込みがあればここで例外になる
+        ruby.thread_check_ints()?;
+    }
+
+    result.map_err(|e| csv_error_to_ruby(ruby, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_check_interrupt() {
+        assert!(check_interrupt().is_ok());
+
+        let interrupt = AtomicBool::new(true);
+        INTERRUPT.with(|flag| flag.set(&interrupt));
+        let result = check_interrupt();
+        INTERRUPT.with(|flag| flag.set(ptr::null()));
+
+        assert!(matches!(result.unwrap_err().kind(), ErrorKind::Interrupted));
+        assert!(check_interrupt().is_ok());
+    }
+}
@@ -0,0 +1,302 @@
+use crate::error::CsvError;
+
+/// ヘッダー名の正規化方法
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeaderConverter {
+    /// 前後の空白を取り除く
+    Strip,
+    /// 小文字にする
+    Downcase,
+    /// snake_caseにする（"First Name" → "first_name", "OrderID" → "order_id"）
+    SnakeCase,
+    /// Symbolのキーにする（名前自体は変えない）
+    Symbolize,
+}
+
+impl HeaderConverter {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "strip" => Some(HeaderConverter::Strip),
+            "downcase" => Some(HeaderConverter::Downcase),
+            "snake_case" => Some(HeaderConverter::SnakeCase),
+            "symbolize" => Some(HeaderConverter::Symbolize),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, header: &str) -> String {
+        match self {
+            HeaderConverter::Strip => header.trim().to_string(),
+            HeaderConverter::Downcase => header.to_lowercase(),
+            HeaderConverter::SnakeCase => to_snake_case(header),
+            HeaderConverter::Symbolize => header.to_string(),
+        }
+    }
+}
+
+/// 重複したヘッダーの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DuplicateHeaders {
+    /// エラーにする
+    #[default]
+    Raise,
+    /// 2つ目以降に連番を付ける（amount, amount_2, amount_3）
+    Suffix,
+    /// 同名の列の値を配列にまとめる
+    Array,
+}
+
+impl DuplicateHeaders {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "raise" => Some(DuplicateHeaders::Raise),
+            "suffix" => Some(DuplicateHeaders::Suffix),
+            "array" => Some(DuplicateHeaders::Array),
+            _ => None,
+        }
+    }
+}
+
+/// ヘッダー処理の設定
+#[derive(Debug, Clone, Default)]
+pub struct HeaderOptions {
+    pub converters: Vec<HeaderConverter>,
+    pub duplicates: DuplicateHeaders,
+}
+
+/// 処理済みのヘッダー
+#[derive(Debug, Clone, PartialEq)]
+pub struct Headers {
+    /// 重複を解消したヘッダー名
+    pub names: Vec<String>,
+    /// 列番号ごとの `names` のインデックス
+    pub slots: Vec<usize>,
+    /// `names` ごとに複数の列をまとめているか（`DuplicateHeaders::Array` の場合のみtrueになり得る）
+    pub grouped: Vec<bool>,
+    /// キーをSymbolにするか
+    pub symbolize: bool,
+}
+
+impl HeaderOptions {
+    /// ヘッダー名を正規化する（登録順に適用）
+    pub fn normalize(&self, header: &str) -> String {
+        self.converters
+            .iter()
+            .fold(header.to_string(), |header, converter| converter.apply(&header))
+    }
+
+    /// ヘッダー行を正規化し、重複を解消する
+    pub fn process<S: AsRef<str>>(&self, raw_headers: &[S]) -> Result<Headers, CsvError> {
+        let mut names: Vec<String> = Vec::with_capacity(raw_headers.len());
+        let mut slots = Vec::with_capacity(raw_headers.len());
+        let mut grouped = Vec::with_capacity(raw_headers.len());
+
+        for raw in raw_headers {
+            let name = self.normalize(raw.as_ref());
+
+            let Some(existing) = names.iter().position(|n| *n == name) else {
+                slots.push(names.len());
+                names.push(name);
+                grouped.push(false);
+                continue;
+            };
+
+            match self.duplicates {
+                DuplicateHeaders::Raise => {
+                    return Err(CsvError::invalid_data(format!("Duplicate header: {}", name)));
+                }
+                DuplicateHeaders::Suffix => {
+                    let mut counter = 2;
+                    let mut candidate = format!("{}_{}", name, counter);
+                    while names.contains(&candidate) {
+                        counter += 1;
+                        candidate = format!("{}_{}", name, counter);
+                    }
+                    slots.push(names.len());
+                    names.push(candidate);
+                    grouped.push(false);
+                }
+                DuplicateHeaders::Array => {
+                    slots.push(existing);
+                    grouped[existing] = true;
+                }
+            }
+        }
+
+        Ok(Headers {
+            names,
+            slots,
+            grouped,
+            symbolize: self.converters.contains(&HeaderConverter::Symbolize),
+        })
+    }
+}
+
+/// ヘッダー行の検証設定
+#[derive(Debug, Clone, Default)]
+pub struct HeaderValidation {
+    /// 必須のヘッダー名
+    pub required: Vec<String>,
+    /// 真の場合、ヘッダー行が `required` と順序も含めて完全に一致することを求める
+    pub strict: bool,
+    /// 比較前に適用する正規化
+    pub options: HeaderOptions,
+}
+
+impl HeaderValidation {
+    /// ヘッダー行を検証し、不足・余分・順序違いの列をまとめてエラーにする
+    pub fn validate<S: AsRef<str>>(&self, raw_headers: &[S]) -> Result<(), CsvError> {
+        let actual: Vec<String> = raw_headers
+            .iter()
+            .map(|header| self.options.normalize(header.as_ref()))
+            .collect();
+
+        let missing: Vec<&str> = self
+            .required
+            .iter()
+            .filter(|name| !actual.contains(name))
+            .map(String::as_str)
+            .collect();
+
+        let (unexpected, reordered) = if self.strict {
+            let unexpected: Vec<&str> = actual
+                .iter()
+                .filter(|name| !self.required.contains(name))
+                .map(String::as_str)
+                .collect();
+
+            // 両方に存在する列を実際の並びと期待する並びで比較する
+            let expected_order: Vec<&String> = self.required.iter().filter(|name| actual.contains(name)).collect();
+            let actual_order: Vec<&String> = actual.iter().filter(|name| self.required.contains(name)).collect();
+            let reordered: Vec<&str> = actual_order
+                .iter()
+                .zip(&expected_order)
+                .filter(|(actual, expected)| actual != expected)
+                .map(|(actual, _)| actual.as_str())
+                .collect();
+
+            (unexpected, reordered)
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        if missing.is_empty() && unexpected.is_empty() && reordered.is_empty() {
+            return Ok(());
+        }
+
+        let mut details = Vec::new();
+        if !missing.is_empty() {
+            details.push(format!("missing [{}]", missing.join(", ")));
+        }
+        if !unexpected.is_empty() {
+            details.push(format!("unexpected [{}]", unexpected.join(", ")));
+        }
+        if !reordered.is_empty() {
+            details.push(format!("reordered [{}]", reordered.join(", ")));
+        }
+
+        Err(CsvError::header_mismatch(details.join("; ")))
+    }
+}
+
+/// 文字列をsnake_caseに変換する
+fn to_snake_case(s: &str) -> String {
+    let chars: Vec<char> = s.trim().chars().collect();
+    let mut result = String::with_capacity(s.len() + 4);
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !result.is_empty() && !result.ends_with('_') {
+                result.push('_');
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && i > 0 && !result.is_empty() && !result.ends_with('_') {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            // "orderId" や "OrderID" の単語境界、"HTTPServer" の略語の終わりで区切る
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lower) {
+                result.push('_');
+            }
+        }
+
+        result.extend(c.to_lowercase());
+    }
+
+    result.trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_snake_case() {
+        assert_eq!(to_snake_case("First Name"), "first_name");
+        assert_eq!(to_snake_case("OrderID"), "order_id");
+        assert_eq!(to_snake_case("HTTPServer"), "http_server");
+        assert_eq!(to_snake_case("amount-usd"), "amount_usd");
+        assert_eq!(to_snake_case(" Total (JPY) "), "total_jpy");
+    }
+
+    #[test]
+    fn test_normalize_applies_in_order() {
+        let options = HeaderOptions {
+            converters: vec![HeaderConverter::Strip, HeaderConverter::Downcase],
+            duplicates: DuplicateHeaders::Raise,
+        };
+        assert_eq!(options.normalize("  Amount "), "amount");
+    }
+
+    #[test]
+    fn test_header_validation_required() {
+        let validation = HeaderValidation {
+            required: vec!["id".to_string(), "amount".to_string()],
+            ..Default::default()
+        };
+        assert!(validation.validate(&["amount", "id", "note"]).is_ok());
+
+        let err = validation.validate(&["id", "total"]).unwrap_err();
+        assert!(err.to_string().contains("missing [amount]"));
+    }
+
+    #[test]
+    fn test_header_validation_strict() {
+        let validation = HeaderValidation {
+            required: vec!["id".to_string(), "name".to_string(), "amount".to_string()],
+            strict: true,
+            options: HeaderOptions {
+                converters: vec![HeaderConverter::Downcase],
+                duplicates: DuplicateHeaders::Raise,
+            },
+        };
+        assert!(validation.validate(&["ID", "Name", "Amount"]).is_ok());
+
+        let err = validation.validate(&["name", "id", "note"]).unwrap_err().to_string();
+        assert!(err.contains("missing [amount]"));
+        assert!(err.contains("unexpected [note]"));
+        assert!(err.contains("reordered [name, id]"));
+    }
+
+    #[test]
+    fn test_duplicate_headers() {
+        let raw = ["id", "Amount", "amount "];
+        let mut options = HeaderOptions {
+            converters: vec![HeaderConverter::Strip, HeaderConverter::Downcase],
+            duplicates: DuplicateHeaders::Raise,
+        };
+        assert!(options.process(&raw).unwrap_err().to_string().contains("Duplicate header: amount"));
+
+        options.duplicates = DuplicateHeaders::Suffix;
+        let headers = options.process(&raw).unwrap();
+        assert_eq!(headers.names, vec!["id", "amount", "amount_2"]);
+        assert_eq!(headers.slots, vec![0, 1, 2]);
+
+        options.duplicates = DuplicateHeaders::Array;
+        let headers = options.process(&raw).unwrap();
+        assert_eq!(headers.names, vec!["id", "amount"]);
+        assert_eq!(headers.slots, vec![0, 1, 1]);
+        assert_eq!(headers.grouped, vec![false, true]);
+    }
+}
@@ -1,26 +1,101 @@
+mod aggregate;
+mod compression;
+mod converter;
+mod dedup;
 mod error;
+mod filter;
+mod gvl;
+mod header;
+mod options;
+mod parallel;
 mod parser;
+mod profile;
+mod reader;
+mod rows;
 mod ruby_api;
+mod ruby_io;
+mod schema;
+mod sort;
+mod source;
+mod table;
+mod trim;
+mod validator;
 mod value;
+mod writer;
 
-use magnus::{Object, Ruby};
-use ruby_api::{parse, parse_trim, read, read_trim, write, parse_typed, parse_typed_trim, read_typed, read_typed_trim};
+use magnus::{Module, Object, Ruby};
+use reader::Reader;
+use table::Table;
+use writer::Writer;
+use ruby_api::{parse, parse_trim, read, read_trim, write, parse_typed, parse_typed_trim, read_typed, read_typed_trim, validate, validate_file, foreach, each_slice, profile, sort, dedup, aggregate};
 
 #[magnus::init]
 fn init(ruby: &Ruby) -> Result<(), magnus::Error> {
     let module = ruby.define_module("RbCsv")?;
 
-    module.define_singleton_method("parse", magnus::function!(parse, 1))?;
-    module.define_singleton_method("parse!", magnus::function!(parse_trim, 1))?;
-    module.define_singleton_method("read", magnus::function!(read, 1))?;
-    module.define_singleton_method("read!", magnus::function!(read_trim, 1))?;
-    module.define_singleton_method("write", magnus::function!(write, 2))?;
+    // 例外クラス（lib/rbcsv.rb の定義と同じ継承関係）
+    let error = module.define_error("Error", ruby.exception_standard_error())?;
+    module.define_error("HeaderError", error)?;
+
+    module.define_singleton_method("parse", magnus::function!(parse, -1))?;
+    module.define_singleton_method("parse!", magnus::function!(parse_trim, -1))?;
+    module.define_singleton_method("read", magnus::function!(read, -1))?;
+    module.define_singleton_method("read!", magnus::function!(read_trim, -1))?;
+    module.define_singleton_method("write", magnus::function!(write, -1))?;
+    module.define_singleton_method("foreach", magnus::method!(foreach, -1))?;
+    module.define_singleton_method("each_slice", magnus::method!(each_slice, -1))?;
 
     // typed variants
-    module.define_singleton_method("parse_typed", magnus::function!(parse_typed, 1))?;
-    module.define_singleton_method("parse_typed!", magnus::function!(parse_typed_trim, 1))?;
-    module.define_singleton_method("read_typed", magnus::function!(read_typed, 1))?;
-    module.define_singleton_method("read_typed!", magnus::function!(read_typed_trim, 1))?;
+    module.define_singleton_method("parse_typed", magnus::function!(parse_typed, -1))?;
+    module.define_singleton_method("parse_typed!", magnus::function!(parse_typed_trim, -1))?;
+    module.define_singleton_method("read_typed", magnus::function!(read_typed, -1))?;
+    module.define_singleton_method("read_typed!", magnus::function!(read_typed_trim, -1))?;
+
+    // validation
+    module.define_singleton_method("validate", magnus::function!(validate, -1))?;
+    module.define_singleton_method("validate_file", magnus::function!(validate_file, -1))?;
+
+    // column statistics
+    module.define_singleton_method("profile", magnus::function!(profile, -1))?;
+    module.define_singleton_method("aggregate", magnus::function!(aggregate, -1))?;
+
+    // sorting and deduplication
+    module.define_singleton_method("sort", magnus::function!(sort, -1))?;
+    module.define_singleton_method("dedup", magnus::function!(dedup, -1))?;
+
+    // streaming writer
+    let writer = module.define_class("Writer", ruby.class_object())?;
+    writer.define_singleton_method("new", magnus::function!(Writer::new, -1))?;
+    writer.define_method("<<", magnus::method!(Writer::push, 1))?;
+    writer.define_method("flush", magnus::method!(Writer::flush, 0))?;
+    writer.define_method("close", magnus::method!(Writer::close, 0))?;
+    writer.define_method("closed?", magnus::method!(Writer::is_closed, 0))?;
+    writer.define_method("lineno", magnus::method!(Writer::lineno, 0))?;
+
+    // streaming reader
+    let reader = module.define_class("Reader", ruby.class_object())?;
+    reader.define_singleton_method("new", magnus::function!(Reader::new, -1))?;
+    reader.define_method("each", magnus::method!(Reader::each, 0))?;
+    reader.define_method("read_all", magnus::method!(Reader::read_all, 0))?;
+    reader.define_method("headers", magnus::method!(Reader::headers, 0))?;
+    reader.define_method("line", magnus::method!(Reader::line, 0))?;
+    reader.define_method("close", magnus::method!(Reader::close, 0))?;
+    reader.define_method("closed?", magnus::method!(Reader::is_closed, 0))?;
+
+    // columnar table
+    let table = module.define_class("Table", ruby.class_object())?;
+    table.define_singleton_method("parse", magnus::function!(Table::parse, -1))?;
+    table.define_singleton_method("read", magnus::function!(Table::read, -1))?;
+    table.define_method("headers", magnus::method!(Table::headers, 0))?;
+    table.define_method("size", magnus::method!(Table::size, 0))?;
+    table.define_method("length", magnus::method!(Table::size, 0))?;
+    table.define_method("[]", magnus::method!(Table::aref, 1))?;
+    table.define_method("row", magnus::method!(Table::row, 1))?;
+    table.define_method("column", magnus::method!(Table::column, 1))?;
+    table.define_method("each_row", magnus::method!(Table::each_row, 0))?;
+    table.define_method("each", magnus::method!(Table::each_row, 0))?;
+    table.define_method("to_a", magnus::method!(Table::to_a, 0))?;
+    table.define_method("to_h", magnus::method!(Table::to_h, 0))?;
 
     Ok(())
 }
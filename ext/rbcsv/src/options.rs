@@ -0,0 +1,765 @@
+use magnus::{
+    value::ReprValue, Error as MagnusError, Integer, RArray, RClass, RHash, RString, Ruby, Symbol, TryConvert,
+    Value as MagnusValue,
+};
+use crate::aggregate::{AggregateFunction, AggregateOptions, Aggregation};
+use crate::compression::Compression;
+use crate::converter::ColumnKey;
+use crate::dedup::{DedupOptions, Keep};
+use crate::filter::RowFilter;
+use crate::header::{DuplicateHeaders, HeaderConverter, HeaderOptions, HeaderValidation};
+use crate::parser::CsvParseOptions;
+use crate::profile::{DistinctMode, ProfileOptions};
+use crate::ruby_api::{build_converters, compression_from_ruby, StringMode};
+use crate::sort::{SortKey, SortOptions, SortOrder};
+use crate::trim::{TrimConfig, TrimMode};
+
+/// parse/read系（`parse_typed`・`read!` 等を含む）で指定できるキーワード引数
+/// 既定以外の書式（`col_sep:` 等）を指定した場合、`threads:` を指定しても分割せずにパースする
+pub const READ_OPTIONS: &[&str] = &[
+    "col_sep",
+    "quote_char",
+    "row_sep",
+    "flexible",
+    "headers",
+    "typed",
+    "trim",
+    "trim_chars",
+    "converters",
+    "header_converters",
+    "duplicate_headers",
+    "required_headers",
+    "strict_headers",
+    "dedup",
+    "freeze",
+    "where",
+    "as",
+    "schema",
+    "threads",
+];
+
+/// `foreach`・`each_slice`・`RbCsv::Reader` で指定できるキーワード引数
+pub const STREAM_OPTIONS: &[&str] = &[
+    "col_sep",
+    "quote_char",
+    "row_sep",
+    "flexible",
+    "headers",
+    "typed",
+    "trim",
+    "trim_chars",
+    "converters",
+    "header_converters",
+    "duplicate_headers",
+    "required_headers",
+    "strict_headers",
+    "dedup",
+    "freeze",
+    "where",
+];
+
+/// Rubyのキーワード引数から変換した読み込みのオプション
+pub struct ReadOptions {
+    pub parse: CsvParseOptions,
+    pub strings: StringMode,
+    /// `converters:` のProc一覧（`Converter::Custom` のインデックスで参照する）
+    pub procs: Vec<MagnusValue>,
+    /// `as:` で指定されたStruct/Dataクラス
+    pub record_class: Option<RClass>,
+    /// `as:` のメンバーごとの型（`schema:`）
+    pub schema: Option<RHash>,
+    pub threads: usize,
+}
+
+/// `RbCsv::Table.parse`・`RbCsv::Table.read` で指定できるキーワード引数
+pub const TABLE_OPTIONS: &[&str] = &[
+    "headers",
+    "typed",
+    "trim",
+    "trim_chars",
+    "converters",
+    "header_converters",
+    "duplicate_headers",
+    "required_headers",
+    "strict_headers",
+    "dedup",
+    "freeze",
+    "where",
+    "threads",
+];
+
+/// `RbCsv.profile` で指定できるキーワード引数（`top:`・`distinct:` を除く）
+pub const PROFILE_OPTIONS: &[&str] = &["col_sep", "quote_char", "row_sep", "headers", "trim", "trim_chars"];
+
+/// `RbCsv.sort` で指定できるキーワード引数（`by:`・`order:`・`memory_limit:`・`compress:` を除く）
+pub const SORT_OPTIONS: &[&str] = &["col_sep", "quote_char", "row_sep", "flexible", "headers", "typed", "trim", "trim_chars"];
+
+/// `RbCsv.dedup` で指定できるキーワード引数（`keys:`・`keep:`・`compress:` を除く）
+pub const DEDUP_OPTIONS: &[&str] = &["col_sep", "quote_char", "row_sep", "flexible", "headers", "typed", "trim", "trim_chars"];
+
+/// `RbCsv.aggregate` で指定できるキーワード引数（`group_by:`・`aggregations:`・`format:` を除く）
+pub const AGGREGATE_OPTIONS: &[&str] = &[
+    "col_sep",
+    "quote_char",
+    "row_sep",
+    "flexible",
+    "headers",
+    "typed",
+    "trim",
+    "trim_chars",
+    "where",
+];
+
+/// 集計結果の形式（`format:`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AggregateFormat {
+    /// 列名をキーとするHashの配列
+    #[default]
+    Hashes,
+    /// 先頭に列名の行がある配列の配列
+    Arrays,
+}
+
+/// 指定されたキーワード引数（変換前）
+#[derive(Default)]
+struct RawOptions {
+    col_sep: Option<RString>,
+    quote_char: Option<RString>,
+    row_sep: Option<MagnusValue>,
+    flexible: Option<bool>,
+    headers: Option<bool>,
+    typed: Option<bool>,
+    trim: Option<MagnusValue>,
+    trim_chars: Option<MagnusValue>,
+    converters: Option<RHash>,
+    header_converters: Option<MagnusValue>,
+    duplicate_headers: Option<Symbol>,
+    required_headers: Option<RArray>,
+    strict_headers: Option<bool>,
+    dedup: Option<bool>,
+    freeze: Option<bool>,
+    filter: Option<RString>,
+    record_class: Option<RClass>,
+    schema: Option<RHash>,
+    threads: Option<Integer>,
+}
+
+/// キーワード引数（Hash）を読み込みのオプションに変換する
+///
+/// `supported` に含まれないキーはArgumentError、値の型が違う場合はTypeErrorにする。
+/// nilを渡したオプションは指定しなかったものとして扱い、`defaults`（メソッドごとの既定値）を使う。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `keywords` - キーワード引数
+/// * `supported` - 指定できるキーワードの一覧（`READ_OPTIONS`・`STREAM_OPTIONS`）
+/// * `defaults` - `trim:`・`typed:` 等を省略した場合の値
+///
+/// # Returns
+/// * `Result<ReadOptions, MagnusError>` - オプションまたはエラー
+pub fn read_options_from_ruby(
+    ruby: &Ruby,
+    keywords: RHash,
+    supported: &[&str],
+    defaults: CsvParseOptions,
+) -> Result<ReadOptions, MagnusError> {
+    let mut raw = RawOptions::default();
+
+    for (key, value) in keywords.to_vec::<MagnusValue, MagnusValue>()? {
+        let name = match Symbol::from_value(key) {
+            Some(name) => name.name()?.into_owned(),
+            None => String::new(),
+        };
+        if !supported.contains(&name.as_str()) {
+            return Err(MagnusError::new(
+                ruby.exception_arg_error(),
+                format!("unknown keyword: {}", key.inspect()),
+            ));
+        }
+        if value.is_nil() {
+            continue;
+        }
+
+        match name.as_str() {
+            "col_sep" => raw.col_sep = Some(expect(ruby, &name, value, "a String", RString::from_value)?),
+            "quote_char" => raw.quote_char = Some(expect(ruby, &name, value, "a String", RString::from_value)?),
+            "row_sep" => raw.row_sep = Some(value),
+            "flexible" => raw.flexible = Some(expect_bool(ruby, &name, value)?),
+            "headers" => raw.headers = Some(expect_bool(ruby, &name, value)?),
+            "typed" => raw.typed = Some(expect_bool(ruby, &name, value)?),
+            "trim" => raw.trim = Some(value),
+            "trim_chars" => raw.trim_chars = Some(value),
+            "converters" => raw.converters = Some(expect(ruby, &name, value, "a Hash", RHash::from_value)?),
+            "header_converters" => raw.header_converters = Some(value),
+            "duplicate_headers" => {
+                raw.duplicate_headers = Some(expect(ruby, &name, value, "a Symbol", Symbol::from_value)?)
+            }
+            "required_headers" => {
+                raw.required_headers = Some(expect(ruby, &name, value, "an Array", RArray::from_value)?)
+            }
+            "strict_headers" => raw.strict_headers = Some(expect_bool(ruby, &name, value)?),
+            "dedup" => raw.dedup = Some(expect_bool(ruby, &name, value)?),
+            "freeze" => raw.freeze = Some(expect_bool(ruby, &name, value)?),
+            "where" => raw.filter = Some(expect(ruby, &name, value, "a String", RString::from_value)?),
+            "as" => raw.record_class = Some(expect(ruby, &name, value, "a Class", RClass::from_value)?),
+            "schema" => raw.schema = Some(expect(ruby, &name, value, "a Hash", RHash::from_value)?),
+            "threads" => raw.threads = Some(expect(ruby, &name, value, "an Integer", Integer::from_value)?),
+            _ => unreachable!("supported option without a conversion: {}", name),
+        }
+    }
+
+    raw.into_options(ruby, defaults)
+}
+
+impl RawOptions {
+    fn into_options(self, ruby: &Ruby, defaults: CsvParseOptions) -> Result<ReadOptions, MagnusError> {
+        let headers = self.headers.unwrap_or(defaults.headers);
+        let typed = self.typed.unwrap_or(defaults.typed);
+        let trim = trim_config_from_ruby(ruby, self.trim, self.trim_chars, defaults.trim)?;
+
+        if headers && self.record_class.is_some() {
+            return Err(argument_error(ruby, "headers: and as: cannot be used together"));
+        }
+        if typed && self.record_class.is_some() {
+            return Err(argument_error(ruby, "typed: and as: cannot be used together"));
+        }
+        if self.schema.is_some() && self.record_class.is_none() {
+            return Err(argument_error(ruby, "schema: requires as: to be specified"));
+        }
+        if self.converters.is_some() && !typed {
+            return Err(argument_error(ruby, "converters: requires typed: true"));
+        }
+
+        let threads = match self.threads {
+            Some(threads) => match threads.to_i64()? {
+                threads if threads > 0 => threads as usize,
+                _ => return Err(argument_error(ruby, "threads must be a positive integer")),
+            },
+            None => 1,
+        };
+
+        let uses_headers = headers || self.record_class.is_some();
+        let mut dialect = defaults.dialect;
+        if let Some(col_sep) = self.col_sep {
+            dialect.delimiter = single_byte(ruby, "col_sep", &col_sep.to_string()?)?;
+        }
+        if let Some(quote_char) = self.quote_char {
+            dialect.quote = single_byte(ruby, "quote_char", &quote_char.to_string()?)?;
+        }
+        if let Some(row_sep) = self.row_sep {
+            dialect.terminator = row_sep_from_ruby(ruby, row_sep)?.unwrap_or(csv::Terminator::CRLF);
+        }
+        if let Some(flexible) = self.flexible {
+            if flexible && uses_headers {
+                return Err(argument_error(ruby, "flexible: cannot be used with headers: true"));
+            }
+            dialect.flexible = flexible;
+        }
+        let header_options = header_options_from_ruby(ruby, uses_headers, self.header_converters, self.duplicate_headers)?;
+        let validation = header_validation_from_ruby(ruby, &header_options, self.required_headers, self.strict_headers)?;
+        let (converters, procs) = build_converters(ruby, self.converters)?;
+        let header_row = uses_headers || validation.is_some() || converters.uses_header_names();
+        // 先頭行をヘッダーとして扱わない場合、`trim: :headers`・`:fields` はすべての行を同じに扱う
+        let trim = if header_row { trim } else { trim.without_header_row() };
+        let filter = match self.filter {
+            Some(source) => {
+                let mut filter = RowFilter::compile(&source.to_string()?)
+                    .map_err(|e| argument_error(ruby, &format!("invalid where: expression: {}", e)))?;
+                filter.header_row = header_row;
+                filter.header_options = header_options.clone();
+                Some(filter)
+            }
+            None => None,
+        };
+
+        Ok(ReadOptions {
+            parse: CsvParseOptions {
+                trim,
+                headers,
+                typed,
+                header_options,
+                validation,
+                converters,
+                dialect,
+                filter,
+            },
+            strings: StringMode::from_options(self.dedup, self.freeze),
+            procs,
+            record_class: self.record_class,
+            schema: self.schema,
+            threads,
+        })
+    }
+}
+
+/// `RbCsv.profile` のキーワード引数を読み込みのオプションと集計の設定に変換する
+///
+/// `top:`（0以上の整数）と `distinct:`（`:exact`・`:approximate`）を取り出し、
+/// 残りは `PROFILE_OPTIONS` として `read_options_from_ruby` で変換する。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `keywords` - キーワード引数
+/// * `defaults` - 読み込みのオプションの既定値
+///
+/// # Returns
+/// * `Result<(ReadOptions, ProfileOptions), MagnusError>` - オプションまたはエラー
+pub fn profile_options_from_ruby(
+    ruby: &Ruby,
+    keywords: RHash,
+    defaults: CsvParseOptions,
+) -> Result<(ReadOptions, ProfileOptions), MagnusError> {
+    let mut profile = ProfileOptions::default();
+    let rest = ruby.hash_new();
+
+    for (key, value) in keywords.to_vec::<MagnusValue, MagnusValue>()? {
+        let name = match Symbol::from_value(key) {
+            Some(name) => name.name()?.into_owned(),
+            None => String::new(),
+        };
+        match name.as_str() {
+            "top" if !value.is_nil() => {
+                profile.top = match expect(ruby, &name, value, "an Integer", Integer::from_value)?.to_i64()? {
+                    top if top >= 0 => top as usize,
+                    _ => return Err(argument_error(ruby, "top must be a non-negative integer")),
+                };
+            }
+            "distinct" if !value.is_nil() => {
+                let mode = expect(ruby, &name, value, "a Symbol", Symbol::from_value)?;
+                profile.distinct = DistinctMode::from_name(&mode.name()?).ok_or_else(|| {
+                    MagnusError::new(
+                        ruby.exception_arg_error(),
+                        format!("unknown distinct mode: {}", mode.inspect()),
+                    )
+                })?;
+            }
+            "top" | "distinct" => {}
+            _ => rest.aset(key, value)?,
+        }
+    }
+
+    let options = read_options_from_ruby(ruby, rest, PROFILE_OPTIONS, defaults)?;
+    Ok((options, profile))
+}
+
+/// `RbCsv.sort` のキーワード引数を読み込みのオプションと並べ替えの設定に変換する
+///
+/// `by:`（列番号またはヘッダー名、その配列）・`order:`（`:asc`・`:desc`、または列ごとの配列）・
+/// `memory_limit:`（バイト数）・`compress:` を取り出し、残りは `SORT_OPTIONS` として
+/// `read_options_from_ruby` で変換する。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `keywords` - キーワード引数
+/// * `defaults` - 読み込みのオプションの既定値
+///
+/// # Returns
+/// * `Result<(ReadOptions, SortOptions, Option<Compression>), MagnusError>` - オプションまたはエラー
+pub fn sort_options_from_ruby(
+    ruby: &Ruby,
+    keywords: RHash,
+    defaults: CsvParseOptions,
+) -> Result<(ReadOptions, SortOptions, Option<Compression>), MagnusError> {
+    let mut sort = SortOptions::default();
+    let mut columns = None;
+    let mut orders = None;
+    let mut compress = None;
+    let rest = ruby.hash_new();
+
+    for (key, value) in keywords.to_vec::<MagnusValue, MagnusValue>()? {
+        let name = match Symbol::from_value(key) {
+            Some(name) => name.name()?.into_owned(),
+            None => String::new(),
+        };
+        match name.as_str() {
+            "by" if !value.is_nil() => {
+                columns = Some(list_from_ruby(value, |value| column_from_ruby(ruby, &name, value))?);
+            }
+            "order" if !value.is_nil() => {
+                orders = Some(list_from_ruby(value, |value| sort_order_from_ruby(ruby, value))?);
+            }
+            "memory_limit" if !value.is_nil() => {
+                sort.memory_limit = match expect(ruby, &name, value, "an Integer", Integer::from_value)?.to_i64()? {
+                    limit if limit > 0 => limit as usize,
+                    _ => return Err(argument_error(ruby, "memory_limit must be a positive integer")),
+                };
+            }
+            "compress" if !value.is_nil() => {
+                let name = expect(ruby, &name, value, "a Symbol", Symbol::from_value)?;
+                compress = Some(compression_from_ruby(ruby, name)?);
+            }
+            "by" | "order" | "memory_limit" | "compress" => {}
+            _ => rest.aset(key, value)?,
+        }
+    }
+
+    let columns = columns.ok_or_else(|| argument_error(ruby, "missing keyword: :by"))?;
+    if columns.is_empty() {
+        return Err(argument_error(ruby, "by: must name at least one column"));
+    }
+    // `order:` が1つの場合はすべての列に適用する
+    let orders = match orders {
+        None => vec![SortOrder::Asc; columns.len()],
+        Some(orders) if orders.len() == 1 => vec![orders[0]; columns.len()],
+        Some(orders) if orders.len() == columns.len() => orders,
+        Some(_) => return Err(argument_error(ruby, "order: must be a Symbol or have one entry per by: column")),
+    };
+    sort.keys = columns
+        .into_iter()
+        .zip(orders)
+        .map(|(column, order)| SortKey { column, order })
+        .collect();
+
+    let options = read_options_from_ruby(ruby, rest, SORT_OPTIONS, defaults)?;
+    Ok((options, sort, compress))
+}
+
+/// `RbCsv.dedup` のキーワード引数を読み込みのオプションと重複の除去の設定に変換する
+///
+/// `keys:`（列番号またはヘッダー名、その配列。省略時はすべての列）・`keep:`（`:first`・`:last`）・
+/// `compress:` を取り出し、残りは `DEDUP_OPTIONS` として `read_options_from_ruby` で変換する。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `keywords` - キーワード引数
+/// * `defaults` - 読み込みのオプションの既定値
+///
+/// # Returns
+/// * `Result<(ReadOptions, DedupOptions, Option<Compression>), MagnusError>` - オプションまたはエラー
+pub fn dedup_options_from_ruby(
+    ruby: &Ruby,
+    keywords: RHash,
+    defaults: CsvParseOptions,
+) -> Result<(ReadOptions, DedupOptions, Option<Compression>), MagnusError> {
+    let mut dedup = DedupOptions::default();
+    let mut compress = None;
+    let rest = ruby.hash_new();
+
+    for (key, value) in keywords.to_vec::<MagnusValue, MagnusValue>()? {
+        let name = match Symbol::from_value(key) {
+            Some(name) => name.name()?.into_owned(),
+            None => String::new(),
+        };
+        match name.as_str() {
+            "keys" if !value.is_nil() => {
+                dedup.keys = list_from_ruby(value, |value| column_from_ruby(ruby, &name, value))?;
+            }
+            "keep" if !value.is_nil() => {
+                let keep = expect(ruby, &name, value, "a Symbol", Symbol::from_value)?;
+                dedup.keep = Keep::from_name(&keep.name()?).ok_or_else(|| {
+                    MagnusError::new(
+                        ruby.exception_arg_error(),
+                        format!("unknown keep: {}", keep.inspect()),
+                    )
+                })?;
+            }
+            "compress" if !value.is_nil() => {
+                let name = expect(ruby, &name, value, "a Symbol", Symbol::from_value)?;
+                compress = Some(compression_from_ruby(ruby, name)?);
+            }
+            "keys" | "keep" | "compress" => {}
+            _ => rest.aset(key, value)?,
+        }
+    }
+
+    let options = read_options_from_ruby(ruby, rest, DEDUP_OPTIONS, defaults)?;
+    Ok((options, dedup, compress))
+}
+
+/// `RbCsv.aggregate` のキーワード引数を読み込みのオプションと集計の設定に変換する
+///
+/// `group_by:`（列番号またはヘッダー名、その配列）・`aggregations:`（列 => 集計関数またはその配列のHash、必須）・
+/// `format:`（`:hashes`・`:arrays`）を取り出し、残りは `AGGREGATE_OPTIONS` として `read_options_from_ruby` で変換する。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `keywords` - キーワード引数
+/// * `defaults` - 読み込みのオプションの既定値
+///
+/// # Returns
+/// * `Result<(ReadOptions, AggregateOptions, AggregateFormat), MagnusError>` - オプションまたはエラー
+pub fn aggregate_options_from_ruby(
+    ruby: &Ruby,
+    keywords: RHash,
+    defaults: CsvParseOptions,
+) -> Result<(ReadOptions, AggregateOptions, AggregateFormat), MagnusError> {
+    let mut aggregate = AggregateOptions::default();
+    let mut aggregations = None;
+    let mut format = AggregateFormat::default();
+    let rest = ruby.hash_new();
+
+    for (key, value) in keywords.to_vec::<MagnusValue, MagnusValue>()? {
+        let name = match Symbol::from_value(key) {
+            Some(name) => name.name()?.into_owned(),
+            None => String::new(),
+        };
+        match name.as_str() {
+            "group_by" if !value.is_nil() => {
+                aggregate.group_by = list_from_ruby(value, |value| column_from_ruby(ruby, &name, value))?;
+            }
+            "aggregations" if !value.is_nil() => {
+                let spec = expect(ruby, &name, value, "a Hash", RHash::from_value)?;
+                let mut list = Vec::new();
+                for (column, functions) in spec.to_vec::<MagnusValue, MagnusValue>()? {
+                    let column = column_from_ruby(ruby, &name, column)?;
+                    for function in list_from_ruby(functions, |value| aggregate_function_from_ruby(ruby, value))? {
+                        list.push(Aggregation { column: column.clone(), function });
+                    }
+                }
+                aggregations = Some(list);
+            }
+            "format" if !value.is_nil() => {
+                let name = expect(ruby, &name, value, "a Symbol", Symbol::from_value)?;
+                format = match name.name()?.as_ref() {
+                    "hashes" => AggregateFormat::Hashes,
+                    "arrays" => AggregateFormat::Arrays,
+                    _ => {
+                        return Err(MagnusError::new(
+                            ruby.exception_arg_error(),
+                            format!("unknown format: {}", name.inspect()),
+                        ))
+                    }
+                };
+            }
+            "group_by" | "aggregations" | "format" => {}
+            _ => rest.aset(key, value)?,
+        }
+    }
+
+    aggregate.aggregations = aggregations.ok_or_else(|| argument_error(ruby, "missing keyword: :aggregations"))?;
+    if aggregate.aggregations.is_empty() {
+        return Err(argument_error(ruby, "aggregations: must have at least one column"));
+    }
+
+    let options = read_options_from_ruby(ruby, rest, AGGREGATE_OPTIONS, defaults)?;
+    Ok((options, aggregate, format))
+}
+
+/// 集計関数（`:count`・`:sum`・`:mean`・`:min`・`:max`）を変換する
+fn aggregate_function_from_ruby(ruby: &Ruby, value: MagnusValue) -> Result<AggregateFunction, MagnusError> {
+    let name = expect(ruby, "aggregations", value, "a Symbol", Symbol::from_value)?;
+    AggregateFunction::from_name(&name.name()?).ok_or_else(|| {
+        MagnusError::new(
+            ruby.exception_arg_error(),
+            format!("unknown aggregation: {}", name.inspect()),
+        )
+    })
+}
+
+/// 値またはその配列を変換する
+fn list_from_ruby<T>(
+    value: MagnusValue,
+    mut convert: impl FnMut(MagnusValue) -> Result<T, MagnusError>,
+) -> Result<Vec<T>, MagnusError> {
+    match RArray::from_value(value) {
+        Some(values) => values.into_iter().map(convert).collect(),
+        None => Ok(vec![convert(value)?]),
+    }
+}
+
+/// `by:`・`keys:`・`group_by:`・`aggregations:` の列（列番号またはヘッダー名）を変換する
+fn column_from_ruby(ruby: &Ruby, name: &str, value: MagnusValue) -> Result<ColumnKey, MagnusError> {
+    if let Some(index) = Integer::from_value(value) {
+        return Ok(ColumnKey::Index(index.to_usize()?));
+    }
+    if let Some(name) = Symbol::from_value(value) {
+        return Ok(ColumnKey::Name(name.name()?.into_owned()));
+    }
+    let column = expect(ruby, name, value, "a column index or header name", RString::from_value)?;
+    Ok(ColumnKey::Name(column.to_string()?))
+}
+
+/// `order:` の値（`:asc`・`:desc`）を変換する
+fn sort_order_from_ruby(ruby: &Ruby, value: MagnusValue) -> Result<SortOrder, MagnusError> {
+    let name = expect(ruby, "order", value, "a Symbol", Symbol::from_value)?;
+    SortOrder::from_name(&name.name()?).ok_or_else(|| {
+        MagnusError::new(
+            ruby.exception_arg_error(),
+            format!("unknown sort order: {}", name.inspect()),
+        )
+    })
+}
+
+/// `trim:`（true/false または `:all`・`:headers`・`:fields`・`:none`）と `trim_chars:`（除去する文字の文字列）を
+/// 空白の除去の設定に変換する。`trim:` がnilの場合は `default` を使う
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `trim` - `trim:` の値
+/// * `trim_chars` - `trim_chars:` の値
+/// * `default` - `trim:` を省略した場合の設定
+///
+/// # Returns
+/// * `Result<TrimConfig, MagnusError>` - 空白の除去の設定またはエラー
+pub fn trim_config_from_ruby(
+    ruby: &Ruby,
+    trim: Option<MagnusValue>,
+    trim_chars: Option<MagnusValue>,
+    default: TrimConfig,
+) -> Result<TrimConfig, MagnusError> {
+    let mut config = match trim {
+        Some(value) if value.is_kind_of(ruby.class_true_class()) => TrimConfig::all(),
+        Some(value) if value.is_kind_of(ruby.class_false_class()) => TrimConfig::default(),
+        Some(value) => {
+            let name = expect(ruby, "trim", value, "true, false or a Symbol", Symbol::from_value)?;
+            let mode = TrimMode::from_name(&name.name()?).ok_or_else(|| {
+                MagnusError::new(
+                    ruby.exception_arg_error(),
+                    format!("unknown trim mode: {}", name.inspect()),
+                )
+            })?;
+            TrimConfig::new(mode)
+        }
+        None => default,
+    };
+
+    if let Some(value) = trim_chars {
+        if config.mode == TrimMode::None {
+            return Err(argument_error(ruby, "trim_chars: requires trim: to be enabled"));
+        }
+        let chars = expect(ruby, "trim_chars", value, "a String", RString::from_value)?;
+        config.chars = chars.to_string()?.chars().collect();
+    }
+
+    Ok(config)
+}
+
+/// 1バイトの文字（`col_sep:`・`quote_char:`）をバイトに変換する
+pub fn single_byte(ruby: &Ruby, name: &str, value: &str) -> Result<u8, MagnusError> {
+    match value.as_bytes() {
+        &[byte] => Ok(byte),
+        _ => Err(MagnusError::new(
+            ruby.exception_arg_error(),
+            format!("{} must be a single byte character: {:?}", name, value),
+        )),
+    }
+}
+
+/// `row_sep:`（`:auto`・`"\r\n"`・1バイトの文字）を行区切りに変換する（`:auto` はNone）
+pub fn row_sep_from_ruby(ruby: &Ruby, value: MagnusValue) -> Result<Option<csv::Terminator>, MagnusError> {
+    if let Some(name) = Symbol::from_value(value) {
+        if name.name()? == "auto" {
+            return Ok(None);
+        }
+        return Err(MagnusError::new(
+            ruby.exception_arg_error(),
+            format!("unknown row_sep: {}", name.inspect()),
+        ));
+    }
+
+    let row_sep = expect(ruby, "row_sep", value, "a String or :auto", RString::from_value)?.to_string()?;
+    if row_sep == "\r\n" {
+        return Ok(Some(csv::Terminator::CRLF));
+    }
+    single_byte(ruby, "row_sep", &row_sep).map(|byte| Some(csv::Terminator::Any(byte)))
+}
+
+fn argument_error(ruby: &Ruby, message: &str) -> MagnusError {
+    MagnusError::new(ruby.exception_arg_error(), message.to_string())
+}
+
+/// オプションの値を変換する（型が違う場合はTypeError）
+fn expect<T>(
+    ruby: &Ruby,
+    name: &str,
+    value: MagnusValue,
+    expected: &str,
+    convert: impl FnOnce(MagnusValue) -> Option<T>,
+) -> Result<T, MagnusError> {
+    convert(value).ok_or_else(|| {
+        MagnusError::new(
+            ruby.exception_type_error(),
+            format!("{}: expected {}, got {}", name, expected, value.inspect()),
+        )
+    })
+}
+
+/// true/false以外の値（1や "yes" 等）はTypeErrorにする
+fn expect_bool(ruby: &Ruby, name: &str, value: MagnusValue) -> Result<bool, MagnusError> {
+    expect(ruby, name, value, "true or false", |value| {
+        if value.is_kind_of(ruby.class_true_class()) {
+            Some(true)
+        } else if value.is_kind_of(ruby.class_false_class()) {
+            Some(false)
+        } else {
+            None
+        }
+    })
+}
+
+/// `header_converters:`（Symbolまたはその配列）と `duplicate_headers:` をヘッダー処理の設定に変換する
+fn header_options_from_ruby(
+    ruby: &Ruby,
+    uses_headers: bool,
+    header_converters: Option<MagnusValue>,
+    duplicate_headers: Option<Symbol>,
+) -> Result<HeaderOptions, MagnusError> {
+    if !uses_headers && (header_converters.is_some() || duplicate_headers.is_some()) {
+        return Err(argument_error(
+            ruby,
+            "header_converters: and duplicate_headers: require headers: true",
+        ));
+    }
+
+    let mut options = HeaderOptions::default();
+
+    if let Some(header_converters) = header_converters {
+        let names: Vec<Symbol> = match Symbol::from_value(header_converters) {
+            Some(name) => vec![name],
+            None => Vec::<Symbol>::try_convert(header_converters)?,
+        };
+        for name in names {
+            let converter = HeaderConverter::from_name(&name.name()?).ok_or_else(|| {
+                MagnusError::new(
+                    ruby.exception_arg_error(),
+                    format!("unknown header converter: {}", name.inspect()),
+                )
+            })?;
+            options.converters.push(converter);
+        }
+    }
+
+    if let Some(duplicate_headers) = duplicate_headers {
+        options.duplicates = DuplicateHeaders::from_name(&duplicate_headers.name()?).ok_or_else(|| {
+            MagnusError::new(
+                ruby.exception_arg_error(),
+                format!("unknown duplicate_headers strategy: {}", duplicate_headers.inspect()),
+            )
+        })?;
+    }
+
+    Ok(options)
+}
+
+/// `required_headers:`（String/Symbolの配列）と `strict_headers:` をヘッダー検証の設定に変換する
+/// 比較は `header_converters:` で正規化した後のヘッダー名で行う
+fn header_validation_from_ruby(
+    ruby: &Ruby,
+    header_options: &HeaderOptions,
+    required_headers: Option<RArray>,
+    strict_headers: Option<bool>,
+) -> Result<Option<HeaderValidation>, MagnusError> {
+    let Some(required_headers) = required_headers else {
+        if strict_headers.is_some() {
+            return Err(argument_error(
+                ruby,
+                "strict_headers: requires required_headers: to be specified",
+            ));
+        }
+        return Ok(None);
+    };
+
+    let required = required_headers
+        .into_iter()
+        .map(|name| match Symbol::from_value(name) {
+            Some(name) => name.name().map(|name| name.into_owned()),
+            None => String::try_convert(name),
+        })
+        .collect::<Result<Vec<String>, MagnusError>>()?;
+
+    Ok(Some(HeaderValidation {
+        required,
+        strict: strict_headers.unwrap_or(false),
+        options: header_options.clone(),
+    }))
+}
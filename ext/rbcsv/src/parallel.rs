@@ -0,0 +1,374 @@
+use crate::error::CsvError;
+use crate::filter::RowSelector;
+use crate::gvl::{check_interrupt, InterruptHandle};
+use crate::parser::{
+    dialect_reader, field_count_error, parse_csv_rows, parse_csv_typed, record_error, typed_record, CsvParseOptions,
+    INTERRUPT_CHECK_INTERVAL,
+};
+use crate::rows::Rows;
+use crate::value::CsvValue;
+use std::panic;
+use std::thread;
+
+/// 1チャンクあたりの最小バイト数（これより小さい入力は分割せずにパースする）
+const MIN_CHUNK_SIZE: usize = 256 * 1024;
+
+/// CSVを複数スレッドで並列にパースする
+///
+/// 1行目を読み込んだ後、残りをレコードの区切りで `threads` 個のチャンクに分割し、
+/// 各チャンクを別スレッドでパースしてから元の順序で結合する。結果は `parse_csv_rows` と同じになる。
+/// 入力が小さい場合、`threads` が1の場合、既定以外の書式（`col_sep:` 等）の場合は分割せずにパースする。
+///
+/// スレッドは呼び出しごとに `thread::scope` で起動する。チャンクは入力を借用したまま渡せ、
+/// 1チャンクは `MIN_CHUNK_SIZE` 以上あるため起動のコストはパースに比べて小さい。
+///
+/// # Arguments
+/// * `input` - パースするCSV文字列
+/// * `parse` - trim・ヘッダー行の検証・絞り込み条件等の設定
+/// * `threads` - 使用するスレッド数
+pub fn parse_csv_parallel(input: &str, parse: &CsvParseOptions, threads: usize) -> Result<Rows, CsvError> {
+    if !splits_into_chunks(input, parse, threads) {
+        return parse_csv_rows(input, parse);
+    }
+
+    let (mut first, offset) = first_record(input.as_bytes(), parse)?;
+    if let Some(validation) = &parse.validation {
+        validation.validate(&first.iter().collect::<Vec<&str>>())?;
+    }
+
+    // 1行目はバッファに移すため、列数は先に取り出しておく
+    let expected_len = first.len();
+    let mut rows = Rows::new();
+    let mut selector = RowSelector::new(parse.filter.clone());
+    if selector.select(0, &first)? {
+        rows.push_record(&mut first);
+    }
+    let predicate = selector.predicate();
+    let chunks = parse_body(&input.as_bytes()[offset..], expected_len, parse, threads, |rows: &mut Rows, record| {
+        if predicate.is_none_or(|predicate| predicate.matches(record)) {
+            rows.push_record(record);
+        }
+    })?;
+    for chunk in chunks {
+        rows.append(chunk);
+    }
+
+    Ok(rows)
+}
+
+/// 型認識を行うCSVを複数スレッドで並列にパースする
+///
+/// コンバーターの対象列は1行目から解決してからチャンクを分割する。結果は `parse_csv_typed` と同じになる。
+pub fn parse_csv_typed_parallel(
+    input: &str,
+    parse: &CsvParseOptions,
+    threads: usize,
+) -> Result<Vec<Vec<CsvValue>>, CsvError> {
+    if !splits_into_chunks(input, parse, threads) {
+        return parse_csv_typed(input, parse);
+    }
+
+    let converters = &parse.converters;
+    let trim_fields = parse.trim.trims_fields();
+    let (first, offset) = first_record(input.as_bytes(), parse)?;
+    let (first_row, column_converters) = if parse.reads_header_row() {
+        if let Some(validation) = &parse.validation {
+            validation.validate(&first.iter().collect::<Vec<&str>>())?;
+        }
+        let row = first.iter().map(|field| CsvValue::String(field.to_string())).collect();
+        (row, converters.resolve(Some(&first), &parse.header_options)?)
+    } else {
+        let column_converters = converters.resolve(None, &parse.header_options)?;
+        (typed_record(&first, &column_converters, trim_fields), column_converters)
+    };
+    let mut selector = RowSelector::new(parse.filter.clone());
+    let keep_first = selector.select_converted(0, &first, &column_converters, trim_fields)?;
+
+    let predicate = selector.predicate();
+    let chunks = parse_body(&input.as_bytes()[offset..], first.len(), parse, threads, |rows: &mut Vec<_>, record| {
+        if predicate.is_none_or(|predicate| predicate.matches_converted(record, &column_converters, trim_fields)) {
+            rows.push(typed_record(record, &column_converters, trim_fields));
+        }
+    })?;
+
+    let mut records = Vec::with_capacity(chunks.iter().map(Vec::len).sum::<usize>() + 1);
+    if keep_first {
+        records.push(first_row);
+    }
+    records.extend(chunks.into_iter().flatten());
+    Ok(records)
+}
+
+/// 入力の大きさとスレッド数からチャンク数を決める
+fn chunk_count(len: usize, threads: usize) -> usize {
+    threads.min(len / MIN_CHUNK_SIZE).max(1)
+}
+
+/// 入力をチャンクに分割して並列にパースするか
+/// チャンクの分割は既定の書式（`,`・`"`・改行）を前提とするため、それ以外の書式では分割しない
+fn splits_into_chunks(input: &str, parse: &CsvParseOptions, threads: usize) -> bool {
+    chunk_count(input.len(), threads) > 1 && parse.dialect.is_default()
+}
+
+/// 1行目のレコードと、2行目の開始位置（バイト）を返す
+fn first_record(input: &[u8], parse: &CsvParseOptions) -> Result<(csv::StringRecord, usize), CsvError> {
+    let mut reader = dialect_reader(input, &parse.dialect);
+    let mut record = csv::StringRecord::new();
+    if !reader.read_record(&mut record).map_err(|e| record_error(0, e))? {
+        return Err(CsvError::empty_data());
+    }
+    parse.trim.apply(&mut record, true);
+
+    Ok((record, reader.position().byte() as usize))
+}
+
+/// 2行目以降をチャンクに分割して並列にパースする
+///
+/// 各チャンクのレコードは `push` で集計先（チャンクごとに作成）に渡し、集計先をチャンクの順序で返す。
+/// `push` はレコードを取り出して（空のレコードに置き換えて）よい。
+/// `push` が追加しなかった（絞り込んだ）レコードも行番号の計算には含める。
+fn parse_body<A, F>(
+    body: &[u8],
+    expected_len: usize,
+    parse: &CsvParseOptions,
+    threads: usize,
+    push: F,
+) -> Result<Vec<A>, CsvError>
+where
+    A: Default + Send,
+    F: Fn(&mut A, &mut csv::StringRecord) + Sync,
+{
+    let chunks = split_chunks(body, chunk_count(body.len(), threads));
+    let interrupt = InterruptHandle::current();
+
+    let results: Vec<Result<(A, usize), ChunkError>> = thread::scope(|scope| {
+        let push = &push;
+        let handles: Vec<_> = chunks
+            .iter()
+            .map(|&chunk| {
+                scope.spawn(move || interrupt.enter(|| parse_chunk(chunk, expected_len, parse, push)))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    });
+
+    // ヘッダー行と前のチャンクまでのレコード数から、チャンク先頭の行番号を求める
+    let mut base = 1;
+    let mut parsed = Vec::with_capacity(results.len());
+    for result in results {
+        let (rows, count) = result.map_err(|e| e.into_csv_error(base))?;
+        base += count;
+        parsed.push(rows);
+    }
+
+    Ok(parsed)
+}
+
+/// チャンク内で発生したエラー（位置はチャンク先頭からのレコード数）
+enum ChunkError {
+    Record(usize, csv::Error),
+    FieldCount { index: usize, expected_len: usize, len: usize },
+    Interrupted(CsvError),
+}
+
+impl ChunkError {
+    fn into_csv_error(self, base: usize) -> CsvError {
+        match self {
+            ChunkError::Record(index, e) => record_error(base + index, e),
+            ChunkError::FieldCount { index, expected_len, len } => field_count_error(base + index, expected_len, len),
+            ChunkError::Interrupted(e) => e,
+        }
+    }
+}
+
+/// 1つのチャンクをパースし、集計先とレコード数を返す
+/// 列数は1行目（ヘッダー行）と比較する（チャンクをまたいだ不一致も検出するため）
+fn parse_chunk<A: Default>(
+    chunk: &[u8],
+    expected_len: usize,
+    parse: &CsvParseOptions,
+    push: &impl Fn(&mut A, &mut csv::StringRecord),
+) -> Result<(A, usize), ChunkError> {
+    let mut reader = dialect_reader(chunk, &parse.dialect);
+    let mut record = csv::StringRecord::new();
+    let mut rows = A::default();
+    let mut count: usize = 0;
+
+    loop {
+        if count.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+            check_interrupt().map_err(ChunkError::Interrupted)?;
+        }
+        match reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => return Err(ChunkError::Record(count, e)),
+        }
+        parse.trim.apply(&mut record, false);
+        if record.len() != expected_len {
+            return Err(ChunkError::FieldCount { index: count, expected_len, len: record.len() });
+        }
+        push(&mut rows, &mut record);
+        count += 1;
+    }
+
+    Ok((rows, count))
+}
+
+/// レコードの区切りを判定するためのスキャン状態
+#[derive(Clone, Copy, PartialEq)]
+enum ScanState {
+    /// フィールドの先頭
+    FieldStart,
+    /// クォートされていないフィールドの中
+    Unquoted,
+    /// クォートされたフィールドの中
+    Quoted,
+    /// クォートされたフィールドの中でクォートを読んだ直後（閉じクォートまたはエスケープ）
+    QuoteInQuoted,
+}
+
+/// 入力をおよそ均等な大きさの `count` 個以下のチャンクに分割する
+///
+/// 分割位置はクォートの外にある `\n` の直後に限るため、クォートされたフィールド内の改行で
+/// レコードが分断されることはない。クォートはcsvクレートと同じくフィールドの先頭でのみ認識する。
+/// 行区切りが `\r` だけの入力は分割位置がないため、1つのチャンクになる。
+fn split_chunks(input: &[u8], count: usize) -> Vec<&[u8]> {
+    let target = input.len() / count.max(1);
+    let mut chunks = Vec::with_capacity(count);
+    let mut start = 0;
+    let mut state = ScanState::FieldStart;
+
+    for (pos, &byte) in input.iter().enumerate() {
+        state = match (state, byte) {
+            (ScanState::Quoted, b'"') => ScanState::QuoteInQuoted,
+            (ScanState::Quoted, _) => ScanState::Quoted,
+            (ScanState::FieldStart, b'"') | (ScanState::QuoteInQuoted, b'"') => ScanState::Quoted,
+            (_, b',') => ScanState::FieldStart,
+            // csvクレートの既定の行区切りと同じく、単独の `\r` もレコードの終わりとして扱う
+            (_, b'\r') => ScanState::FieldStart,
+            (_, b'\n') => {
+                if pos + 1 - start >= target && chunks.len() + 1 < count {
+                    chunks.push(&input[start..=pos]);
+                    start = pos + 1;
+                }
+                ScanState::FieldStart
+            }
+            _ => ScanState::Unquoted,
+        };
+    }
+
+    if start < input.len() {
+        chunks.push(&input[start..]);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::ColumnConverters;
+    use crate::error::ErrorKind;
+    use crate::filter::RowFilter;
+    use crate::parser::Dialect;
+    use crate::trim::TrimConfig;
+
+    /// 行区切りが `\r` と `\r\n` の混在した、先頭のフィールドのクォート内に改行を含むCSV
+    fn large_csv_with_cr(rows: usize) -> String {
+        let mut input = String::from("note,id,name\r");
+        for i in 0..rows {
+            let terminator = if i % 10 == 9 { "\r\n" } else { "\r" };
+            input.push_str(&format!("\"line one\nline two {}\",{},user{}{}", i, i, i, terminator));
+        }
+        input
+    }
+
+    fn large_csv(rows: usize) -> String {
+        let mut input = String::from("id,name,note\n");
+        for i in 0..rows {
+            input.push_str(&format!("{},user{},\"line one\nline \"\"two\"\", {}\"\n", i, i, i));
+        }
+        input
+    }
+
+    #[test]
+    fn test_split_chunks_at_record_boundaries() {
+        let input = b"a,\"x\ny\"\nb,\"\"\"\n\"\nc,d\ne,f\n";
+        for count in 1..=6 {
+            let chunks = split_chunks(input, count);
+            assert!(chunks.len() <= count);
+            assert_eq!(chunks.concat(), input.to_vec());
+            for chunk in &chunks {
+                assert!(chunk.ends_with(b"\n"));
+                assert!(!chunk.starts_with(b"y") && !chunk.starts_with(b"\"\n"));
+            }
+        }
+
+        // 行区切りが `\r` だけの場合も、次のレコードの先頭のクォート内の改行では分割しない
+        let input = b"a,b\r\"x\ny\",c\rd,e\r";
+        for count in 1..=6 {
+            let chunks = split_chunks(input, count);
+            assert_eq!(chunks, vec![&input[..]]);
+        }
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let input = large_csv(20_000);
+        assert!(chunk_count(input.len(), 4) > 1);
+
+        let parse = CsvParseOptions::default();
+        let expected = parse_csv_rows(&input, &parse).unwrap();
+        assert_eq!(parse_csv_parallel(&input, &parse, 4).unwrap(), expected);
+
+        let typed = CsvParseOptions { trim: TrimConfig::all(), headers: true, ..Default::default() };
+        let expected = parse_csv_typed(&input, &typed).unwrap();
+        assert_eq!(parse_csv_typed_parallel(&input, &typed, 4).unwrap(), expected);
+
+        let input = large_csv_with_cr(20_000);
+        assert!(chunk_count(input.len(), 4) > 1);
+        let expected = parse_csv_rows(&input, &parse).unwrap();
+        assert_eq!(expected.len(), 20_001);
+        assert_eq!(parse_csv_parallel(&input, &parse, 4).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parallel_filter_matches_sequential() {
+        let input = large_csv(20_000);
+        let mut filter = RowFilter::compile("name =~ /7$/ && id >= 1000").unwrap();
+        filter.header_row = true;
+
+        let parse = CsvParseOptions { filter: Some(filter), ..Default::default() };
+        let expected = parse_csv_rows(&input, &parse).unwrap();
+        assert_eq!(expected.len(), 1 + 1900);
+        assert_eq!(parse_csv_parallel(&input, &parse, 4).unwrap(), expected);
+
+        let typed = CsvParseOptions { headers: true, converters: ColumnConverters::new(), ..parse };
+        let expected = parse_csv_typed(&input, &typed).unwrap();
+        assert_eq!(parse_csv_typed_parallel(&input, &typed, 4).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parallel_falls_back_for_other_dialects() {
+        let input = large_csv(20_000).replace(',', ";");
+        let parse = CsvParseOptions { dialect: Dialect { delimiter: b';', ..Default::default() }, ..Default::default() };
+        assert!(!splits_into_chunks(&input, &parse, 4));
+
+        let rows = parse_csv_parallel(&input, &parse, 4).unwrap();
+        assert_eq!(rows.len(), 20_001);
+        assert_eq!(rows.iter().next().unwrap().iter().collect::<Vec<_>>(), vec!["id", "name", "note"]);
+    }
+
+    #[test]
+    fn test_parallel_field_count_error_line() {
+        let mut input = large_csv(20_000);
+        input.push_str("1,2\n");
+
+        let err = parse_csv_parallel(&input, &CsvParseOptions::default(), 4).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::FieldCountMismatch));
+        assert!(err.to_string().contains("at line 20002"), "{}", err);
+    }
+}
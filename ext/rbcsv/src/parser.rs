@@ -1,21 +1,86 @@
+use crate::converter::{ColumnConverters, Converter};
 use crate::error::{CsvError, ErrorKind};
+use crate::filter::{RowFilter, RowSelector};
+use crate::gvl::check_interrupt;
+use crate::compression::{CompressedWriter, Compression};
+use crate::header::{HeaderOptions, HeaderValidation};
+use crate::rows::Rows;
+use crate::source::FileSource;
+use crate::trim::TrimConfig;
+use crate::value::CsvValue;
+use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
+/// 中断の要求を確認する間隔（レコード数）
+pub const INTERRUPT_CHECK_INTERVAL: usize = 1024;
+
+/// 書き込み用の一時ファイル名の重複を避けるための連番
+static TEMP_FILE_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// パースのオプション
+/// `RbCsv::Reader` やストリーミング読み込みで、行の読み込み方と変換方法をまとめて保持する
+#[derive(Debug, Clone, Default)]
 pub struct CsvParseOptions {
-    pub trim: bool,
+    /// 前後の空白の除去
+    pub trim: TrimConfig,
+    /// 1行目をヘッダーとして扱い、各行をヘッダー名をキーとするHashにする
+    pub headers: bool,
+    /// フィールドを数値・真偽値等に型変換する
+    pub typed: bool,
+    /// ヘッダー名の正規化・重複の扱い
+    pub header_options: HeaderOptions,
+    /// ヘッダー行の検証
+    pub validation: Option<HeaderValidation>,
+    /// 列ごとのコンバーター（`typed: true` の場合に使う）
+    pub converters: ColumnConverters,
+    /// 区切り文字・クォート文字・行区切り（ストリーミング読み込みでのみ使う）
+    pub dialect: Dialect,
+    /// 行の絞り込み条件（`where:`）
+    pub filter: Option<RowFilter>,
+}
+
+impl CsvParseOptions {
+    /// 1行目をヘッダー行として読むか（型変換せず、コンバーターの列名の解決と検証に使う）
+    pub fn reads_header_row(&self) -> bool {
+        self.headers || self.validation.is_some() || self.converters.uses_header_names()
+    }
 }
 
-impl Default for CsvParseOptions {
+/// CSVの書式
+#[derive(Debug, Clone, Copy)]
+pub struct Dialect {
+    /// 区切り文字（`col_sep:`）
+    pub delimiter: u8,
+    /// クォート文字（`quote_char:`）
+    pub quote: u8,
+    /// 行区切り（`row_sep:`。既定では `\r\n`・`\n`・`\r` のいずれも行区切りとして扱う）
+    pub terminator: csv::Terminator,
+    /// 行ごとにフィールド数が異なることを許可する
+    pub flexible: bool,
+}
+
+impl Default for Dialect {
     fn default() -> Self {
         Self {
-            trim: false,
+            delimiter: b',',
+            quote: b'"',
+            terminator: csv::Terminator::CRLF,
+            flexible: false,
         }
     }
 }
 
+impl Dialect {
+    /// 既定の書式か（並列パースのチャンク分割は既定の書式を前提とする）
+    pub fn is_default(&self) -> bool {
+        self.delimiter == b',' && self.quote == b'"' && matches!(self.terminator, csv::Terminator::CRLF) && !self.flexible
+    }
+}
+
 pub fn _escape_sanitize(s: &str) -> String {
     s.replace("\\n", "\n")
         .replace("\\r", "\r")
@@ -25,127 +90,235 @@ pub fn _escape_sanitize(s: &str) -> String {
 }
 
 /// 基本的なCSVパース処理
-pub fn parse_csv_core(input: &str, trim_config: csv::Trim) -> Result<Vec<Vec<String>>, CsvError> {
+/// `parse.validation` が指定された場合、1行目をヘッダー行として検証してから残りの行をパースする
+#[cfg(test)]
+pub fn parse_csv_core(input: &str, parse: &CsvParseOptions) -> Result<Vec<Vec<String>>, CsvError> {
+    parse_csv_rows(input, parse).map(Rows::into_vec)
+}
+
+/// CSVをパースし、行をまとめて1つのバッファに格納する
+/// フィールドごとの `String` を作らないため、Rubyの値に変換する場合はこちらを使う
+pub fn parse_csv_rows(input: &str, parse: &CsvParseOptions) -> Result<Rows, CsvError> {
     if input.trim().is_empty() {
         return Err(CsvError::empty_data());
     }
 
-    let mut reader = csv::ReaderBuilder::new()
-        .has_headers(false)
-        .trim(trim_config)
-        .from_reader(input.as_bytes());
-
-    let mut records = Vec::new();
+    parse_csv_rows_reader(input.as_bytes(), parse)
+}
 
-    for (line_num, result) in reader.records().enumerate() {
-        match result {
-            Ok(record) => {
-                let row: Vec<String> = record.iter().map(|field| field.to_string()).collect();
-                records.push(row);
-            }
-            Err(e) => {
-                if let csv::ErrorKind::UnequalLengths { expected_len, len, .. } = e.kind() {
-                    let error_msg = format!(
-                        "Field count mismatch at line {}: expected {} fields, got {} fields",
-                        line_num + 1,
-                        expected_len,
-                        len
-                    );
-                    return Err(CsvError::new(ErrorKind::FieldCountMismatch, error_msg));
-                }
-
-                return Err(CsvError::from(e));
+/// `std::io::Read` からCSVを読み込んでパースする
+/// 書式・trim・ヘッダー行の検証・絞り込み条件（`parse.filter`）を適用し、条件を満たさない行は格納しない
+pub fn parse_csv_rows_reader<R: Read>(input: R, parse: &CsvParseOptions) -> Result<Rows, CsvError> {
+    let mut reader = dialect_reader(input, &parse.dialect);
+    let mut record = csv::StringRecord::new();
+    let mut rows = Rows::new();
+    let mut selector = RowSelector::new(parse.filter.clone());
+    let mut line_num: usize = 0;
+
+    loop {
+        if line_num.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+            check_interrupt()?;
+        }
+        if !reader.read_record(&mut record).map_err(|e| record_error(line_num, e))? {
+            break;
+        }
+        parse.trim.apply(&mut record, line_num == 0);
+        if line_num == 0 {
+            if let Some(validation) = &parse.validation {
+                validation.validate(&record.iter().collect::<Vec<&str>>())?;
             }
         }
+        if selector.select(line_num, &record)? {
+            rows.push_record(&mut record);
+        }
+        line_num += 1;
     }
 
-    if records.is_empty() {
+    if line_num == 0 {
         return Err(CsvError::empty_data());
     }
 
-    Ok(records)
+    Ok(rows)
 }
 
-/// オプション設定を使ったCSV解析（文字列用）
-pub fn _parse_csv_with_options(input: &str, options: &CsvParseOptions) -> Result<Vec<Vec<String>>, CsvError> {
-    let trim_config = if options.trim { csv::Trim::All } else { csv::Trim::None };
-    parse_csv_core(input, trim_config)
+/// 指定の書式でCSVリーダーを作成する（ヘッダーなし）
+/// 前後の空白の除去は読み込んだ後に `TrimConfig::apply` で行う（ヘッダー行とそれ以外を区別するため）
+pub fn dialect_reader<R: Read>(input: R, dialect: &Dialect) -> csv::Reader<R> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(dialect.delimiter)
+        .quote(dialect.quote)
+        .terminator(dialect.terminator)
+        .flexible(dialect.flexible)
+        .from_reader(input)
 }
 
-/// オプション設定を使ったCSV解析（ファイル用）
-pub fn _parse_csv_file_with_options(file_path: &str, options: &CsvParseOptions) -> Result<Vec<Vec<String>>, CsvError> {
-    let trim_config = if options.trim { csv::Trim::All } else { csv::Trim::None };
-    parse_csv_file(file_path, trim_config)
+/// 1件ずつのレコードの読み込み（並べ替え・重複の除去・集計・プロファイル用）
+///
+/// 書式とtrimを適用し、一定の件数ごとに中断の要求を確認する。
+pub struct RecordReader<'a, R> {
+    reader: csv::Reader<R>,
+    trim: &'a TrimConfig,
+    line_num: usize,
 }
 
-/// ファイルからCSVを読み込んでパースする
-pub fn parse_csv_file(file_path: &str, trim_config: csv::Trim) -> Result<Vec<Vec<String>>, CsvError> {
-    // ファイルパスの検証
-    let path = Path::new(file_path);
-    if !path.exists() {
-        return Err(CsvError::io(format!("File not found: {}", file_path)));
-    }
+impl<'a, R: Read> RecordReader<'a, R> {
+    /// 読み込みを始める。`parse.headers` が真の場合は1行目をヘッダー行として読み込んで返す
+    /// ヘッダー行もない空の入力の場合はNone
+    pub fn open(input: R, parse: &'a CsvParseOptions) -> Result<Option<(Self, Option<csv::StringRecord>)>, CsvError> {
+        let mut records = Self { reader: dialect_reader(input, &parse.dialect), trim: &parse.trim, line_num: 0 };
+        if !parse.headers {
+            return Ok(Some((records, None)));
+        }
 
-    if !path.is_file() {
-        return Err(CsvError::io(format!("Path is not a file: {}", file_path)));
+        let mut header = csv::StringRecord::new();
+        Ok(records.read(&mut header)?.map(|_| (records, Some(header))))
     }
 
-    // ファイル読み込み
-    let content = match fs::read_to_string(path) {
-        Ok(content) => content,
-        Err(e) => {
-            return Err(CsvError::io(format!("Failed to read file '{}': {}", file_path, e)));
+    /// 次のレコードを読み込み、0始まりの行番号を返す（終端ならNone）
+    pub fn read(&mut self, record: &mut csv::StringRecord) -> Result<Option<usize>, CsvError> {
+        let line_num = self.line_num;
+        if line_num.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+            check_interrupt()?;
         }
-    };
+        if !self.reader.read_record(record).map_err(|e| record_error(line_num, e))? {
+            return Ok(None);
+        }
+        self.trim.apply(record, line_num == 0);
+        self.line_num += 1;
+
+        Ok(Some(line_num))
+    }
+}
+
+/// 指定の書式でCSVライターを作成する（ヘッダーなし）
+/// 行区切りは `row_sep:` で1バイトの文字を指定した場合はその文字、それ以外は `\n` にする
+pub fn dialect_writer<W: Write>(output: W, dialect: &Dialect) -> csv::Writer<W> {
+    let mut builder = csv::WriterBuilder::new();
+    builder
+        .has_headers(false)
+        .delimiter(dialect.delimiter)
+        .quote(dialect.quote)
+        .flexible(dialect.flexible);
+    if let csv::Terminator::Any(byte) = dialect.terminator {
+        builder.terminator(csv::Terminator::Any(byte));
+    }
+    builder.from_writer(output)
+}
+
+/// csvクレートのエラーをCsvErrorに変換する
+/// 列数の不一致は行番号（1始まり）付きのメッセージにする
+pub fn record_error(line_num: usize, e: csv::Error) -> CsvError {
+    if let csv::ErrorKind::UnequalLengths { expected_len, len, .. } = e.kind() {
+        return field_count_error(line_num, *expected_len as usize, *len as usize);
+    }
+
+    CsvError::from(e)
+}
+
+/// 列数の不一致のエラーを作成する（`line_num` は0始まり）
+pub fn field_count_error(line_num: usize, expected_len: usize, len: usize) -> CsvError {
+    let error_msg = format!(
+        "Field count mismatch at line {}: expected {} fields, got {} fields",
+        line_num + 1,
+        expected_len,
+        len
+    );
+    CsvError::new(ErrorKind::FieldCountMismatch, error_msg)
+}
+
+/// 1行分のフィールドを型認識する
+/// `column_converters` に登録された列はコンバーターで変換する
+pub fn typed_record<C: Borrow<Converter>>(
+    record: &csv::StringRecord,
+    column_converters: &HashMap<usize, C>,
+    trim_fields: bool,
+) -> Vec<CsvValue> {
+    record
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match column_converters.get(&index) {
+            Some(converter) if trim_fields => converter.borrow().convert(field.trim()),
+            Some(converter) => converter.borrow().convert(field),
+            None if trim_fields => CsvValue::from_str_trimmed(field),
+            None => CsvValue::from_str(field),
+        })
+        .collect()
+}
+
+/// ファイルからCSVを読み込んでパースする
+#[cfg(test)]
+pub fn parse_csv_file(file_path: &str, parse: &CsvParseOptions) -> Result<Vec<Vec<String>>, CsvError> {
+    parse_csv_file_rows(file_path, parse).map(Rows::into_vec)
+}
+
+/// ファイルからCSVを読み込み、行をまとめて1つのバッファに格納する
+pub fn parse_csv_file_rows(file_path: &str, parse: &CsvParseOptions) -> Result<Rows, CsvError> {
+    // ファイル読み込み（メモリマップ。圧縮されていれば展開する）
+    let source = FileSource::open(file_path)?;
 
     // CSVパース
-    parse_csv_core(&content, trim_config)
+    parse_csv_rows(source.as_str(), parse)
 }
 
 /// 型認識を行うCSVパース処理
-pub fn parse_csv_typed(input: &str, trim_config: csv::Trim) -> Result<Vec<Vec<crate::value::CsvValue>>, CsvError> {
-    use crate::value::CsvValue;
-
+///
+/// `parse.converters` に登録された列はコンバーターで変換し、それ以外は通常の型認識を行う。
+/// `parse.headers` が真、またはヘッダー名で指定されたコンバーターがある場合は1行目をヘッダーとして扱い、
+/// 型変換せず文字列のまま返す。`parse.validation` が指定された場合は1行目を検証してから残りの行をパースする。
+/// `parse.filter` が指定された場合、条件を満たさない行は型変換せずに読み飛ばす。
+pub fn parse_csv_typed(input: &str, parse: &CsvParseOptions) -> Result<Vec<Vec<CsvValue>>, CsvError> {
     if input.trim().is_empty() {
         return Err(CsvError::empty_data());
     }
 
-    let mut reader = csv::ReaderBuilder::new()
-        .has_headers(false)
-        .trim(trim_config)
-        .from_reader(input.as_bytes());
+    parse_csv_typed_reader(input.as_bytes(), parse)
+}
 
+/// `std::io::Read` からCSVを読み込んで型認識する（`parse_csv_typed` と同じ規則）
+pub fn parse_csv_typed_reader<R: Read>(input: R, parse: &CsvParseOptions) -> Result<Vec<Vec<CsvValue>>, CsvError> {
+    let mut reader = dialect_reader(input, &parse.dialect);
+    let (converters, validation) = (&parse.converters, parse.validation.as_ref());
+
+    let trim_fields = parse.trim.trims_fields();
+    let mut resolved = if parse.reads_header_row() {
+        None
+    } else {
+        Some(converters.resolve(None, &parse.header_options)?)
+    };
     let mut records = Vec::new();
+    let mut selector = RowSelector::new(parse.filter.clone());
+    let mut line_count = 0;
 
     for (line_num, result) in reader.records().enumerate() {
-        match result {
-            Ok(record) => {
-                let row: Vec<CsvValue> = record.iter().map(|field| {
-                    if matches!(trim_config, csv::Trim::All | csv::Trim::Fields) {
-                        CsvValue::from_str_trimmed(field)
-                    } else {
-                        CsvValue::from_str(field)
-                    }
-                }).collect();
-                records.push(row);
-            }
-            Err(e) => {
-                if let csv::ErrorKind::UnequalLengths { expected_len, len, .. } = e.kind() {
-                    let error_msg = format!(
-                        "Field count mismatch at line {}: expected {} fields, got {} fields",
-                        line_num + 1,
-                        expected_len,
-                        len
-                    );
-                    return Err(CsvError::new(ErrorKind::FieldCountMismatch, error_msg));
-                }
-
-                return Err(CsvError::from(e));
-            }
+        if line_num.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+            check_interrupt()?;
+        }
+        let mut record = result.map_err(|e| record_error(line_num, e))?;
+        parse.trim.apply(&mut record, line_num == 0);
+        line_count += 1;
+        let selected = match &resolved {
+            Some(column_converters) => selector.select_converted(line_num, &record, column_converters, trim_fields)?,
+            None => selector.select(line_num, &record)?,
+        };
+        if !selected {
+            continue;
         }
+
+        let Some(column_converters) = &resolved else {
+            // ヘッダー行からコンバーターの対象列を解決する
+            if let Some(validation) = validation {
+                validation.validate(&record.iter().collect::<Vec<&str>>())?;
+            }
+            resolved = Some(converters.resolve(Some(&record), &parse.header_options)?);
+            records.push(record.iter().map(|field| CsvValue::String(field.to_string())).collect());
+            continue;
+        };
+
+        records.push(typed_record(&record, column_converters, trim_fields));
     }
 
-    if records.is_empty() {
+    if line_count == 0 {
         return Err(CsvError::empty_data());
     }
 
@@ -153,24 +326,10 @@ pub fn parse_csv_typed(input: &str, trim_config: csv::Trim) -> Result<Vec<Vec<cr
 }
 
 /// 型認識を行うCSVファイル読み込み処理
-pub fn parse_csv_file_typed(file_path: &str, trim_config: csv::Trim) -> Result<Vec<Vec<crate::value::CsvValue>>, CsvError> {
-    let path = Path::new(file_path);
-    if !path.exists() {
-        return Err(CsvError::io(format!("File not found: {}", file_path)));
-    }
+pub fn parse_csv_file_typed(file_path: &str, parse: &CsvParseOptions) -> Result<Vec<Vec<CsvValue>>, CsvError> {
+    let source = FileSource::open(file_path)?;
 
-    if !path.is_file() {
-        return Err(CsvError::io(format!("Path is not a file: {}", file_path)));
-    }
-
-    let content = match fs::read_to_string(path) {
-        Ok(content) => content,
-        Err(e) => {
-            return Err(CsvError::io(format!("Failed to read file '{}': {}", file_path, e)));
-        }
-    };
-
-    parse_csv_typed(&content, trim_config)
+    parse_csv_typed(source.as_str(), parse)
 }
 
 #[cfg(test)]
@@ -180,7 +339,7 @@ mod tests {
     #[test]
     fn test_parse_csv_core_basic() {
         let csv_data = "a,b,c\n1,2,3";
-        let result = parse_csv_core(csv_data, csv::Trim::None);
+        let result = parse_csv_core(csv_data, &CsvParseOptions::default());
 
         assert!(result.is_ok());
         let records = result.unwrap();
@@ -191,7 +350,7 @@ mod tests {
 
     #[test]
     fn test_parse_csv_file_not_found() {
-        let result = parse_csv_file("non_existent_file.csv", csv::Trim::None);
+        let result = parse_csv_file("non_existent_file.csv", &CsvParseOptions::default());
 
         assert!(result.is_err());
         if let Err(e) = result {
@@ -202,7 +361,7 @@ mod tests {
     #[test]
     fn test_parse_csv_file_directory() {
         // ディレクトリを指定した場合のテスト
-        let result = parse_csv_file(".", csv::Trim::None);
+        let result = parse_csv_file(".", &CsvParseOptions::default());
 
         assert!(result.is_err());
         if let Err(e) = result {
@@ -225,7 +384,7 @@ mod tests {
         }
 
         // ファイルからCSVを読み込み
-        let result = parse_csv_file(temp_path, csv::Trim::None);
+        let result = parse_csv_file(temp_path, &CsvParseOptions::default());
 
         // クリーンアップ
         let _ = std::fs::remove_file(temp_path);
@@ -239,6 +398,96 @@ mod tests {
         assert_eq!(records[2], vec!["Bob", "30", "Osaka"]);
     }
 
+    #[test]
+    fn test_parse_csv_typed_with_header_converters() {
+        use crate::converter::{ColumnKey, Converter};
+        use crate::value::CsvValue;
+
+        let mut converters = ColumnConverters::new();
+        converters.add(ColumnKey::Name("price".to_string()), Converter::StripPrefix("$".to_string()));
+        converters.add(ColumnKey::Index(0), Converter::Custom(0));
+
+        let result = parse_csv_typed("name,price\nApple,$1.5", &CsvParseOptions { converters, ..Default::default() }).unwrap();
+        assert_eq!(result[0], vec![CsvValue::String("name".to_string()), CsvValue::String("price".to_string())]);
+        assert_eq!(
+            result[1],
+            vec![
+                CsvValue::Deferred { converter: 0, raw: "Apple".to_string() },
+                CsvValue::Float(1.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_typed_keeps_header_row_as_strings() {
+        use crate::value::CsvValue;
+
+        let result = parse_csv_typed("2024,2025\n1,2", &CsvParseOptions { headers: true, ..Default::default() }).unwrap();
+        assert_eq!(result[0], vec![CsvValue::String("2024".to_string()), CsvValue::String("2025".to_string())]);
+        assert_eq!(result[1], vec![CsvValue::Integer(1), CsvValue::Integer(2)]);
+    }
+
+    #[test]
+    fn test_parse_csv_core_header_mismatch() {
+        let validation = HeaderValidation {
+            required: vec!["name".to_string(), "email".to_string()],
+            ..Default::default()
+        };
+
+        let result = parse_csv_core("name,age\nAlice,25", &CsvParseOptions { validation: Some(validation), ..Default::default() });
+        assert!(result.is_err());
+        if let Err(e) = result {
+            assert!(e.to_string().contains("missing [email]"));
+        }
+    }
+
+    #[test]
+    fn test_dialect_reader() {
+        let dialect = Dialect {
+            delimiter: b';',
+            quote: b'\'',
+            terminator: csv::Terminator::Any(b'|'),
+            flexible: true,
+        };
+        let mut reader = dialect_reader("a;'b;c'|1|x;y;z".as_bytes(), &dialect);
+        let records: Vec<Vec<String>> = reader
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect();
+        assert_eq!(records, vec![vec!["a", "b;c"], vec!["1"], vec!["x", "y", "z"]]);
+
+        let mut strict = dialect_reader("a,b\n1\n".as_bytes(), &Dialect::default());
+        assert!(strict.records().any(|record| record.is_err()));
+    }
+
+    #[test]
+    fn test_parse_csv_reader_matches_core() {
+        let csv_data = "a,b\n\"x\ny\",2\n";
+        let from_reader = parse_csv_rows_reader(std::io::Cursor::new(csv_data), &CsvParseOptions::default()).unwrap();
+        assert_eq!(from_reader.into_vec(), parse_csv_core(csv_data, &CsvParseOptions::default()).unwrap());
+
+        let typed = parse_csv_typed_reader(csv_data.as_bytes(), &CsvParseOptions { headers: true, ..Default::default() })
+            .unwrap();
+        assert_eq!(typed[1], vec![CsvValue::String("x\ny".to_string()), CsvValue::Integer(2)]);
+    }
+
+    #[test]
+    fn test_parse_with_dialect() {
+        let dialect = Dialect { delimiter: b'\t', quote: b'\'', ..Default::default() };
+        let parse = CsvParseOptions { dialect, ..Default::default() };
+        let rows = parse_csv_core("a\t'b\tc'\n1\t2\n", &parse).unwrap();
+        assert_eq!(rows, vec![vec!["a", "b\tc"], vec!["1", "2"]]);
+
+        let typed = parse_csv_typed("1\t2.5\n", &parse).unwrap();
+        assert_eq!(typed, vec![vec![CsvValue::Integer(1), CsvValue::Float(2.5)]]);
+    }
+
+    #[test]
+    fn test_parse_csv_reader_empty() {
+        let result = parse_csv_rows_reader(std::io::empty(), &CsvParseOptions::default());
+        assert!(matches!(result.unwrap_err().kind(), ErrorKind::EmptyData));
+    }
+
     #[test]
     fn test_write_csv_file_basic() {
 
@@ -250,7 +499,7 @@ mod tests {
         ];
 
         // ファイルに書き込み
-        let result = write_csv_file(temp_path, &test_data);
+        let result = write_csv_file(temp_path, &test_data, Compression::None);
         assert!(result.is_ok(), "Write should succeed");
 
         // 書き込んだファイルを読み込んで検証
@@ -262,12 +511,76 @@ mod tests {
         let _ = std::fs::remove_file(temp_path);
     }
 
+    #[test]
+    fn test_write_and_read_compressed_file() {
+        let data = vec![
+            vec!["id".to_string(), "name".to_string()],
+            vec!["1".to_string(), "Alice".to_string()],
+        ];
+
+        for (temp_path, compression) in [
+            ("/tmp/test_write_csv.csv.gz", Compression::Gzip),
+            ("/tmp/test_write_csv.csv.zst", Compression::Zstd),
+        ] {
+            write_csv_file(temp_path, &data, compression).unwrap();
+            assert_eq!(parse_csv_file(temp_path, &CsvParseOptions::default()).unwrap(), data);
+            let _ = std::fs::remove_file(temp_path);
+        }
+    }
+
+    #[test]
+    fn test_write_csv_file_with_keeps_file_on_error() {
+        let temp_path = "/tmp/test_write_csv_with.csv";
+        std::fs::write(temp_path, "id\n1\n").unwrap();
+
+        // 失敗した場合は既存のファイルを変更せず、一時ファイルも残さない
+        let result = write_csv_file_with(temp_path, Compression::None, |output| {
+            output.write_all(b"partial").map_err(|e| CsvError::io(e.to_string()))?;
+            Err::<(), _>(CsvError::invalid_data("failed"))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(temp_path).unwrap(), "id\n1\n");
+        let leftovers = std::fs::read_dir("/tmp")
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(".test_write_csv_with.csv."))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        // 読み込み中のファイルに書き込んでも、置き換えるまで元の内容を読める
+        let copied = write_csv_file_with(temp_path, Compression::None, |output| {
+            let input = std::fs::read(temp_path).map_err(|e| CsvError::io(e.to_string()))?;
+            output.write_all(&input).map_err(|e| CsvError::io(e.to_string()))?;
+            Ok(input.len())
+        });
+        assert_eq!(copied.unwrap(), 5);
+        assert_eq!(std::fs::read_to_string(temp_path).unwrap(), "id\n1\n");
+
+        let _ = std::fs::remove_file(temp_path);
+    }
+
+    #[test]
+    fn test_write_csv_rows() {
+        let data = vec![
+            vec!["name".to_string(), "note".to_string()],
+            vec!["Alice".to_string(), "a, b".to_string()],
+        ];
+        let mut output = Vec::new();
+        write_csv_rows(&data, &mut output).unwrap();
+        assert_eq!(output, b"name,note\nAlice,\"a, b\"\n");
+
+        let uneven = vec![vec!["a".to_string()], vec!["b".to_string(), "c".to_string()]];
+        let mut output = Vec::new();
+        assert!(write_csv_rows(&uneven, &mut output).is_err());
+        assert!(output.is_empty());
+    }
+
     #[test]
     fn test_write_csv_file_empty_data() {
         let temp_path = "/tmp/test_write_empty.csv";
         let empty_data: Vec<Vec<String>> = vec![];
 
-        let result = write_csv_file(temp_path, &empty_data);
+        let result = write_csv_file(temp_path, &empty_data, Compression::None);
         assert!(result.is_err());
         if let Err(e) = result {
             assert!(e.to_string().contains("CSV data is empty"));
@@ -282,7 +595,7 @@ mod tests {
             vec!["Alice".to_string(), "25".to_string(), "Tokyo".to_string()], // 3 fields instead of 2
         ];
 
-        let result = write_csv_file(temp_path, &inconsistent_data);
+        let result = write_csv_file(temp_path, &inconsistent_data, Compression::None);
         assert!(result.is_err());
         if let Err(e) = result {
             assert!(e.to_string().contains("Field count mismatch"));
@@ -292,7 +605,7 @@ mod tests {
     #[test]
     fn test_write_csv_file_permission_denied() {
         // 書き込み権限のないパスをテスト（rootディレクトリ）
-        let result = write_csv_file("/root/test.csv", &vec![vec!["test".to_string()]]);
+        let result = write_csv_file("/root/test.csv", &vec![vec!["test".to_string()]], Compression::None);
         assert!(result.is_err());
         if let Err(e) = result {
             // Permission deniedまたはParent directory does not existのいずれかになる
@@ -303,7 +616,110 @@ mod tests {
 }
 
 /// CSVデータをファイルに書き込む
-pub fn write_csv_file(file_path: &str, data: &[Vec<String>]) -> Result<(), CsvError> {
+/// `compression` に応じてgzip/zstdで圧縮する
+pub fn write_csv_file(file_path: &str, data: &[Vec<String>], compression: Compression) -> Result<(), CsvError> {
+    validate_rows(data)?;
+
+    // CSV Writer作成とデータ書き込み
+    let file = create_csv_file(file_path)?;
+    let output = CompressedWriter::new(file, compression)
+        .map_err(|e| CsvError::io(format!("Failed to create file '{}': {}", file_path, e)))?;
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(output);
+
+    // データ書き込み
+    for row in data {
+        if let Err(e) = writer.write_record(row) {
+            return Err(CsvError::from(e));
+        }
+    }
+
+    // ファイルフラッシュ：データの確実な書き込み保証（圧縮ストリームの終端を含む）
+    let flushed = writer
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(|output| output.finish());
+    if let Err(e) = flushed {
+        return Err(CsvError::io(format!("Failed to flush data to file '{}': {}", file_path, e)));
+    }
+
+    Ok(())
+}
+
+/// CSVファイルを作成し、`f` で書き込む
+/// `compression` に応じてgzip/zstdで圧縮し、最後に圧縮ストリームを終端する
+///
+/// 同じディレクトリの一時ファイルに書き込んでから `file_path` に置き換えるため、
+/// 書き込みに失敗した場合や中断された場合は既存のファイルを変更しない（`f` が同じファイルを読み込んでいてもよい）。
+pub fn write_csv_file_with<T>(
+    file_path: &str,
+    compression: Compression,
+    f: impl FnOnce(&mut dyn Write) -> Result<T, CsvError>,
+) -> Result<T, CsvError> {
+    let (temp_path, file) = create_temp_file(file_path)?;
+    let result = write_compressed(file, file_path, compression, f).and_then(|result| {
+        fs::rename(&temp_path, file_path)
+            .map_err(|e| CsvError::io(format!("Failed to create file '{}': {}", file_path, e)))?;
+        Ok(result)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+fn write_compressed<T>(
+    file: fs::File,
+    file_path: &str,
+    compression: Compression,
+    f: impl FnOnce(&mut dyn Write) -> Result<T, CsvError>,
+) -> Result<T, CsvError> {
+    let mut output = CompressedWriter::new(file, compression)
+        .map_err(|e| CsvError::io(format!("Failed to create file '{}': {}", file_path, e)))?;
+    let result = f(&mut output)?;
+    output
+        .finish()
+        .map_err(|e| CsvError::io(format!("Failed to flush data to file '{}': {}", file_path, e)))?;
+
+    Ok(result)
+}
+
+/// `file_path` と同じディレクトリに書き込み用の一時ファイルを作成する
+fn create_temp_file(file_path: &str) -> Result<(PathBuf, fs::File), CsvError> {
+    let path = Path::new(file_path);
+    let Some(file_name) = path.file_name() else {
+        return Err(CsvError::io(format!("Failed to create file '{}': not a file path", file_path)));
+    };
+    let sequence = TEMP_FILE_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let temp_path = path.with_file_name(format!(
+        ".{}.rbcsv-{}-{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        sequence
+    ));
+    let file = open_csv_file(&temp_path, file_path, fs::OpenOptions::new().write(true).create_new(true))?;
+
+    Ok((temp_path, file))
+}
+
+/// CSVデータを `output` に書き出す（RubyのIOへの書き込み用。圧縮は呼び出し元で行う）
+pub fn write_csv_rows(data: &[Vec<String>], output: &mut dyn Write) -> Result<(), CsvError> {
+    validate_rows(data)?;
+
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(output);
+    for row in data {
+        writer.write_record(row)?;
+    }
+
+    writer.flush().map_err(|e| CsvError::io(e.to_string()))
+}
+
+/// 書き込むデータを検証する（空配列、各行のフィールド数の一貫性）
+fn validate_rows(data: &[Vec<String>]) -> Result<(), CsvError> {
     // データ検証：空配列チェック
     if data.is_empty() {
         return Err(CsvError::invalid_data("CSV data is empty"));
@@ -325,40 +741,30 @@ pub fn write_csv_file(file_path: &str, data: &[Vec<String>]) -> Result<(), CsvEr
         }
     }
 
+    Ok(())
+}
+
+/// 書き込み用にCSVファイルを作成する
+pub fn create_csv_file(file_path: &str) -> Result<fs::File, CsvError> {
+    open_csv_file(Path::new(file_path), file_path, fs::OpenOptions::new().write(true).create(true).truncate(true))
+}
+
+/// `path` を開く（エラーのメッセージには `file_path` を使う）
+fn open_csv_file(path: &Path, file_path: &str, options: &fs::OpenOptions) -> Result<fs::File, CsvError> {
     // ファイルパス検証：親ディレクトリの存在確認
-    let path = Path::new(file_path);
     if let Some(parent) = path.parent() {
         if !parent.exists() {
             return Err(CsvError::io(format!("Parent directory does not exist: {}", parent.display())));
         }
     }
 
-    // CSV Writer作成とデータ書き込み
-    let file = match fs::File::create(path) {
-        Ok(file) => file,
+    match options.open(path) {
+        Ok(file) => Ok(file),
         Err(e) => {
             if e.kind() == std::io::ErrorKind::PermissionDenied {
                 return Err(CsvError::write_permission(format!("Permission denied: {}", file_path)));
             }
-            return Err(CsvError::io(format!("Failed to create file '{}': {}", file_path, e)));
-        }
-    };
-
-    let mut writer = csv::WriterBuilder::new()
-        .has_headers(false)
-        .from_writer(file);
-
-    // データ書き込み
-    for row in data {
-        if let Err(e) = writer.write_record(row) {
-            return Err(CsvError::from(e));
+            Err(CsvError::io(format!("Failed to create file '{}': {}", file_path, e)))
         }
     }
-
-    // ファイルフラッシュ：データの確実な書き込み保証
-    if let Err(e) = writer.flush() {
-        return Err(CsvError::io(format!("Failed to flush data to file '{}': {}", file_path, e)));
-    }
-
-    Ok(())
 }
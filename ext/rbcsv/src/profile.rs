@@ -0,0 +1,436 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use crate::error::CsvError;
+use crate::parser::{CsvParseOptions, RecordReader};
+use crate::value::CsvValue;
+
+/// HyperLogLogのレジスタ数の指数（2^14個、標準誤差は約0.8%）
+const HLL_PRECISION: u32 = 14;
+
+/// 近似モードで出現回数を数える値の数の上限（Misra-Griesの要約の大きさ）
+const HEAVY_HITTER_CAPACITY: usize = 1024;
+
+/// 異なる値の数の数え方（`distinct:`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DistinctMode {
+    /// すべての値を保持して正確に数える
+    #[default]
+    Exact,
+    /// HyperLogLogで推定する（メモリ使用量は列ごとに一定）
+    Approximate,
+}
+
+impl DistinctMode {
+    /// Rubyのオプション名（`:exact`・`:approximate`）から変換する
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exact" => Some(DistinctMode::Exact),
+            "approximate" => Some(DistinctMode::Approximate),
+            _ => None,
+        }
+    }
+}
+
+/// 集計の設定
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileOptions {
+    pub distinct: DistinctMode,
+    /// 出現回数の多い値をいくつ返すか（`top:`）
+    pub top: usize,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        Self { distinct: DistinctMode::Exact, top: 5 }
+    }
+}
+
+/// 推定した列の型（`CsvValue::from_str` と同じ規則で、空でない値がすべて変換できる型）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Integer,
+    Float,
+    String,
+    /// 空でない値がない
+    Nil,
+}
+
+impl ColumnType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::String => "string",
+            ColumnType::Nil => "nil",
+        }
+    }
+
+    /// 列の値をこの型の値に変換する（`top` の値に使う）
+    pub fn value(&self, raw: &str) -> CsvValue {
+        match self {
+            ColumnType::Integer => CsvValue::from_str(raw),
+            ColumnType::Float => raw.parse().map(CsvValue::Float).unwrap_or_else(|_| CsvValue::from_str(raw)),
+            _ => CsvValue::String(raw.to_string()),
+        }
+    }
+}
+
+/// 1列分の集計結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    /// ヘッダー名（ヘッダーがない場合はNone）
+    pub name: Option<String>,
+    pub column_type: ColumnType,
+    /// 空でない値の数
+    pub count: usize,
+    /// 空の値（行にフィールドがない場合を含む）の数
+    pub nulls: usize,
+    /// 異なる値の数（近似モードでは推定値）
+    pub distinct: usize,
+    /// 最小値・最大値（数値の列は数値、それ以外は文字列として比較する）
+    pub min: Option<CsvValue>,
+    pub max: Option<CsvValue>,
+    /// 数値の列の平均と標本標準偏差
+    pub mean: Option<f64>,
+    pub stddev: Option<f64>,
+    /// 値の最大の文字数
+    pub max_length: usize,
+    /// 出現回数の多い値と回数（近似モードでは回数は下限値）
+    pub top: Vec<(String, usize)>,
+}
+
+/// CSVを1行ずつ読み込み、列ごとの統計を集計する
+///
+/// 全体をメモリに読み込まないため、大きなファイルでも一定のメモリで処理できる
+/// （`DistinctMode::Exact` の場合は列ごとに異なる値を保持する）。
+///
+/// # Arguments
+/// * `input` - CSVの読み込み元
+/// * `parse` - 書式・ヘッダー・trimの設定（`headers` が真の場合は1行目を列名として扱う）
+/// * `options` - 集計の設定
+pub fn profile_reader<R: Read>(
+    input: R,
+    parse: &CsvParseOptions,
+    options: &ProfileOptions,
+) -> Result<Vec<ColumnProfile>, CsvError> {
+    // 空の入力（ヘッダー行もない）は列がない
+    let Some((mut records, header)) = RecordReader::open(input, parse)? else {
+        return Ok(Vec::new());
+    };
+    let names: Vec<String> = header.iter().flat_map(|header| header.iter().map(str::to_string)).collect();
+    let mut columns: Vec<ColumnStats> = names.iter().map(|_| ColumnStats::new(options.distinct)).collect();
+    let mut record = csv::StringRecord::new();
+    let mut rows = 0;
+
+    while records.read(&mut record)?.is_some() {
+        if columns.len() < record.len() {
+            columns.resize_with(record.len(), || ColumnStats::new(options.distinct));
+        }
+        for (stats, field) in columns.iter_mut().zip(record.iter()) {
+            stats.push(field);
+        }
+        rows += 1;
+    }
+
+    let mut names = names.into_iter();
+    Ok(columns
+        .into_iter()
+        .map(|stats| stats.finish(names.next(), rows, options.top))
+        .collect())
+}
+
+/// 1列分の集計の途中経過
+struct ColumnStats {
+    integers: usize,
+    floats: usize,
+    strings: usize,
+    int_min: i64,
+    int_max: i64,
+    num_min: f64,
+    num_max: f64,
+    /// 数値の平均と偏差の二乗和（Welfordの方法で逐次更新する）
+    mean: f64,
+    m2: f64,
+    text_min: Option<String>,
+    text_max: Option<String>,
+    max_length: usize,
+    counter: ValueCounter,
+}
+
+impl ColumnStats {
+    fn new(mode: DistinctMode) -> Self {
+        Self {
+            integers: 0,
+            floats: 0,
+            strings: 0,
+            int_min: i64::MAX,
+            int_max: i64::MIN,
+            num_min: f64::INFINITY,
+            num_max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+            text_min: None,
+            text_max: None,
+            max_length: 0,
+            counter: ValueCounter::new(mode),
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.integers + self.floats + self.strings
+    }
+
+    fn push(&mut self, field: &str) {
+        // 空の値は `finish` で行数から数える
+        if field.is_empty() {
+            return;
+        }
+
+        match CsvValue::from_str(field) {
+            CsvValue::Integer(i) => {
+                self.integers += 1;
+                self.int_min = self.int_min.min(i);
+                self.int_max = self.int_max.max(i);
+                self.push_number(i as f64);
+            }
+            CsvValue::Float(f) => {
+                self.floats += 1;
+                self.push_number(f);
+            }
+            _ => self.strings += 1,
+        }
+
+        if self.text_min.as_deref().is_none_or(|min| field < min) {
+            self.text_min = Some(field.to_string());
+        }
+        if self.text_max.as_deref().is_none_or(|max| field > max) {
+            self.text_max = Some(field.to_string());
+        }
+        self.max_length = self.max_length.max(field.chars().count());
+        self.counter.insert(field);
+    }
+
+    fn push_number(&mut self, value: f64) {
+        let n = (self.integers + self.floats) as f64;
+        let delta = value - self.mean;
+        self.mean += delta / n;
+        self.m2 += delta * (value - self.mean);
+        self.num_min = self.num_min.min(value);
+        self.num_max = self.num_max.max(value);
+    }
+
+    /// 集計結果にする（`rows` は行数。フィールドが足りなかった行は空の値として数える）
+    fn finish(self, name: Option<String>, rows: usize, top: usize) -> ColumnProfile {
+        let count = self.count();
+        let column_type = if self.strings > 0 {
+            ColumnType::String
+        } else if self.floats > 0 {
+            ColumnType::Float
+        } else if self.integers > 0 {
+            ColumnType::Integer
+        } else {
+            ColumnType::Nil
+        };
+
+        let (min, max) = match column_type {
+            ColumnType::Integer => (Some(CsvValue::Integer(self.int_min)), Some(CsvValue::Integer(self.int_max))),
+            ColumnType::Float => (Some(CsvValue::Float(self.num_min)), Some(CsvValue::Float(self.num_max))),
+            _ => (self.text_min.map(CsvValue::String), self.text_max.map(CsvValue::String)),
+        };
+        let numeric = matches!(column_type, ColumnType::Integer | ColumnType::Float);
+        let mean = numeric.then_some(self.mean);
+        let stddev = (numeric && count > 1).then(|| (self.m2 / (count - 1) as f64).sqrt());
+
+        ColumnProfile {
+            name,
+            column_type,
+            count,
+            nulls: rows - count,
+            distinct: self.counter.distinct(),
+            min,
+            max,
+            mean,
+            stddev,
+            max_length: self.max_length,
+            top: self.counter.top(top),
+        }
+    }
+}
+
+/// 値ごとの出現回数
+enum ValueCounter {
+    Exact(HashMap<String, usize>),
+    Approximate { distinct: HyperLogLog, frequent: HashMap<String, usize> },
+}
+
+impl ValueCounter {
+    fn new(mode: DistinctMode) -> Self {
+        match mode {
+            DistinctMode::Exact => ValueCounter::Exact(HashMap::new()),
+            DistinctMode::Approximate => ValueCounter::Approximate {
+                distinct: HyperLogLog::new(),
+                frequent: HashMap::new(),
+            },
+        }
+    }
+
+    fn insert(&mut self, value: &str) {
+        match self {
+            ValueCounter::Exact(counts) => increment(counts, value),
+            ValueCounter::Approximate { distinct, frequent } => {
+                distinct.insert(value);
+                // Misra-Gries: 上限に達した場合はすべての回数を1減らし、0になった値を捨てる
+                if frequent.len() < HEAVY_HITTER_CAPACITY || frequent.contains_key(value) {
+                    increment(frequent, value);
+                } else {
+                    frequent.retain(|_, count| {
+                        *count -= 1;
+                        *count > 0
+                    });
+                }
+            }
+        }
+    }
+
+    fn distinct(&self) -> usize {
+        match self {
+            ValueCounter::Exact(counts) => counts.len(),
+            ValueCounter::Approximate { distinct, .. } => distinct.estimate(),
+        }
+    }
+
+    /// 出現回数の多い順（同数の場合は値の順）に `limit` 件返す
+    fn top(self, limit: usize) -> Vec<(String, usize)> {
+        let counts = match self {
+            ValueCounter::Exact(counts) => counts,
+            ValueCounter::Approximate { frequent, .. } => frequent,
+        };
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        counts.truncate(limit);
+        counts
+    }
+}
+
+fn increment(counts: &mut HashMap<String, usize>, value: &str) {
+    match counts.get_mut(value) {
+        Some(count) => *count += 1,
+        None => {
+            counts.insert(value.to_string(), 1);
+        }
+    }
+}
+
+/// 異なる値の数を一定のメモリで推定するHyperLogLog
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        Self { registers: vec![0; 1 << HLL_PRECISION] }
+    }
+
+    fn insert(&mut self, value: &str) {
+        // 鍵を指定しないSipHashのため、同じ入力からは常に同じ推定値になる
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // 残りのビットが0だけの場合でも順位が64 - 精度を超えないように番兵のビットを立てる
+        let rest = (hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    fn estimate(&self) -> usize {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&rank| 2f64.powi(-(rank as i32))).sum();
+        let raw = alpha * m * m / sum;
+
+        // 値が少ない場合は空のレジスタの数から推定する（Linear Counting）
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(input: &str, headers: bool, options: ProfileOptions) -> Vec<ColumnProfile> {
+        let parse = CsvParseOptions { headers, ..Default::default() };
+        profile_reader(input.as_bytes(), &parse, &options).unwrap()
+    }
+
+    #[test]
+    fn test_profile_columns() {
+        let input = "id,price,name,note\n1,1.5,Apple,\n2,2.5,Banana,\n3,,Apple,\n";
+        let columns = profile(input, true, ProfileOptions::default());
+        assert_eq!(columns.len(), 4);
+
+        let id = &columns[0];
+        assert_eq!(id.name.as_deref(), Some("id"));
+        assert_eq!(id.column_type, ColumnType::Integer);
+        assert_eq!((id.count, id.nulls, id.distinct), (3, 0, 3));
+        assert_eq!((id.min.clone(), id.max.clone()), (Some(CsvValue::Integer(1)), Some(CsvValue::Integer(3))));
+        assert_eq!(id.mean, Some(2.0));
+        assert_eq!(id.stddev, Some(1.0));
+
+        let price = &columns[1];
+        assert_eq!(price.column_type, ColumnType::Float);
+        assert_eq!((price.count, price.nulls), (2, 1));
+        assert_eq!(price.max, Some(CsvValue::Float(2.5)));
+        assert_eq!(price.mean, Some(2.0));
+
+        let name = &columns[2];
+        assert_eq!(name.column_type, ColumnType::String);
+        assert_eq!(name.distinct, 2);
+        assert_eq!(name.min, Some(CsvValue::String("Apple".to_string())));
+        assert_eq!(name.max_length, 6);
+        assert_eq!(name.mean, None);
+        assert_eq!(name.top, vec![("Apple".to_string(), 2), ("Banana".to_string(), 1)]);
+
+        let note = &columns[3];
+        assert_eq!(note.column_type, ColumnType::Nil);
+        assert_eq!((note.count, note.nulls, note.distinct), (0, 3, 0));
+        assert_eq!((note.min.clone(), note.stddev), (None, None));
+    }
+
+    #[test]
+    fn test_profile_without_headers() {
+        let columns = profile("1,a\n2,b\n", false, ProfileOptions { top: 1, ..Default::default() });
+        assert_eq!(columns[0].name, None);
+        assert_eq!(columns[0].count, 2);
+        assert_eq!(columns[1].top, vec![("a".to_string(), 1)]);
+        assert_eq!(ColumnType::Float.value("2"), CsvValue::Float(2.0));
+        assert_eq!(ColumnType::String.value("2"), CsvValue::String("2".to_string()));
+        assert!(profile("", true, ProfileOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_profile_approximate() {
+        let mut input = String::from("id,kind\n");
+        for i in 0..50_000 {
+            input.push_str(&format!("{},{}\n", i, if i % 10 == 0 { "rare" } else { "common" }));
+        }
+        let options = ProfileOptions { distinct: DistinctMode::Approximate, top: 2 };
+        let columns = profile(&input, true, options);
+
+        let error = (columns[0].distinct as f64 - 50_000.0).abs() / 50_000.0;
+        assert!(error < 0.03, "estimate {}", columns[0].distinct);
+        assert_eq!(columns[1].distinct, 2);
+        assert_eq!(columns[1].top, vec![("common".to_string(), 45_000), ("rare".to_string(), 5_000)]);
+    }
+}
@@ -0,0 +1,216 @@
+use magnus::{
+    gc::Marker,
+    scan_args::scan_args,
+    typed_data::Obj,
+    value::{Opaque, ReprValue},
+    DataTypeFunctions, Error as MagnusError, RArray, RHash, Ruby, TypedData, Value as MagnusValue,
+};
+use std::cell::{Cell, RefCell};
+use std::io::Read;
+use std::mem;
+use crate::compression::{decompress, open_csv_reader};
+use crate::error::CsvError;
+use crate::options::{read_options_from_ruby, STREAM_OPTIONS};
+use crate::parser::{dialect_reader, record_error, CsvParseOptions};
+use crate::ruby_api::{csv_error_to_ruby, input_from_ruby, Input, RowBuilder};
+use crate::ruby_io::{exception_from_io, RubyReader};
+
+/// 読み込み元
+struct Source {
+    reader: csv::Reader<Box<dyn Read + Send>>,
+    /// 読み込み用のバッファ（行ごとに使い回す）
+    record: csv::StringRecord,
+}
+
+/// 1行ずつCSVを読み込むリーダー（`RbCsv::Reader`）
+///
+/// ファイルパスまたはIOから必要な分だけ読み込み、パースのオプション（`CsvParseOptions`）を保持する。
+/// `headers:`・`typed:`・`trim:` 等はすべてキーワード引数で指定するため、オプションごとに
+/// 関数を増やす必要がない。
+#[derive(TypedData)]
+#[magnus(class = "RbCsv::Reader", free_immediately, mark)]
+pub struct Reader {
+    /// 読み込み元のIO（ファイルパス指定時はNone）
+    io: Option<Opaque<MagnusValue>>,
+    /// closeした後はNone
+    source: RefCell<Option<Source>>,
+    builder: RefCell<RowBuilder>,
+    /// 読み込んだレコード数（ヘッダー行を含む）
+    records: Cell<usize>,
+    /// 最後に読み込んだレコードの開始行（1始まり）
+    line: Cell<u64>,
+}
+
+impl DataTypeFunctions for Reader {
+    fn mark(&self, marker: &Marker) {
+        if let Some(io) = self.io {
+            marker.mark(io);
+        }
+        self.builder.borrow().mark(marker);
+    }
+}
+
+impl Reader {
+    /// リーダーを作成する
+    ///
+    /// # Arguments
+    /// * `ruby` - Ruby VMの参照
+    /// * `args` - CSVファイルのパス（またはIO）と `headers:`・`typed:`・`trim:`・`converters:` 等のキーワード引数
+    ///
+    /// # Returns
+    /// * `Result<Reader, MagnusError>` - リーダーまたはエラー
+    pub fn new(ruby: &Ruby, args: &[MagnusValue]) -> Result<Self, MagnusError> {
+        let args = scan_args::<(MagnusValue,), (), (), (), RHash, ()>(args)?;
+        let input = input_from_ruby(ruby, args.required.0)?;
+        let options = read_options_from_ruby(ruby, args.keywords, STREAM_OPTIONS, CsvParseOptions::default())?;
+        let dialect = options.parse.dialect;
+        let builder = RowBuilder::new(ruby, options.parse, options.strings, options.procs)?;
+
+        let (io, input) = match input {
+            Input::Text(path) => (None, open_csv_reader(&path).map_err(|e| csv_error_to_ruby(ruby, e))?),
+            Input::Io(io) => {
+                let input = decompress(RubyReader::new(io)?).map_err(|e| {
+                    exception_from_io(ruby, &e)
+                        .unwrap_or_else(|| csv_error_to_ruby(ruby, CsvError::io(format!("Failed to read IO: {}", e))))
+                })?;
+                (Some(Opaque::from(io)), input)
+            }
+        };
+
+        Ok(Self {
+            io,
+            source: RefCell::new(Some(Source {
+                reader: dialect_reader(input, &dialect),
+                record: csv::StringRecord::new(),
+            })),
+            builder: RefCell::new(builder),
+            records: Cell::new(0),
+            line: Cell::new(0),
+        })
+    }
+
+    /// 残りの行を1行ずつブロックに渡す（ブロックなしの場合はEnumeratorを返す）
+    pub fn each(ruby: &Ruby, rb_self: Obj<Self>) -> Result<MagnusValue, MagnusError> {
+        if !ruby.block_given() {
+            return Ok(rb_self.enumeratorize("each", ()).as_value());
+        }
+
+        while let Some(row) = rb_self.shift(ruby)? {
+            let _: MagnusValue = ruby.yield_value(row)?;
+        }
+
+        Ok(rb_self.as_value())
+    }
+
+    /// 残りの行をすべて読み込んで配列で返す
+    pub fn read_all(ruby: &Ruby, rb_self: Obj<Self>) -> Result<RArray, MagnusError> {
+        let rows = ruby.ary_new();
+        while let Some(row) = rb_self.shift(ruby)? {
+            rows.push(row)?;
+        }
+
+        Ok(rows)
+    }
+
+    /// ヘッダー（Hashのキーの配列）を返す
+    /// まだ1行も読み込んでいない場合はヘッダー行だけを読み込む。`headers: true` でない場合はnil
+    pub fn headers(ruby: &Ruby, rb_self: Obj<Self>) -> Result<Option<RArray>, MagnusError> {
+        if !rb_self.builder.borrow().uses_headers() {
+            return Ok(None);
+        }
+        if rb_self.records.get() == 0 {
+            let Some(record) = rb_self.read_record(ruby)? else {
+                return Ok(None);
+            };
+            rb_self.builder.borrow_mut().select(ruby, 0, &record)?;
+            rb_self.builder.borrow_mut().take_header(ruby, 0, &record)?;
+            rb_self.restore_record(record);
+        }
+
+        Ok(rb_self.builder.borrow().header_keys(ruby).map(|keys| keys.dup()))
+    }
+
+    /// 最後に読み込んだ行の行番号（1始まり、まだ読み込んでいない場合は0）
+    pub fn line(&self) -> u64 {
+        self.line.get()
+    }
+
+    /// リーダーを閉じる（ファイルを閉じる。読み込み元がIOの場合、IO自体は閉じない）
+    pub fn close(&self) {
+        self.source.borrow_mut().take();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.source.borrow().is_none()
+    }
+
+    /// 次の行を読み込んでRubyの値に変換する（終端ならNone）
+    /// ヘッダー行は取り込んで読み飛ばす
+    fn shift(&self, ruby: &Ruby) -> Result<Option<MagnusValue>, MagnusError> {
+        loop {
+            let line_num = self.records.get();
+            let Some(record) = self.read_record(ruby)? else {
+                return Ok(None);
+            };
+
+            // 変換中はバッファを借用しない（コンバーターのProcから再入されても安全なように）
+            let selected = self.builder.borrow_mut().select(ruby, line_num, &record)?;
+            let consumed = line_num == 0 && self.builder.borrow_mut().take_header(ruby, line_num, &record)?;
+            let row = if !selected || consumed {
+                None
+            } else {
+                Some(self.builder.borrow().build(ruby, line_num, &record)?)
+            };
+            self.restore_record(record);
+
+            if row.is_some() {
+                return Ok(row);
+            }
+        }
+    }
+
+    /// 次のレコードを読み込む（終端ならNone）
+    fn read_record(&self, ruby: &Ruby) -> Result<Option<csv::StringRecord>, MagnusError> {
+        let mut source = self.source.borrow_mut();
+        let Some(source) = source.as_mut() else {
+            return Err(MagnusError::new(ruby.exception_io_error(), "closed reader"));
+        };
+
+        let line_num = self.records.get();
+        let mut record = mem::take(&mut source.record);
+        match source.reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => {
+                source.record = record;
+                return Ok(None);
+            }
+            Err(e) => return Err(read_error(ruby, line_num, e)),
+        }
+
+        self.builder.borrow().trim(line_num, &mut record);
+        self.records.set(line_num + 1);
+        if let Some(position) = record.position() {
+            self.line.set(position.line());
+        }
+        Ok(Some(record))
+    }
+
+    /// 読み込み用のバッファを戻す（閉じられていた場合は捨てる）
+    fn restore_record(&self, record: csv::StringRecord) {
+        if let Some(source) = self.source.borrow_mut().as_mut() {
+            source.record = record;
+        }
+    }
+}
+
+/// 読み込みエラーをRubyの例外に変換する
+/// IOの読み込み中にRuby側で発生した例外はそのまま返す
+fn read_error(ruby: &Ruby, line_num: usize, e: csv::Error) -> MagnusError {
+    if let csv::ErrorKind::Io(io_error) = e.kind() {
+        if let Some(exception) = exception_from_io(ruby, io_error) {
+            return exception;
+        }
+    }
+
+    csv_error_to_ruby(ruby, record_error(line_num, e))
+}
@@ -0,0 +1,83 @@
+/// パース済みの行をまとめて保持するバッファ
+///
+/// 読み込んだ `csv::StringRecord` をそのまま保持する。フィールドごとに `String` を確保せず、
+/// Rubyの文字列に変換する際に各レコードのバッファから直接コピーするため、コピーは1回で済む。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rows {
+    records: Vec<csv::StringRecord>,
+}
+
+impl Rows {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// レコードを末尾に追加する
+    /// `record` は同じ大きさのバッファを確保した空のレコードに置き換え、続けて読み込みに使えるようにする
+    pub fn push_record(&mut self, record: &mut csv::StringRecord) {
+        let next = csv::StringRecord::with_capacity(record.as_slice().len(), record.len());
+        self.records.push(std::mem::replace(record, next));
+    }
+
+    /// 別のバッファの行を末尾に追加する
+    pub fn append(&mut self, mut other: Rows) {
+        self.records.append(&mut other.records);
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, csv::StringRecord> {
+        self.records.iter()
+    }
+
+    #[cfg(test)]
+    pub fn into_vec(self) -> Vec<Vec<String>> {
+        self.iter()
+            .map(|row| row.iter().map(|field| field.to_string()).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fields: &[&str]) -> csv::StringRecord {
+        csv::StringRecord::from(fields.to_vec())
+    }
+
+    #[test]
+    fn test_push_and_iter() {
+        let mut rows = Rows::new();
+        let mut header = record(&["name", "", "city"]);
+        rows.push_record(&mut header);
+        rows.push_record(&mut record(&["Alice", "25", "東京"]));
+
+        assert!(header.is_empty());
+        assert_eq!(rows.len(), 2);
+        let mut iter = rows.iter();
+        assert_eq!(iter.next().unwrap().iter().collect::<Vec<_>>(), vec!["name", "", "city"]);
+        assert_eq!(iter.next().unwrap().iter().collect::<Vec<_>>(), vec!["Alice", "25", "東京"]);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_append() {
+        let mut rows = Rows::new();
+        rows.push_record(&mut record(&["a", "b"]));
+        let mut other = Rows::new();
+        other.push_record(&mut record(&["c", "d"]));
+        other.push_record(&mut record(&["e", "f"]));
+
+        rows.append(other);
+        assert!(!rows.is_empty());
+        assert_eq!(rows.into_vec(), vec![vec!["a", "b"], vec!["c", "d"], vec!["e", "f"]]);
+    }
+}
@@ -1,90 +1,674 @@
-use magnus::{Error as MagnusError, Ruby, Value as MagnusValue, value::ReprValue};
-use crate::parser::{parse_csv_core, parse_csv_file, write_csv_file, parse_csv_typed, parse_csv_file_typed};
+use magnus::{
+    gc::Marker,
+    scan_args::{get_kwargs, scan_args},
+    Error as MagnusError, ExceptionClass, Integer, KwArgs, RArray, RClass, RHash, RString, Ruby, Symbol, TryConvert,
+    Value as MagnusValue,
+    value::{Lazy, Opaque, ReprValue},
+    Module,
+};
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use crate::compression::{decompress, open_csv_reader, CompressedWriter, Compression};
+use crate::converter::{ColumnConverters, ColumnKey, Converter};
+use crate::header::{DuplicateHeaders, HeaderOptions, Headers};
+use crate::options::{
+    aggregate_options_from_ruby, dedup_options_from_ruby, profile_options_from_ruby, read_options_from_ruby, sort_options_from_ruby, trim_config_from_ruby, AggregateFormat, ReadOptions, READ_OPTIONS, STREAM_OPTIONS,
+};
+use crate::schema::{FieldType, RecordSchema};
+use crate::source::FileSource;
+use crate::trim::TrimConfig;
+use crate::error::{CsvError, ErrorKind};
+use crate::filter::RowSelector;
+use crate::parser::{
+    dialect_reader, CsvParseOptions, parse_csv_file_rows, parse_csv_file_typed, parse_csv_rows, parse_csv_rows_reader, parse_csv_typed,
+    parse_csv_typed_reader, record_error, typed_record, write_csv_file, write_csv_file_with, write_csv_rows,
+};
+use crate::gvl::without_gvl;
+use crate::parallel::{parse_csv_parallel, parse_csv_typed_parallel};
+use crate::profile::{profile_reader, ColumnProfile};
+use crate::rows::Rows;
+use crate::ruby_io::{RubyReader, RubyWriter};
+use crate::sort::sort_reader;
+use crate::dedup::dedup_reader;
+use crate::aggregate::aggregate_reader;
+use crate::writer::{write_target, WriteTarget};
+use crate::validator::{Rule, ValidationReport, Validator};
+use crate::value::CsvValue;
+
+static HEADER_ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| {
+    ruby.define_module("RbCsv")
+        .and_then(|module| module.const_get("HeaderError"))
+        .expect("RbCsv::HeaderError is defined in init")
+});
+
+/// CsvErrorをRubyの例外に変換する
+/// ヘッダー不一致は `RbCsv::HeaderError`、それ以外は `RuntimeError` になる
+pub fn csv_error_to_ruby(ruby: &Ruby, error: CsvError) -> MagnusError {
+    match error.kind() {
+        ErrorKind::HeaderMismatch => MagnusError::new(ruby.get_inner(&HEADER_ERROR), error.to_string()),
+        ErrorKind::InvalidArgument => MagnusError::new(ruby.exception_arg_error(), error.to_string()),
+        _ => MagnusError::new(ruby.exception_runtime_error(), error.to_string()),
+    }
+}
+
+/// パース対象（文字列またはファイルパス、RubyのIOオブジェクト）
+pub enum Input {
+    Text(String),
+    Io(MagnusValue),
+}
+
+/// 第1引数をパース対象に変換する
+///
+/// Stringはそのまま、`readpartial`/`read` に応答するオブジェクト（File, StringIO, $stdin, Zlib::GzipReader等）は
+/// IOとして扱う。Pathnameのように `to_path` に応答するオブジェクトはパスとして扱う。
+pub fn input_from_ruby(ruby: &Ruby, value: MagnusValue) -> Result<Input, MagnusError> {
+    if let Some(s) = RString::from_value(value) {
+        return Ok(Input::Text(s.to_string()?));
+    }
+    if value.respond_to("readpartial", false)? {
+        return Ok(Input::Io(value));
+    }
+    if value.respond_to("to_path", false)? {
+        return Ok(Input::Text(value.funcall("to_path", ())?));
+    }
+    if RubyReader::is_io(value)? {
+        return Ok(Input::Io(value));
+    }
+
+    Err(MagnusError::new(
+        ruby.exception_type_error(),
+        format!("expected a String or an IO responding to read: {}", value.inspect()),
+    ))
+}
+
+/// RubyのIOを `std::io::Read` として処理に渡す
+/// gzip/zstdで圧縮された入力は展開してから渡す。
+/// 読み込み中にRuby側で発生した例外（IOError等）はCSVのエラーより優先してそのまま返す
+fn with_io<T>(
+    ruby: &Ruby,
+    io: MagnusValue,
+    f: impl FnOnce(Box<dyn Read + '_>) -> Result<T, MagnusError>,
+) -> Result<T, MagnusError> {
+    let mut reader = RubyReader::new(io)?;
+    let result = decompress(&mut reader)
+        .map_err(|e| csv_error_to_ruby(ruby, CsvError::io(format!("Failed to read IO: {}", e))))
+        .and_then(|input| f(input));
+    match reader.take_error(ruby) {
+        Some(error) => Err(error),
+        None => result,
+    }
+}
+
+/// 入力をパースする（`from_file` が真の場合、文字列はファイルパスとして扱う）
+/// `threads` が2以上の場合、文字列・ファイルは複数スレッドで並列にパースする
+pub fn read_rows(
+    ruby: &Ruby,
+    input: Input,
+    from_file: bool,
+    parse: &CsvParseOptions,
+    threads: usize,
+) -> Result<Rows, MagnusError> {
+    // 文字列・ファイルのパースはGVLを解放して行う（IOはRubyのメソッドを呼び出すため解放しない）
+    match input {
+        Input::Io(_) if threads > 1 => Err(io_threads_error(ruby)),
+        Input::Text(path) if from_file && threads > 1 => {
+            without_gvl(ruby, || parse_csv_parallel(FileSource::open(&path)?.as_str(), parse, threads))
+        }
+        Input::Text(path) if from_file => without_gvl(ruby, || parse_csv_file_rows(&path, parse)),
+        Input::Text(s) if threads > 1 => without_gvl(ruby, || parse_csv_parallel(&s, parse, threads)),
+        Input::Text(s) => without_gvl(ruby, || parse_csv_rows(&s, parse)),
+        Input::Io(io) => with_io(ruby, io, |reader| {
+            parse_csv_rows_reader(reader, parse).map_err(|e| csv_error_to_ruby(ruby, e))
+        }),
+    }
+}
+
+/// 入力を型認識してパースする（`from_file` が真の場合、文字列はファイルパスとして扱う）
+pub fn read_typed_rows(
+    ruby: &Ruby,
+    input: Input,
+    from_file: bool,
+    parse: &CsvParseOptions,
+    threads: usize,
+) -> Result<Vec<Vec<CsvValue>>, MagnusError> {
+    match input {
+        Input::Io(_) if threads > 1 => Err(io_threads_error(ruby)),
+        Input::Text(path) if from_file && threads > 1 => {
+            without_gvl(ruby, || parse_csv_typed_parallel(FileSource::open(&path)?.as_str(), parse, threads))
+        }
+        Input::Text(s) if threads > 1 => without_gvl(ruby, || parse_csv_typed_parallel(&s, parse, threads)),
+        Input::Text(path) if from_file => without_gvl(ruby, || parse_csv_file_typed(&path, parse)),
+        Input::Text(s) => without_gvl(ruby, || parse_csv_typed(&s, parse)),
+        Input::Io(io) => with_io(ruby, io, |reader| {
+            parse_csv_typed_reader(reader, parse).map_err(|e| csv_error_to_ruby(ruby, e))
+        }),
+    }
+}
+
+/// IOは先頭から順に読み込むしかなく、チャンクに分割できないため `threads:` を受け付けない
+fn io_threads_error(ruby: &Ruby) -> MagnusError {
+    MagnusError::new(ruby.exception_arg_error(), "threads: is not supported for IO input")
+}
+
+/// フィールドの文字列の生成方法（`dedup:`・`freeze:`）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StringMode {
+    /// 通常の（変更可能な）文字列
+    #[default]
+    Plain,
+    /// 凍結した文字列
+    Frozen,
+    /// Rubyのfstringテーブルで重複を除いた凍結文字列（同じ値は同じオブジェクトを共有する）
+    Deduplicated,
+}
+
+impl StringMode {
+    pub fn from_options(dedup: Option<bool>, freeze: Option<bool>) -> Self {
+        if dedup.unwrap_or(false) {
+            StringMode::Deduplicated
+        } else if freeze.unwrap_or(false) {
+            StringMode::Frozen
+        } else {
+            StringMode::Plain
+        }
+    }
+
+    pub fn str_new(self, ruby: &Ruby, s: &str) -> RString {
+        let string = ruby.str_new(s);
+        match self {
+            StringMode::Plain => string,
+            StringMode::Frozen => {
+                string.freeze();
+                string
+            }
+            StringMode::Deduplicated => string.to_interned_str(),
+        }
+    }
+
+    /// CsvValueをRubyの値に変換する（文字列の値にこの生成方法を適用する）
+    pub fn value_to_ruby(self, ruby: &Ruby, value: &CsvValue) -> MagnusValue {
+        match value {
+            CsvValue::String(s) => self.str_new(ruby, s).as_value(),
+            value => value.to_ruby(ruby),
+        }
+    }
+}
+
+/// 入力と読み込みのオプションを取り出す
+/// `defaults` はメソッドごとの既定値（`parse!` ならtrim、`parse_typed` なら型変換）
+fn scan_read_args(
+    ruby: &Ruby,
+    args: &[MagnusValue],
+    defaults: CsvParseOptions,
+) -> Result<(Input, ReadOptions), MagnusError> {
+    let args = scan_args::<(MagnusValue,), (), (), (), RHash, ()>(args)?;
+    let input = input_from_ruby(ruby, args.required.0)?;
+    let options = read_options_from_ruby(ruby, args.keywords, READ_OPTIONS, defaults)?;
+
+    Ok((input, options))
+}
+
+/// 入力を読み込み、オプションに応じたRubyの値（配列・Hash・レコードの配列）に変換する
+/// parse/read系のメソッドはすべてここを経由する
+fn read_to_ruby(
+    ruby: &Ruby,
+    args: &[MagnusValue],
+    from_file: bool,
+    defaults: CsvParseOptions,
+) -> Result<MagnusValue, MagnusError> {
+    let (input, options) = scan_read_args(ruby, args, defaults)?;
+
+    if options.parse.typed {
+        let rows = read_typed_rows(ruby, input, from_file, &options.parse, options.threads)?;
+        return typed_rows_to_ruby(ruby, rows, &options.procs, &options.parse, options.strings);
+    }
+
+    let rows = read_rows(ruby, input, from_file, &options.parse, options.threads)?;
+    rows_to_ruby(ruby, rows, &options)
+}
+
+/// `parse!`・`read!` の既定値（前後の空白を除去する）
+fn trimmed() -> CsvParseOptions {
+    CsvParseOptions { trim: TrimConfig::all(), ..CsvParseOptions::default() }
+}
+
+/// `parse_typed`・`read_typed` の既定値（型変換する）
+fn typed() -> CsvParseOptions {
+    CsvParseOptions { typed: true, ..CsvParseOptions::default() }
+}
+
+/// パース結果をRubyの値に変換する
+/// `headers: true` の場合はHashの配列、`as:` の場合はレコードの配列を返す
+/// 各フィールドはパースしたレコードから直接Rubyの文字列にコピーする
+fn rows_to_ruby(ruby: &Ruby, rows: Rows, options: &ReadOptions) -> Result<MagnusValue, MagnusError> {
+    if let Some(record_class) = options.record_class {
+        return build_records(ruby, &rows, record_class, options);
+    }
+    if !options.parse.headers {
+        return rows_to_arrays(ruby, &rows, options.strings);
+    }
+
+    let mut rows = rows.iter();
+    let raw_headers = rows.next().map(|row| row.iter().collect::<Vec<&str>>()).unwrap_or_default();
+    let headers = options
+        .parse
+        .header_options
+        .process(&raw_headers)
+        .map_err(|e| csv_error_to_ruby(ruby, e))?;
+
+    rows_to_hashes(ruby, &headers, rows.map(|row| row.iter()), |field| {
+        Ok(options.strings.str_new(ruby, field).as_value())
+    })
+}
+
+/// 行を文字列の配列の配列に変換する
+/// 配列は行数・列数の分だけ確保してから要素を追加する
+fn rows_to_arrays(ruby: &Ruby, rows: &Rows, strings: StringMode) -> Result<MagnusValue, MagnusError> {
+    let result = ruby.ary_new_capa(rows.len());
+    for row in rows.iter() {
+        let fields = ruby.ary_new_capa(row.len());
+        for field in row.iter() {
+            fields.push(strings.str_new(ruby, field))?;
+        }
+        result.push(fields)?;
+    }
+
+    Ok(result.as_value())
+}
+
+/// 1行目を除く各行をヘッダー名をキーとするHashに変換する
+fn rows_to_hashes<T, R: IntoIterator<Item = T>>(
+    ruby: &Ruby,
+    headers: &Headers,
+    rows: impl Iterator<Item = R>,
+    mut convert: impl FnMut(T) -> Result<MagnusValue, MagnusError>,
+) -> Result<MagnusValue, MagnusError> {
+    let keys = header_keys(ruby, headers)?;
+
+    let result = ruby.ary_new();
+    for row in rows {
+        result.push(row_to_hash(ruby, headers, keys, row, &mut convert)?)?;
+    }
+
+    Ok(result.as_value())
+}
+
+/// ヘッダー名からHashのキーを生成する
+/// キーは一度だけ生成し、全行で使い回す
+pub fn header_keys(ruby: &Ruby, headers: &Headers) -> Result<RArray, MagnusError> {
+    let keys = ruby.ary_new_capa(headers.names.len());
+    for name in &headers.names {
+        if headers.symbolize {
+            keys.push(ruby.to_symbol(name))?;
+        } else {
+            let key = ruby.str_new(name);
+            key.freeze();
+            keys.push(key)?;
+        }
+    }
+
+    Ok(keys)
+}
+
+/// 1行をヘッダー名をキーとするHashに変換する
+/// 重複ヘッダーを配列にまとめる設定の場合、同名の列の値は配列に格納する
+fn row_to_hash<T>(
+    ruby: &Ruby,
+    headers: &Headers,
+    keys: RArray,
+    row: impl IntoIterator<Item = T>,
+    mut convert: impl FnMut(T) -> Result<MagnusValue, MagnusError>,
+) -> Result<RHash, MagnusError> {
+    let hash = ruby.hash_new();
+    for (column, field) in row.into_iter().enumerate() {
+        let slot = headers.slots[column];
+        let key: MagnusValue = keys.entry(slot as isize)?;
+        let value = convert(field)?;
+
+        if !headers.grouped[slot] {
+            hash.aset(key, value)?;
+            continue;
+        }
+        match hash.get(key).and_then(RArray::from_value) {
+            Some(values) => values.push(value)?,
+            None => {
+                let values = ruby.ary_new();
+                values.push(value)?;
+                hash.aset(key, values)?;
+            }
+        }
+    }
+
+    Ok(hash)
+}
+
+/// Struct/Dataクラスのインスタンスを1行ごとに生成する
+///
+/// ヘッダー名とクラスのメンバー名を対応付け、`schema:` で型が指定されたメンバーは変換してから渡す。
+/// `keyword_init: true` のStructにはキーワード引数、それ以外は位置引数で生成する。
+fn build_records(
+    ruby: &Ruby,
+    rows: &Rows,
+    record_class: RClass,
+    options: &ReadOptions,
+) -> Result<MagnusValue, MagnusError> {
+    let members: Vec<Symbol> = record_class.funcall("members", ())?;
+    let member_names = members
+        .iter()
+        .map(|member| member.name().map(|name| name.into_owned()))
+        .collect::<Result<Vec<String>, MagnusError>>()?;
+
+    let mut record_schema = RecordSchema::new(member_names);
+    if let Some(schema) = options.schema {
+        for (member, type_name) in schema.to_vec::<MagnusValue, Symbol>()? {
+            let member = match Symbol::from_value(member) {
+                Some(member) => member.name()?.into_owned(),
+                None => String::try_convert(member)?,
+            };
+            let field_type = FieldType::from_name(&type_name.name()?).ok_or_else(|| {
+                MagnusError::new(
+                    ruby.exception_arg_error(),
+                    format!("unknown schema type: {}", type_name.inspect()),
+                )
+            })?;
+            record_schema
+                .set_type(&member, field_type)
+                .map_err(|e| MagnusError::new(ruby.exception_arg_error(), e.to_string()))?;
+        }
+    }
+
+    let keyword_init = record_class.respond_to("keyword_init?", false)?
+        && record_class.funcall::<_, _, Option<bool>>("keyword_init?", ())? == Some(true);
+
+    let mut rows = rows.iter();
+    let raw_headers = rows.next().map(|row| row.iter().collect::<Vec<&str>>()).unwrap_or_default();
+    let header_options = &options.parse.header_options;
+    // 重複したヘッダー名は `duplicate_headers:` の設定に従って例外にする
+    let indices = header_options
+        .process(&raw_headers)
+        .and_then(|_| record_schema.resolve(&raw_headers, header_options))
+        .map_err(|e| csv_error_to_ruby(ruby, e))?;
+
+    let records = ruby.ary_new();
+    for (line_num, row) in rows.enumerate() {
+        let values = record_schema
+            .convert_row(&indices, &row.iter().collect::<Vec<&str>>(), line_num + 2)
+            .map_err(|e| csv_error_to_ruby(ruby, e))?;
+
+        let record: MagnusValue = if keyword_init {
+            let kwargs = ruby.hash_new();
+            for (member, value) in members.iter().zip(values) {
+                kwargs.aset(*member, options.strings.value_to_ruby(ruby, &value))?;
+            }
+            record_class.funcall("new", (KwArgs(kwargs),))?
+        } else {
+            // 生成途中の値がGCで回収されないようRuby配列に保持してから渡す
+            let args = ruby.ary_new_capa(values.len());
+            for value in values {
+                args.push(options.strings.value_to_ruby(ruby, &value))?;
+            }
+            record_class.funcall("new", args.to_vec::<MagnusValue>()?.as_slice())?
+        };
+        records.push(record)?;
+    }
+
+    Ok(records.as_value())
+}
 
 /// CSV文字列をパースする（通常版）
 ///
 /// # Arguments
 /// * `ruby` - Ruby VMの参照
-/// * `s` - パースするCSV文字列
+/// * `args` - パースするCSV文字列（またはIO）と `as:`・`schema:`・`headers:` 等のキーワード引数
 ///
 /// # Returns
-/// * `Result<Vec<Vec<String>>, MagnusError>` - パース結果またはエラー
-pub fn parse(ruby: &Ruby, s: String) -> Result<Vec<Vec<String>>, MagnusError> {
-    parse_csv_core(&s, csv::Trim::None)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))
+/// * `Result<MagnusValue, MagnusError>` - パース結果（`headers:` 指定時はHashの配列、`as:` 指定時はレコードの配列）またはエラー
+pub fn parse(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    read_to_ruby(ruby, args, false, CsvParseOptions::default())
 }
 
 /// CSV文字列をパースする（trim版）
 ///
 /// # Arguments
 /// * `ruby` - Ruby VMの参照
-/// * `s` - パースするCSV文字列
+/// * `args` - パースするCSV文字列（またはIO）と `as:`・`schema:`・`headers:` 等のキーワード引数
 ///
 /// # Returns
-/// * `Result<Vec<Vec<String>>, MagnusError>` - パース結果またはエラー
-pub fn parse_trim(ruby: &Ruby, s: String) -> Result<Vec<Vec<String>>, MagnusError> {
-    parse_csv_core(&s, csv::Trim::All)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))
+/// * `Result<MagnusValue, MagnusError>` - パース結果（`headers:` 指定時はHashの配列、`as:` 指定時はレコードの配列）またはエラー
+pub fn parse_trim(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    read_to_ruby(ruby, args, false, trimmed())
 }
 
 /// CSVファイルを読み込む（通常版）
 ///
 /// # Arguments
 /// * `ruby` - Ruby VMの参照
-/// * `file_path` - 読み込むCSVファイルのパス
+/// * `args` - 読み込むCSVファイルのパス（またはIO）と `as:`・`schema:`・`headers:` 等のキーワード引数
 ///
 /// # Returns
-/// * `Result<Vec<Vec<String>>, MagnusError>` - パース結果またはエラー
-pub fn read(ruby: &Ruby, file_path: String) -> Result<Vec<Vec<String>>, MagnusError> {
-    parse_csv_file(&file_path, csv::Trim::None)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))
+/// * `Result<MagnusValue, MagnusError>` - パース結果（`headers:` 指定時はHashの配列、`as:` 指定時はレコードの配列）またはエラー
+pub fn read(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    read_to_ruby(ruby, args, true, CsvParseOptions::default())
 }
 
 /// CSVファイルを読み込む（trim版）
 ///
 /// # Arguments
 /// * `ruby` - Ruby VMの参照
-/// * `file_path` - 読み込むCSVファイルのパス
+/// * `args` - 読み込むCSVファイルのパス（またはIO）と `as:`・`schema:`・`headers:` 等のキーワード引数
 ///
 /// # Returns
-/// * `Result<Vec<Vec<String>>, MagnusError>` - パース結果またはエラー
-pub fn read_trim(ruby: &Ruby, file_path: String) -> Result<Vec<Vec<String>>, MagnusError> {
-    parse_csv_file(&file_path, csv::Trim::All)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))
+/// * `Result<MagnusValue, MagnusError>` - パース結果（`headers:` 指定時はHashの配列、`as:` 指定時はレコードの配列）またはエラー
+pub fn read_trim(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    read_to_ruby(ruby, args, true, trimmed())
 }
 
 /// CSVファイルに書き込む
 ///
 /// # Arguments
 /// * `ruby` - Ruby VMの参照
-/// * `file_path` - 書き込み先ファイルのパス
-/// * `data` - 書き込むCSVデータ（2次元配列）
+/// * `args` - 書き込み先ファイルのパス（または `write` に応答するIO）、書き込むCSVデータ（2次元配列）と `compress:` のキーワード引数
 ///
 /// # Returns
 /// * `Result<(), MagnusError>` - 成功時は空、失敗時はエラー
-pub fn write(ruby: &Ruby, file_path: String, data: Vec<Vec<String>>) -> Result<(), MagnusError> {
-    write_csv_file(&file_path, &data)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))
+pub fn write(ruby: &Ruby, args: &[MagnusValue]) -> Result<(), MagnusError> {
+    let args = scan_args::<(MagnusValue, Vec<Vec<String>>), (), (), (), RHash, ()>(args)?;
+    let (target, data) = args.required;
+    let kwargs = get_kwargs::<_, (), (Option<Symbol>,), ()>(args.keywords, &[], &["compress"])?;
+    let (compress,) = kwargs.optional;
+    let compress = compress.map(|name| compression_from_ruby(ruby, name)).transpose()?;
+
+    match write_target(ruby, target)? {
+        WriteTarget::Path(file_path) => {
+            let compression = compress.unwrap_or_else(|| Compression::from_path(&file_path));
+            write_csv_file(&file_path, &data, compression).map_err(|e| csv_error_to_ruby(ruby, e))
+        }
+        WriteTarget::Io(io) => {
+            with_output_io(ruby, io, compress.unwrap_or(Compression::None), |output| write_csv_rows(&data, output))
+        }
+    }
 }
 
-/// CSV文字列を型認識してパースする（通常版）
+/// `compress:`（`:gzip`, `:zstd`, `:none`）を圧縮形式に変換する
+pub fn compression_from_ruby(ruby: &Ruby, name: Symbol) -> Result<Compression, MagnusError> {
+    Compression::from_name(&name.name()?).ok_or_else(|| {
+        MagnusError::new(
+            ruby.exception_arg_error(),
+            format!("unknown compression: {}", name.inspect()),
+        )
+    })
+}
+
+/// `converters:` オプションをコンバーター設定に変換する
 ///
-/// # Arguments
-/// * `ruby` - Ruby VMの参照
-/// * `s` - パースするCSV文字列
+/// キーは列番号（Integer）またはヘッダー名（String/Symbol）、値は以下のいずれか
+/// * `call` に応答するオブジェクト（Proc等）: 生のフィールド文字列を引数に呼び出す
+/// * `{ regex: /(\d+)kg/ }`: マッチした部分（キャプチャがあれば最初のキャプチャ）を型変換する
+/// * `{ lookup: { "Y" => true } }`: 対応表に一致した値に置き換える
+/// * `{ strip_prefix: "$" }`: 接頭辞を取り除いてから型変換する
 ///
 /// # Returns
-/// * `Result<Vec<Vec<MagnusValue>>, MagnusError>` - パース結果（数値は数値型）またはエラー
-pub fn parse_typed(ruby: &Ruby, s: String) -> Result<MagnusValue, MagnusError> {
-    let result = parse_csv_typed(&s, csv::Trim::None)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))?;
+/// * `Result<(ColumnConverters, Vec<MagnusValue>), MagnusError>` - コンバーター設定とProc一覧
+pub fn build_converters(
+    ruby: &Ruby,
+    option: Option<RHash>,
+) -> Result<(ColumnConverters, Vec<MagnusValue>), MagnusError> {
+    let mut converters = ColumnConverters::new();
+    let mut procs = Vec::new();
+
+    let Some(option) = option else {
+        return Ok((converters, procs));
+    };
+
+    for (key, spec) in option.to_vec::<MagnusValue, MagnusValue>()? {
+        let column = column_key_from_ruby(ruby, key)?;
+
+        let converter = if let Some(spec) = RHash::from_value(spec) {
+            declarative_converter(ruby, spec)?
+        } else if spec.respond_to("call", false)? {
+            procs.push(spec);
+            Converter::Custom(procs.len() - 1)
+        } else {
+            return Err(MagnusError::new(
+                ruby.exception_arg_error(),
+                format!("converter must respond to call or be a Hash: {}", spec.inspect()),
+            ));
+        };
+
+        converters.add(column, converter);
+    }
+
+    Ok((converters, procs))
+}
+
+fn column_key_from_ruby(ruby: &Ruby, key: MagnusValue) -> Result<ColumnKey, MagnusError> {
+    if let Some(index) = Integer::from_value(key) {
+        return Ok(ColumnKey::Index(index.to_usize()?));
+    }
+    if let Some(name) = Symbol::from_value(key) {
+        return Ok(ColumnKey::Name(name.name()?.into_owned()));
+    }
+    if let Some(name) = RString::from_value(key) {
+        return Ok(ColumnKey::Name(name.to_string()?));
+    }
+
+    Err(MagnusError::new(
+        ruby.exception_arg_error(),
+        format!("converter key must be a column index or header name: {}", key.inspect()),
+    ))
+}
+
+fn declarative_converter(ruby: &Ruby, spec: RHash) -> Result<Converter, MagnusError> {
+    let entries = spec.to_vec::<Symbol, MagnusValue>()?;
+    let [(kind, value)] = entries.as_slice() else {
+        return Err(MagnusError::new(
+            ruby.exception_arg_error(),
+            "converter Hash must have exactly one of :regex, :lookup, :strip_prefix",
+        ));
+    };
+
+    match kind.name()?.as_ref() {
+        "regex" => {
+            let pattern = regex_pattern_from_ruby(*value)?;
+            let regex = Regex::new(&pattern)
+                .map_err(|e| MagnusError::new(ruby.exception_arg_error(), format!("invalid regex: {}", e)))?;
+            Ok(Converter::Regex(regex))
+        }
+        "lookup" => {
+            let table = RHash::try_convert(*value)?;
+            let mut lookup = HashMap::new();
+            for (from, to) in table.to_vec::<String, MagnusValue>()? {
+                lookup.insert(from, CsvValue::from_ruby(ruby, to)?);
+            }
+            Ok(Converter::Lookup(lookup))
+        }
+        "strip_prefix" => Ok(Converter::StripPrefix(String::try_convert(*value)?)),
+        other => Err(MagnusError::new(
+            ruby.exception_arg_error(),
+            format!("unknown converter type: {}", other),
+        )),
+    }
+}
+
+/// Regexp（またはパターン文字列）をRustのregex構文に変換する
+/// Regexpのオプション（i, x, m）はインラインフラグとして引き継ぐ
+fn regex_pattern_from_ruby(value: MagnusValue) -> Result<String, MagnusError> {
+    if let Some(pattern) = RString::from_value(value) {
+        return pattern.to_string();
+    }
+
+    let source: String = value.funcall("source", ())?;
+    let options: i64 = value.funcall("options", ())?;
+
+    let mut flags = String::new();
+    if options & 1 != 0 {
+        flags.push('i');
+    }
+    if options & 2 != 0 {
+        flags.push('x');
+    }
+    // Rubyの複数行モード（.が改行にマッチ）はRustのsフラグに相当する
+    if options & 4 != 0 {
+        flags.push('s');
+    }
+
+    if flags.is_empty() {
+        Ok(source)
+    } else {
+        Ok(format!("(?{}){}", flags, source))
+    }
+}
+
+/// CsvValueをRubyの値に変換する
+/// Rubyのコンバーターが割り当てられた値はここでProcを呼び出して変換する
+fn typed_value_to_ruby(
+    ruby: &Ruby,
+    value: CsvValue,
+    procs: &[MagnusValue],
+    strings: StringMode,
+) -> Result<MagnusValue, MagnusError> {
+    match value {
+        CsvValue::Deferred { converter, raw } => procs[converter].funcall("call", (raw,)),
+        value => Ok(strings.value_to_ruby(ruby, &value)),
+    }
+}
+
+/// Vec<Vec<CsvValue>> を Ruby配列に変換する
+/// `headers: true` の場合は1行目をヘッダーとしてHashの配列に変換する
+fn typed_rows_to_ruby(
+    ruby: &Ruby,
+    rows: Vec<Vec<CsvValue>>,
+    procs: &[MagnusValue],
+    options: &CsvParseOptions,
+    strings: StringMode,
+) -> Result<MagnusValue, MagnusError> {
+    if options.headers {
+        let mut rows = rows.into_iter();
+        let raw_headers: Vec<String> = rows
+            .next()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|value| match value {
+                CsvValue::String(s) => s,
+                value => value.to_string(),
+            })
+            .collect();
+        let headers = options
+            .header_options
+            .process(&raw_headers)
+            .map_err(|e| csv_error_to_ruby(ruby, e))?;
+
+        return rows_to_hashes(ruby, &headers, rows, |value| {
+            typed_value_to_ruby(ruby, value, procs, strings)
+        });
+    }
 
-    // Vec<Vec<CsvValue>> を Ruby配列に変換
     let outer_array = ruby.ary_new();
-    for row in result {
+    for row in rows {
         let inner_array = ruby.ary_new();
         for value in row {
-            inner_array.push(value.to_ruby(ruby))?;
+            inner_array.push(typed_value_to_ruby(ruby, value, procs, strings)?)?;
         }
         outer_array.push(inner_array.as_value())?;
     }
@@ -92,79 +676,728 @@ pub fn parse_typed(ruby: &Ruby, s: String) -> Result<MagnusValue, MagnusError> {
     Ok(outer_array.as_value())
 }
 
+/// CSV文字列を型認識してパースする（通常版）
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - パースするCSV文字列（またはIO）と `converters:`・`headers:` 等のキーワード引数
+///
+/// # Returns
+/// * `Result<Vec<Vec<MagnusValue>>, MagnusError>` - パース結果（数値は数値型、`headers:` 指定時はHashの配列）またはエラー
+pub fn parse_typed(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    read_to_ruby(ruby, args, false, typed())
+}
+
 /// CSV文字列を型認識してパースする（trim版）
 ///
 /// # Arguments
 /// * `ruby` - Ruby VMの参照
-/// * `s` - パースするCSV文字列
+/// * `args` - パースするCSV文字列（またはIO）と `converters:`・`headers:` 等のキーワード引数
 ///
 /// # Returns
-/// * `Result<Vec<Vec<MagnusValue>>, MagnusError>` - パース結果（数値は数値型）またはエラー
-pub fn parse_typed_trim(ruby: &Ruby, s: String) -> Result<MagnusValue, MagnusError> {
-    let result = parse_csv_typed(&s, csv::Trim::All)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))?;
+/// * `Result<Vec<Vec<MagnusValue>>, MagnusError>` - パース結果（数値は数値型、`headers:` 指定時はHashの配列）またはエラー
+pub fn parse_typed_trim(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    read_to_ruby(ruby, args, false, CsvParseOptions { trim: TrimConfig::all(), ..typed() })
+}
 
-    // Vec<Vec<CsvValue>> を Ruby配列に変換
-    let outer_array = ruby.ary_new();
-    for row in result {
-        let inner_array = ruby.ary_new();
-        for value in row {
-            inner_array.push(value.to_ruby(ruby))?;
+/// CSVファイルを型認識して読み込む（通常版）
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - 読み込むCSVファイルのパス（またはIO）と `converters:`・`headers:` 等のキーワード引数
+///
+/// # Returns
+/// * `Result<Vec<Vec<MagnusValue>>, MagnusError>` - パース結果（数値は数値型、`headers:` 指定時はHashの配列）またはエラー
+pub fn read_typed(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    read_to_ruby(ruby, args, true, typed())
+}
+
+/// CSVファイルを型認識して読み込む（trim版）
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - 読み込むCSVファイルのパス（またはIO）と `converters:`・`headers:` 等のキーワード引数
+///
+/// # Returns
+/// * `Result<Vec<Vec<MagnusValue>>, MagnusError>` - パース結果（数値は数値型、`headers:` 指定時はHashの配列）またはエラー
+pub fn read_typed_trim(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    read_to_ruby(ruby, args, true, CsvParseOptions { trim: TrimConfig::all(), ..typed() })
+}
+
+/// `rules:` オプションを検証器に変換する
+///
+/// キーは列番号（Integer）またはヘッダー名（String/Symbol）、値は以下のキーを持つHash
+/// * `not_empty: true` / `unique: true`
+/// * `pattern: /\A\d+\z/`
+/// * `min: 0` / `max: 100`（数値として認識された値のみ許可する）
+/// * `in: ["a", "b"]`
+/// * `min_length: 1` / `max_length: 255`
+fn build_validator(ruby: &Ruby, rules: RHash) -> Result<Validator, MagnusError> {
+    let mut validator = Validator::new();
+
+    for (key, spec) in rules.to_vec::<MagnusValue, RHash>()? {
+        let column = column_key_from_ruby(ruby, key)?;
+        let mut column_rules = Vec::new();
+
+        for (name, value) in spec.to_vec::<Symbol, MagnusValue>()? {
+            let rule = match name.name()?.as_ref() {
+                "not_empty" => bool::try_convert(value)?.then_some(Rule::NotEmpty),
+                "unique" => bool::try_convert(value)?.then_some(Rule::Unique),
+                "pattern" => {
+                    let pattern = regex_pattern_from_ruby(value)?;
+                    let regex = Regex::new(&pattern)
+                        .map_err(|e| MagnusError::new(ruby.exception_arg_error(), format!("invalid regex: {}", e)))?;
+                    Some(Rule::Pattern(regex))
+                }
+                "min" => Some(Rule::Min(f64::try_convert(value)?)),
+                "max" => Some(Rule::Max(f64::try_convert(value)?)),
+                "in" => {
+                    let candidates = RArray::try_convert(value)?
+                        .into_iter()
+                        .map(|candidate| CsvValue::from_ruby(ruby, candidate).map(|c| c.to_string()))
+                        .collect::<Result<Vec<String>, MagnusError>>()?;
+                    Some(Rule::OneOf(candidates))
+                }
+                "min_length" => Some(Rule::MinLength(usize::try_convert(value)?)),
+                "max_length" => Some(Rule::MaxLength(usize::try_convert(value)?)),
+                other => {
+                    return Err(MagnusError::new(
+                        ruby.exception_arg_error(),
+                        format!("unknown validation rule: {}", other),
+                    ))
+                }
+            };
+            column_rules.extend(rule);
         }
-        outer_array.push(inner_array.as_value())?;
+
+        validator.add_rules(column, column_rules);
     }
 
-    Ok(outer_array.as_value())
+    Ok(validator)
 }
 
-/// CSVファイルを型認識して読み込む（通常版）
+/// 検証の引数（入力と `rules:`・`headers:`・`trim:`・`trim_chars:`）を取り出す
+fn scan_validate_args(ruby: &Ruby, args: &[MagnusValue]) -> Result<(Input, Validator, bool, TrimConfig), MagnusError> {
+    let args = scan_args::<(MagnusValue,), (), (), (), RHash, ()>(args)?;
+    let input = input_from_ruby(ruby, args.required.0)?;
+    let kwargs = get_kwargs::<_, (RHash,), (Option<bool>, Option<MagnusValue>, Option<MagnusValue>), ()>(
+        args.keywords,
+        &["rules"],
+        &["headers", "trim", "trim_chars"],
+    )?;
+    let (rules,) = kwargs.required;
+    let (headers, trim, trim_chars) = kwargs.optional;
+    let headers = headers.unwrap_or(true);
+
+    let validator = build_validator(ruby, rules)?;
+    let trim = trim_config_from_ruby(ruby, trim, trim_chars, TrimConfig::default())?;
+    let trim = if headers { trim } else { trim.without_header_row() };
+
+    Ok((input, validator, headers, trim))
+}
+
+/// 検証結果を `{ valid:, rows:, violations: }` のHashに変換する
+/// `rows` はすべてのルールを満たした行（`headers: true` の場合はHash）
+fn report_to_ruby(ruby: &Ruby, report: ValidationReport) -> Result<MagnusValue, MagnusError> {
+    let violations = ruby.ary_new_capa(report.violations.len());
+    for violation in &report.violations {
+        let entry = ruby.hash_new();
+        entry.aset(ruby.to_symbol("row"), violation.row)?;
+        entry.aset(ruby.to_symbol("column"), violation.column)?;
+        entry.aset(ruby.to_symbol("header"), violation.header.as_deref())?;
+        entry.aset(ruby.to_symbol("rule"), ruby.to_symbol(violation.rule))?;
+        entry.aset(ruby.to_symbol("value"), violation.value.as_str())?;
+        entry.aset(ruby.to_symbol("message"), violation.message.as_str())?;
+        violations.push(entry)?;
+    }
+
+    let headers = report.headers.is_some();
+    let rows: Vec<Vec<CsvValue>> = report.headers.into_iter().chain(report.clean_rows).collect();
+    // 重複したヘッダーは違反として報告済みのため、行のHashでは連番を付けて両方の列を残す
+    let header_options = HeaderOptions { duplicates: DuplicateHeaders::Suffix, ..Default::default() };
+    let options = CsvParseOptions { headers, header_options, ..typed() };
+
+    let result = ruby.hash_new();
+    result.aset(ruby.to_symbol("valid"), violations.is_empty())?;
+    result.aset(ruby.to_symbol("rows"), typed_rows_to_ruby(ruby, rows, &[], &options, StringMode::Plain)?)?;
+    result.aset(ruby.to_symbol("violations"), violations)?;
+
+    Ok(result.as_value())
+}
+
+/// CSV文字列を型認識してパースし、ルールで検証する
+///
+/// 最初の違反で例外にはせず、すべての違反を集めて返す。
 ///
 /// # Arguments
 /// * `ruby` - Ruby VMの参照
-/// * `file_path` - 読み込むCSVファイルのパス
+/// * `args` - 検証するCSV文字列（またはIO）と `rules:`・`headers:`（デフォルトtrue）・`trim:` のキーワード引数
 ///
 /// # Returns
-/// * `Result<Vec<Vec<MagnusValue>>, MagnusError>` - パース結果（数値は数値型）またはエラー
-pub fn read_typed(ruby: &Ruby, file_path: String) -> Result<MagnusValue, MagnusError> {
-    let result = parse_csv_file_typed(&file_path, csv::Trim::None)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))?;
+/// * `Result<RHash, MagnusError>` - `{ valid:, rows:, violations: }` またはエラー
+pub fn validate(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    let (input, validator, headers, trim) = scan_validate_args(ruby, args)?;
+    let options = CsvParseOptions { trim, headers, ..typed() };
+    let rows = read_typed_rows(ruby, input, false, &options, 1)?;
+    let report = validator.validate(rows, headers).map_err(|e| csv_error_to_ruby(ruby, e))?;
 
-    // Vec<Vec<CsvValue>> を Ruby配列に変換
-    let outer_array = ruby.ary_new();
-    for row in result {
-        let inner_array = ruby.ary_new();
-        for value in row {
-            inner_array.push(value.to_ruby(ruby))?;
+    report_to_ruby(ruby, report)
+}
+
+/// CSVファイルを型認識して読み込み、ルールで検証する
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - 検証するCSVファイルのパス（またはIO）と `rules:`・`headers:`（デフォルトtrue）・`trim:` のキーワード引数
+///
+/// # Returns
+/// * `Result<RHash, MagnusError>` - `{ valid:, rows:, violations: }` またはエラー
+pub fn validate_file(ruby: &Ruby, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    let (input, validator, headers, trim) = scan_validate_args(ruby, args)?;
+    let options = CsvParseOptions { trim, headers, ..typed() };
+    let rows = read_typed_rows(ruby, input, true, &options, 1)?;
+    let report = validator.validate(rows, headers).map_err(|e| csv_error_to_ruby(ruby, e))?;
+
+    report_to_ruby(ruby, report)
+}
+
+/// CSVファイルを1行ずつ読み込み、列ごとの統計を集計する
+///
+/// 型の推定・空の値の数・異なる値の数・最小値と最大値・平均と標準偏差（数値の列）・
+/// 最大の文字数・出現回数の多い値を列ごとに返す。全体をメモリに読み込まない。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - CSVファイルのパス（またはIO）と `headers:`（デフォルトtrue）・`top:`（デフォルト5）・
+///   `distinct:`（`:exact`・`:approximate`）・`trim:`・`col_sep:` 等のキーワード引数
+///
+/// # Returns
+/// * `Result<RArray, MagnusError>` - 列ごとの統計（Hash）の配列またはエラー
+pub fn profile(ruby: &Ruby, args: &[MagnusValue]) -> Result<RArray, MagnusError> {
+    let args = scan_args::<(MagnusValue,), (), (), (), RHash, ()>(args)?;
+    let input = input_from_ruby(ruby, args.required.0)?;
+    let defaults = CsvParseOptions { headers: true, ..Default::default() };
+    let (options, profile_options) = profile_options_from_ruby(ruby, args.keywords, defaults)?;
+    let parse = &options.parse;
+
+    // ファイルの集計はGVLを解放して行う（IOはRubyのメソッドを呼び出すため解放しない）
+    let columns = match input {
+        Input::Text(path) => without_gvl(ruby, || profile_reader(open_csv_reader(&path)?, parse, &profile_options)),
+        Input::Io(io) => with_io(ruby, io, |reader| {
+            profile_reader(reader, parse, &profile_options).map_err(|e| csv_error_to_ruby(ruby, e))
+        }),
+    }?;
+
+    let result = ruby.ary_new_capa(columns.len());
+    for (index, column) in columns.iter().enumerate() {
+        result.push(column_profile_to_ruby(ruby, index, column)?)?;
+    }
+    Ok(result)
+}
+
+/// 1列分の統計を `{ index:, name:, type:, count:, nulls:, distinct:, min:, max:, mean:, stddev:, max_length:, top: }`
+/// のHashに変換する。`top` は `[値, 回数]` の配列で、値は列の型に変換する
+fn column_profile_to_ruby(ruby: &Ruby, index: usize, column: &ColumnProfile) -> Result<RHash, MagnusError> {
+    let top = ruby.ary_new_capa(column.top.len());
+    for (value, count) in &column.top {
+        top.push((column.column_type.value(value).to_ruby(ruby), *count))?;
+    }
+    let value_to_ruby = |value: &Option<CsvValue>| value.as_ref().map(|value| value.to_ruby(ruby));
+
+    let entry = ruby.hash_new();
+    entry.aset(ruby.to_symbol("index"), index)?;
+    entry.aset(ruby.to_symbol("name"), column.name.as_deref())?;
+    entry.aset(ruby.to_symbol("type"), ruby.to_symbol(column.column_type.name()))?;
+    entry.aset(ruby.to_symbol("count"), column.count)?;
+    entry.aset(ruby.to_symbol("nulls"), column.nulls)?;
+    entry.aset(ruby.to_symbol("distinct"), column.distinct)?;
+    entry.aset(ruby.to_symbol("min"), value_to_ruby(&column.min))?;
+    entry.aset(ruby.to_symbol("max"), value_to_ruby(&column.max))?;
+    entry.aset(ruby.to_symbol("mean"), column.mean)?;
+    entry.aset(ruby.to_symbol("stddev"), column.stddev)?;
+    entry.aset(ruby.to_symbol("max_length"), column.max_length)?;
+    entry.aset(ruby.to_symbol("top"), top)?;
+    Ok(entry)
+}
+
+/// CSVを列の値で並べ替えて書き込む
+///
+/// 引用符内の改行を含むレコード単位で並べ替える。メモリ上のレコードが `memory_limit:` を超えた場合は
+/// 並べ替えた分を一時ファイルに書き出してマージするため、メモリに収まらない大きなファイルも並べ替えられる。
+/// 入力をすべて読み込んでから書き込むため、入力と同じファイルにも書き込める。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - 入力のパス（またはIO）、出力のパス（またはIO）と `by:`（必須）・`order:`（`:asc`・`:desc`）・
+///   `typed:`・`headers:`（デフォルトtrue）・`memory_limit:`・`compress:`・`trim:`・`col_sep:` 等のキーワード引数
+///
+/// # Returns
+/// * `Result<usize, MagnusError>` - 書き込んだデータ行の数またはエラー
+pub fn sort(ruby: &Ruby, args: &[MagnusValue]) -> Result<usize, MagnusError> {
+    let args = scan_args::<(MagnusValue, MagnusValue), (), (), (), RHash, ()>(args)?;
+    let (input, output) = args.required;
+    let input = input_from_ruby(ruby, input)?;
+    let target = write_target(ruby, output)?;
+    let defaults = CsvParseOptions { headers: true, ..Default::default() };
+    let (options, sort_options, compress) = sort_options_from_ruby(ruby, args.keywords, defaults)?;
+    let parse = &options.parse;
+
+    // ファイルの読み書きはGVLを解放して行う（IOはRubyのメソッドを呼び出すため解放しない）
+    let sorted = match input {
+        Input::Text(path) => without_gvl(ruby, || sort_reader(open_csv_reader(&path)?, parse, &sort_options)),
+        Input::Io(io) => with_io(ruby, io, |reader| {
+            sort_reader(reader, parse, &sort_options).map_err(|e| csv_error_to_ruby(ruby, e))
+        }),
+    }?;
+
+    match target {
+        WriteTarget::Path(path) => {
+            let compression = compress.unwrap_or_else(|| Compression::from_path(&path));
+            without_gvl(ruby, || {
+                write_csv_file_with(&path, compression, |output| sorted.write_to(output, &parse.dialect))
+            })
         }
-        outer_array.push(inner_array.as_value())?;
+        WriteTarget::Io(io) => with_output_io(ruby, io, compress.unwrap_or(Compression::None), |output| {
+            sorted.write_to(output, &parse.dialect)
+        }),
     }
+}
 
-    Ok(outer_array.as_value())
+/// CSVからキーの列が重複する行を取り除いて書き込む
+///
+/// 読み込みながら書き込むため、キーの集合以外はメモリに保持しない（`keep: :last` の場合は残す行を保持する）。
+/// 入力と同じファイルには書き込めない。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - 入力のパス（またはIO）、出力のパス（またはIO）と `keys:`（省略時はすべての列）・
+///   `keep:`（`:first`・`:last`）・`typed:`・`trim:`・`headers:`（デフォルトtrue）・`compress:`・`col_sep:` 等のキーワード引数
+///
+/// # Returns
+/// * `Result<RHash, MagnusError>` - 書き込んだ行数と取り除いた行数（`{ rows:, duplicates: }`）またはエラー
+pub fn dedup(ruby: &Ruby, args: &[MagnusValue]) -> Result<RHash, MagnusError> {
+    let args = scan_args::<(MagnusValue, MagnusValue), (), (), (), RHash, ()>(args)?;
+    let (input, output) = args.required;
+    let input = input_from_ruby(ruby, input)?;
+    let target = write_target(ruby, output)?;
+    let defaults = CsvParseOptions { headers: true, ..Default::default() };
+    let (options, dedup_options, compress) = dedup_options_from_ruby(ruby, args.keywords, defaults)?;
+    let parse = &options.parse;
+
+    // ファイル同士の場合はGVLを解放して行う（IOはRubyのメソッドを呼び出すため解放しない）
+    let stats = match (input, target) {
+        (Input::Text(input), WriteTarget::Path(output)) => without_gvl(ruby, || {
+            let input = open_csv_reader(&input)?;
+            let compression = compress.unwrap_or_else(|| Compression::from_path(&output));
+            write_csv_file_with(&output, compression, |output| dedup_reader(input, output, parse, &dedup_options))
+        }),
+        (input, target) => with_stream_input(ruby, input, |reader| match target {
+            WriteTarget::Path(output) => {
+                let compression = compress.unwrap_or_else(|| Compression::from_path(&output));
+                write_csv_file_with(&output, compression, |output| dedup_reader(reader, output, parse, &dedup_options))
+                    .map_err(|e| csv_error_to_ruby(ruby, e))
+            }
+            WriteTarget::Io(io) => with_output_io(ruby, io, compress.unwrap_or(Compression::None), |output| {
+                dedup_reader(reader, output, parse, &dedup_options)
+            }),
+        }),
+    }?;
+
+    let result = ruby.hash_new();
+    result.aset(ruby.to_symbol("rows"), stats.rows)?;
+    result.aset(ruby.to_symbol("duplicates"), stats.duplicates)?;
+    Ok(result)
 }
 
-/// CSVファイルを型認識して読み込む（trim版）
+/// RubyのIOを `std::io::Write` として処理に渡す
+/// 書き込み中にRuby側で発生した例外（IOError等）はCSVのエラーより優先してそのまま返す
+fn with_output_io<T>(
+    ruby: &Ruby,
+    io: MagnusValue,
+    compression: Compression,
+    f: impl FnOnce(&mut dyn Write) -> Result<T, CsvError>,
+) -> Result<T, MagnusError> {
+    let mut writer = RubyWriter::new(io);
+    let result = CompressedWriter::new(&mut writer, compression)
+        .map_err(|e| CsvError::io(e.to_string()))
+        .and_then(|mut output| {
+            let result = f(&mut output)?;
+            output.finish().map_err(|e| CsvError::io(e.to_string()))?;
+            Ok(result)
+        })
+        .and_then(|result| {
+            writer.flush().map_err(|e| CsvError::io(e.to_string()))?;
+            Ok(result)
+        });
+
+    match writer.take_error(ruby) {
+        Some(error) => Err(error),
+        None => result.map_err(|e| csv_error_to_ruby(ruby, e)),
+    }
+}
+
+/// CSVをグループごとに集計する
+///
+/// 型変換した値（`CsvValue`）をRust側で集計し、グループごとの結果だけをRubyのオブジェクトにする。
+/// 保持するのはグループごとの集計値だけのため、Rubyのメモリに収まらない大きなファイルも集計できる。
 ///
 /// # Arguments
 /// * `ruby` - Ruby VMの参照
-/// * `file_path` - 読み込むCSVファイルのパス
+/// * `args` - CSVファイルのパス（またはIO）と `group_by:`・`aggregations:`（必須）・`format:`（`:hashes`・`:arrays`）・
+///   `headers:`（デフォルトtrue）・`typed:`（デフォルトtrue）・`where:`・`trim:`・`col_sep:` 等のキーワード引数
 ///
 /// # Returns
-/// * `Result<Vec<Vec<MagnusValue>>, MagnusError>` - パース結果（数値は数値型）またはエラー
-pub fn read_typed_trim(ruby: &Ruby, file_path: String) -> Result<MagnusValue, MagnusError> {
-    let result = parse_csv_file_typed(&file_path, csv::Trim::All)
-        .map_err(|e| MagnusError::new(ruby.exception_runtime_error(), e.to_string()))?;
+/// * `Result<RArray, MagnusError>` - グループごとの結果（Hashの配列、または先頭に列名の行がある配列の配列）またはエラー
+pub fn aggregate(ruby: &Ruby, args: &[MagnusValue]) -> Result<RArray, MagnusError> {
+    let args = scan_args::<(MagnusValue,), (), (), (), RHash, ()>(args)?;
+    let input = input_from_ruby(ruby, args.required.0)?;
+    let defaults = CsvParseOptions { headers: true, typed: true, ..Default::default() };
+    let (options, aggregate_options, format) = aggregate_options_from_ruby(ruby, args.keywords, defaults)?;
+    let parse = &options.parse;
 
-    // Vec<Vec<CsvValue>> を Ruby配列に変換
-    let outer_array = ruby.ary_new();
-    for row in result {
-        let inner_array = ruby.ary_new();
-        for value in row {
-            inner_array.push(value.to_ruby(ruby))?;
+    // ファイルの集計はGVLを解放して行う（IOはRubyのメソッドを呼び出すため解放しない）
+    let table = match input {
+        Input::Text(path) => without_gvl(ruby, || aggregate_reader(open_csv_reader(&path)?, parse, &aggregate_options)),
+        Input::Io(io) => with_io(ruby, io, |reader| {
+            aggregate_reader(reader, parse, &aggregate_options).map_err(|e| csv_error_to_ruby(ruby, e))
+        }),
+    }?;
+
+    let columns: Vec<RString> = table.columns.iter().map(|name| ruby.str_new(name)).collect();
+    let result = ruby.ary_new_capa(table.rows.len() + 1);
+    if format == AggregateFormat::Arrays {
+        let header = ruby.ary_new_capa(columns.len());
+        for name in &columns {
+            header.push(*name)?;
         }
-        outer_array.push(inner_array.as_value())?;
+        result.push(header)?;
     }
+    for row in &table.rows {
+        match format {
+            AggregateFormat::Hashes => {
+                let entry = ruby.hash_new();
+                for (name, value) in columns.iter().zip(row) {
+                    entry.aset(*name, value.to_ruby(ruby))?;
+                }
+                result.push(entry)?;
+            }
+            AggregateFormat::Arrays => {
+                let values = ruby.ary_new_capa(row.len());
+                for value in row {
+                    values.push(value.to_ruby(ruby))?;
+                }
+                result.push(values)?;
+            }
+        }
+    }
+    Ok(result)
+}
 
-    Ok(outer_array.as_value())
+/// `each_slice` で最初に確保する読み込みバッファの行数の上限（それ以上は必要に応じて増やす）
+const BATCH_PREALLOCATION: usize = 1024;
+
+/// CSVを1行ずつ読み込み、ブロックに渡す
+///
+/// 全体をメモリに読み込まないため、大きなファイルや `$stdin` 等のIOでも一定のメモリで処理できる。
+/// ブロックを渡さない場合はEnumeratorを返す。`first`・`find`・`lazy` 等で途中で止めた場合は
+/// その時点で読み込みを止めてファイルを閉じる。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - CSVファイルのパス（またはIO）と `headers:`・`typed:`・`trim:`・`converters:` 等のキーワード引数
+///
+/// # Returns
+/// * `Result<MagnusValue, MagnusError>` - nil（ブロックなしの場合はEnumerator）またはエラー
+pub fn foreach(ruby: &Ruby, rb_self: MagnusValue, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    let args = scan_args::<(MagnusValue,), (), (), (), RHash, ()>(args)?;
+    if !ruby.block_given() {
+        return Ok(rb_self.enumeratorize("foreach", (args.required.0, KwArgs(args.keywords))).as_value());
+    }
+
+    let input = input_from_ruby(ruby, args.required.0)?;
+    let options = read_options_from_ruby(ruby, args.keywords, STREAM_OPTIONS, CsvParseOptions::default())?;
+
+    with_stream_input(ruby, input, |reader| each_record(ruby, reader, &options))?;
+    Ok(ruby.qnil().as_value())
+}
+
+/// CSVを `size` 行ずつ読み込み、行の配列としてブロックに渡す
+///
+/// データベースへの一括挿入等に使う。読み込み用のバッファはバッチ間で使い回し、
+/// ブロックから `break` した場合はその時点で読み込みを止めてファイルを閉じる。
+///
+/// # Arguments
+/// * `ruby` - Ruby VMの参照
+/// * `args` - CSVファイルのパス（またはIO）、1バッチの行数と `foreach` と同じキーワード引数
+///
+/// # Returns
+/// * `Result<MagnusValue, MagnusError>` - nil（ブロックなしの場合はEnumerator）またはエラー
+pub fn each_slice(ruby: &Ruby, rb_self: MagnusValue, args: &[MagnusValue]) -> Result<MagnusValue, MagnusError> {
+    let args = scan_args::<(MagnusValue, usize), (), (), (), RHash, ()>(args)?;
+    let (input, size) = args.required;
+    if size == 0 {
+        return Err(MagnusError::new(ruby.exception_arg_error(), "invalid slice size"));
+    }
+    if !ruby.block_given() {
+        return Ok(rb_self.enumeratorize("each_slice", (input, size, KwArgs(args.keywords))).as_value());
+    }
+
+    let input = input_from_ruby(ruby, input)?;
+    let options = read_options_from_ruby(ruby, args.keywords, STREAM_OPTIONS, CsvParseOptions::default())?;
+
+    with_stream_input(ruby, input, |reader| each_batch(ruby, reader, size, &options))?;
+    Ok(ruby.qnil().as_value())
+}
+
+/// ファイル（パス）またはIOを開いて読み込み処理に渡す
+fn with_stream_input<T>(
+    ruby: &Ruby,
+    input: Input,
+    f: impl FnOnce(Box<dyn Read + '_>) -> Result<T, MagnusError>,
+) -> Result<T, MagnusError> {
+    match input {
+        Input::Text(path) => {
+            let file = open_csv_reader(&path).map_err(|e| csv_error_to_ruby(ruby, e))?;
+            f(Box::new(file))
+        }
+        Input::Io(io) => with_io(ruby, io, f),
+    }
+}
+
+/// 読み込んだレコードを1件ずつRubyの行に変換する
+///
+/// `foreach`・`each_slice` と `RbCsv::Reader` で共通に使う。先頭行でヘッダーの検証と
+/// コンバーターの対象列の解決を行い、以降の行をオプションに応じた配列・Hashに変換する。
+/// Rubyの値（コンバーターのProc、ヘッダーのキー）は保持者がGCからマークすること（`mark`）。
+pub struct RowBuilder {
+    options: CsvParseOptions,
+    strings: StringMode,
+    trim_fields: bool,
+    procs: Vec<Opaque<MagnusValue>>,
+    /// 列番号ごとのコンバーター（ヘッダー名で指定されている場合は先頭行を読むまでNone）
+    resolved: Option<HashMap<usize, Converter>>,
+    /// 先頭行をヘッダーとして扱う（型変換しない）か
+    raw_first_row: bool,
+    header_row: Option<(Headers, Opaque<RArray>)>,
+    /// 行の絞り込み（`where:`）
+    selector: RowSelector,
+}
+
+impl RowBuilder {
+    pub fn new(
+        ruby: &Ruby,
+        options: CsvParseOptions,
+        strings: StringMode,
+        procs: Vec<MagnusValue>,
+    ) -> Result<Self, MagnusError> {
+        let reads_header = options.reads_header_row();
+        let resolved = if reads_header {
+            None
+        } else {
+            Some(resolve_converters(ruby, &options, None)?)
+        };
+
+        Ok(Self {
+            trim_fields: options.trim.trims_fields(),
+            raw_first_row: options.typed && reads_header,
+            selector: RowSelector::new(options.filter.clone()),
+            options,
+            strings,
+            procs: procs.into_iter().map(Opaque::from).collect(),
+            resolved,
+            header_row: None,
+        })
+    }
+
+    pub fn mark(&self, marker: &Marker) {
+        for proc in &self.procs {
+            marker.mark(*proc);
+        }
+        if let Some((_, keys)) = &self.header_row {
+            marker.mark(*keys);
+        }
+    }
+
+    /// 読み込んだレコードの前後の空白を除去する（先頭行はヘッダー行の設定に従う）
+    pub fn trim(&self, line_num: usize, record: &mut csv::StringRecord) {
+        self.options.trim.apply(record, line_num == 0);
+    }
+
+    /// 行を返すか（`where:` の条件を満たすか）。先頭行で条件の列名を解決する
+    /// ヘッダー行は常に真を返すため、`take_header` より先に呼び出す
+    /// 型変換する行はコンバーターで変換した値で評価する
+    pub fn select(&mut self, ruby: &Ruby, line_num: usize, record: &csv::StringRecord) -> Result<bool, MagnusError> {
+        let selected = match &self.resolved {
+            Some(converters) if self.options.typed => {
+                self.selector.select_converted(line_num, record, converters, self.trim_fields)
+            }
+            _ => self.selector.select(line_num, record),
+        };
+        selected.map_err(|e| csv_error_to_ruby(ruby, e))
+    }
+
+    /// `headers: true` か
+    pub fn uses_headers(&self) -> bool {
+        self.options.headers
+    }
+
+    /// ヘッダー名から生成したHashのキー（ヘッダー行を読む前はNone）
+    pub fn header_keys(&self, ruby: &Ruby) -> Option<RArray> {
+        self.header_row.as_ref().map(|(_, keys)| ruby.get_inner(*keys))
+    }
+
+    /// 先頭行を処理する（ヘッダーの検証、コンバーターの対象列の解決）
+    /// `headers: true` の場合はヘッダーとして取り込み、真を返す（その行は返さない）
+    pub fn take_header(&mut self, ruby: &Ruby, line_num: usize, record: &csv::StringRecord) -> Result<bool, MagnusError> {
+        if line_num != 0 {
+            return Ok(false);
+        }
+
+        let raw_headers = record.iter().collect::<Vec<&str>>();
+        if let Some(validation) = &self.options.validation {
+            validation.validate(&raw_headers).map_err(|e| csv_error_to_ruby(ruby, e))?;
+        }
+        if self.resolved.is_none() {
+            self.resolved = Some(resolve_converters(ruby, &self.options, Some(record))?);
+        }
+        if !self.options.headers {
+            return Ok(false);
+        }
+
+        let headers = self
+            .options
+            .header_options
+            .process(&raw_headers)
+            .map_err(|e| csv_error_to_ruby(ruby, e))?;
+        let keys = header_keys(ruby, &headers)?;
+        self.header_row = Some((headers, Opaque::from(keys)));
+        Ok(true)
+    }
+
+    pub fn build(&self, ruby: &Ruby, line_num: usize, record: &csv::StringRecord) -> Result<MagnusValue, MagnusError> {
+        let strings = self.strings;
+        let typed = self.options.typed && !(self.raw_first_row && line_num == 0);
+        let typed_values = || {
+            let converters = self.resolved.as_ref().expect("converters are resolved on the first row");
+            typed_record(record, converters, self.trim_fields)
+        };
+        let procs = || self.procs.iter().map(|proc| ruby.get_inner(*proc)).collect::<Vec<MagnusValue>>();
+
+        let row = match (&self.header_row, typed) {
+            (Some((headers, keys)), true) => {
+                let procs = procs();
+                row_to_hash(ruby, headers, ruby.get_inner(*keys), typed_values(), |value| {
+                    typed_value_to_ruby(ruby, value, &procs, strings)
+                })?
+                .as_value()
+            }
+            (Some((headers, keys)), false) => {
+                row_to_hash(ruby, headers, ruby.get_inner(*keys), record.iter(), |field| {
+                    Ok(strings.str_new(ruby, field).as_value())
+                })?
+                .as_value()
+            }
+            (None, true) => {
+                let procs = procs();
+                let values = typed_values();
+                let array = ruby.ary_new_capa(values.len());
+                for value in values {
+                    array.push(typed_value_to_ruby(ruby, value, &procs, strings)?)?;
+                }
+                array.as_value()
+            }
+            (None, false) => {
+                let array = ruby.ary_new_capa(record.len());
+                for field in record.iter() {
+                    array.push(strings.str_new(ruby, field))?;
+                }
+                array.as_value()
+            }
+        };
+        Ok(row)
+    }
+}
+
+/// コンバーターの対象列を解決し、列番号ごとのコンバーターにする
+fn resolve_converters(
+    ruby: &Ruby,
+    options: &CsvParseOptions,
+    headers: Option<&csv::StringRecord>,
+) -> Result<HashMap<usize, Converter>, MagnusError> {
+    let resolved = options.converters.resolve(headers, &options.header_options).map_err(|e| csv_error_to_ruby(ruby, e))?;
+    Ok(resolved.into_iter().map(|(index, converter)| (index, converter.clone())).collect())
+}
+
+/// レコードを1件ずつRubyの値に変換してブロックに渡す
+fn each_record<R: Read>(ruby: &Ruby, input: R, options: &ReadOptions) -> Result<(), MagnusError> {
+    let mut reader = dialect_reader(input, &options.parse.dialect);
+    let mut builder = RowBuilder::new(ruby, options.parse.clone(), options.strings, options.procs.clone())?;
+    let mut record = csv::StringRecord::new();
+    let mut line_num = 0;
+
+    while reader
+        .read_record(&mut record)
+        .map_err(|e| csv_error_to_ruby(ruby, record_error(line_num, e)))?
+    {
+        builder.trim(line_num, &mut record);
+        let selected = builder.select(ruby, line_num, &record)?;
+        if !builder.take_header(ruby, line_num, &record)? && selected {
+            let _: MagnusValue = ruby.yield_value(builder.build(ruby, line_num, &record)?)?;
+        }
+        line_num += 1;
+    }
+
+    Ok(())
+}
+
+/// レコードを `size` 件ずつ読み込み、Rubyの配列に変換してブロックに渡す
+/// 読み込み用の `StringRecord` はバッチ間で使い回す
+fn each_batch<R: Read>(ruby: &Ruby, input: R, size: usize, options: &ReadOptions) -> Result<(), MagnusError> {
+    let mut reader = dialect_reader(input, &options.parse.dialect);
+    let mut builder = RowBuilder::new(ruby, options.parse.clone(), options.strings, options.procs.clone())?;
+    let mut records = vec![csv::StringRecord::new(); size.min(BATCH_PREALLOCATION)];
+    let mut line_num = 0;
+
+    loop {
+        let mut filled = 0;
+        // バッチの先頭のレコードの行番号（`build` は先頭行かどうかの判定にのみ使うため、絞り込んだ行の分は詰める）
+        let mut first_line = line_num;
+        while filled < size {
+            if filled == records.len() {
+                records.push(csv::StringRecord::new());
+            }
+            let more = reader
+                .read_record(&mut records[filled])
+                .map_err(|e| csv_error_to_ruby(ruby, record_error(line_num, e)))?;
+            if !more {
+                break;
+            }
+            builder.trim(line_num, &mut records[filled]);
+            let selected = builder.select(ruby, line_num, &records[filled])?;
+            if builder.take_header(ruby, line_num, &records[filled])? || !selected {
+                if filled == 0 {
+                    first_line = line_num + 1;
+                }
+            } else {
+                filled += 1;
+            }
+            line_num += 1;
+        }
+
+        if filled == 0 {
+            return Ok(());
+        }
+
+        let batch = ruby.ary_new_capa(filled);
+        for (index, record) in records[..filled].iter().enumerate() {
+            batch.push(builder.build(ruby, first_line + index, record)?)?;
+        }
+        let _: MagnusValue = ruby.yield_value(batch)?;
+
+        if filled < size {
+            return Ok(());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -173,7 +1406,7 @@ mod tests {
     #[test]
     fn test_parse_basic() {
         let csv_data = "a,b,c\n1,2,3";
-        let result = crate::parser::parse_csv_core(csv_data, csv::Trim::None);
+        let result = crate::parser::parse_csv_core(csv_data, &crate::parser::CsvParseOptions::default());
 
         assert!(result.is_ok());
         let records = result.unwrap();
@@ -185,7 +1418,7 @@ mod tests {
     #[test]
     fn test_parse_with_trim_enabled() {
         let csv_data = " a , b , c \n 1 , 2 , 3 ";
-        let result = crate::parser::parse_csv_core(csv_data, csv::Trim::All);
+        let result = crate::parser::parse_csv_core(csv_data, &super::trimmed());
 
         assert!(result.is_ok());
         let records = result.unwrap();
@@ -196,7 +1429,7 @@ mod tests {
     #[test]
     fn test_parse_with_trim_disabled() {
         let csv_data = " a , b , c \n 1 , 2 , 3 ";
-        let result = crate::parser::parse_csv_core(csv_data, csv::Trim::None);
+        let result = crate::parser::parse_csv_core(csv_data, &crate::parser::CsvParseOptions::default());
 
         assert!(result.is_ok());
         let records = result.unwrap();
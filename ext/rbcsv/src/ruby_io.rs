@@ -0,0 +1,206 @@
+use magnus::{
+    error::OpaqueError,
+    value::{Opaque, ReprValue},
+    Error as MagnusError, RString, Ruby, Value as MagnusValue,
+};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// IOの1回の呼び出しで読み書きするバイト数の下限
+const MIN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// RubyのIOオブジェクトを `std::io::Read` として扱うアダプター
+///
+/// `readpartial` に応答するIO（File, Socket, パイプ等）は到着済みのデータをすぐに受け取れるよう
+/// `readpartial` を、それ以外（StringIO互換のオブジェクト、Zlib::GzipReader等）は `read(len)` を呼び出す。
+/// Ruby側で発生した例外は保持しておき、`take_error` で元の例外として取り出せる。
+/// 返す `io::Error` にも例外を包んでおくため、リーダーを手放した後でも `exception_from_io` で取り出せる。
+///
+/// IOの参照は保持しないため、`RbCsv::Reader` のように長く保持する場合は所有者がIOをGCからマークすること。
+pub struct RubyReader {
+    io: Opaque<MagnusValue>,
+    partial: bool,
+    buffer: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    error: Option<OpaqueError>,
+}
+
+impl RubyReader {
+    pub fn new(io: MagnusValue) -> Result<Self, MagnusError> {
+        let partial = io.respond_to("readpartial", false)?;
+        Ok(Self {
+            io: Opaque::from(io),
+            partial,
+            buffer: Vec::new(),
+            pos: 0,
+            eof: false,
+            error: None,
+        })
+    }
+
+    /// `read` に応答するオブジェクトか
+    pub fn is_io(value: MagnusValue) -> Result<bool, MagnusError> {
+        value.respond_to("read", false)
+    }
+
+    /// 読み込み中に発生したRubyの例外を取り出す
+    pub fn take_error(&mut self, ruby: &Ruby) -> Option<MagnusError> {
+        self.error.take().map(|error| OpaqueError::into_error_with(error, ruby))
+    }
+
+    /// IOから次のチャンクを読み込む（EOFならNone）
+    fn fetch(&self, ruby: &Ruby, len: usize) -> Result<Option<Vec<u8>>, MagnusError> {
+        let io = ruby.get_inner(self.io);
+        let chunk: Option<RString> = if self.partial {
+            match io.funcall("readpartial", (len,)) {
+                Ok(chunk) => Some(chunk),
+                Err(e) if e.is_kind_of(ruby.exception_eof_error()) => None,
+                Err(e) => return Err(e),
+            }
+        } else {
+            io.funcall("read", (len,))?
+        };
+
+        // Rubyの文字列はすぐにコピーし、GCの対象になる参照を保持しない
+        Ok(chunk.map(|chunk| unsafe { chunk.as_slice() }.to_vec()))
+    }
+}
+
+/// 読み込み中に発生したRubyの例外（`io::Error` に包んで呼び出し元に伝える）
+#[derive(Clone)]
+struct IoException {
+    error: OpaqueError,
+    message: String,
+}
+
+impl fmt::Debug for IoException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IoException").field(&self.message).finish()
+    }
+}
+
+impl fmt::Display for IoException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for IoException {}
+
+/// `RubyReader` が返した `io::Error` から元のRubyの例外を取り出す
+pub fn exception_from_io(ruby: &Ruby, error: &io::Error) -> Option<MagnusError> {
+    let exception = error.get_ref()?.downcast_ref::<IoException>()?;
+    Some(OpaqueError::into_error_with(exception.error.clone(), ruby))
+}
+
+impl Read for RubyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.buffer.len() {
+            if self.eof || buf.is_empty() {
+                return Ok(0);
+            }
+
+            let ruby = Ruby::get().map_err(io::Error::other)?;
+            match self.fetch(&ruby, buf.len().max(MIN_CHUNK_SIZE)) {
+                Ok(Some(chunk)) if chunk.is_empty() => self.eof = true,
+                Ok(Some(chunk)) => {
+                    self.buffer = chunk;
+                    self.pos = 0;
+                }
+                Ok(None) => self.eof = true,
+                Err(e) => {
+                    let exception = IoException {
+                        message: e.to_string(),
+                        error: OpaqueError::from(e),
+                    };
+                    self.error = Some(exception.error.clone());
+                    return Err(io::Error::other(exception));
+                }
+            }
+        }
+
+        let available = &self.buffer[self.pos..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// RubyのIOを `std::io::Write` として扱うアダプター
+///
+/// 書き込まれたバイト列はためておき、一定量ごとにIOの `write` に渡す。UTF-8の文字の途中では区切らない
+/// （圧縮したデータ等、UTF-8でないバイト列はバイナリの文字列として渡す）。
+/// Ruby側で発生した例外は `RubyReader` と同じく保持しておき、`take_error` で取り出せる。
+/// GVLを保持したまま使うこと。
+pub struct RubyWriter {
+    io: Opaque<MagnusValue>,
+    buffer: Vec<u8>,
+    error: Option<OpaqueError>,
+}
+
+impl RubyWriter {
+    pub fn new(io: MagnusValue) -> Self {
+        Self {
+            io: Opaque::from(io),
+            buffer: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// 書き込み中に発生したRubyの例外を取り出す
+    pub fn take_error(&mut self, ruby: &Ruby) -> Option<MagnusError> {
+        self.error.take().map(|error| OpaqueError::into_error_with(error, ruby))
+    }
+
+    /// たまったバイト列をIOに渡す（`all` が偽の場合、末尾の不完全なUTF-8の文字は残す）
+    fn send(&mut self, all: bool) -> io::Result<()> {
+        let len = match std::str::from_utf8(&self.buffer) {
+            Err(e) if !all && e.error_len().is_none() => e.valid_up_to(),
+            _ => self.buffer.len(),
+        };
+        if len == 0 {
+            return Ok(());
+        }
+
+        let ruby = Ruby::get().map_err(io::Error::other)?;
+        let rest = self.buffer.split_off(len);
+        let chunk = std::mem::replace(&mut self.buffer, rest);
+        if let Err(e) = write_bytes(&ruby, ruby.get_inner(self.io), chunk) {
+            let exception = IoException {
+                message: e.to_string(),
+                error: OpaqueError::from(e),
+            };
+            self.error = Some(exception.error.clone());
+            return Err(io::Error::other(exception));
+        }
+
+        Ok(())
+    }
+}
+
+/// バイト列をRubyの文字列にしてIOの `write` に渡す
+/// UTF-8でないバイト列（圧縮したデータ等）はバイナリの文字列として渡す
+pub fn write_bytes(ruby: &Ruby, io: MagnusValue, bytes: Vec<u8>) -> Result<(), MagnusError> {
+    let chunk = match String::from_utf8(bytes) {
+        Ok(s) => ruby.str_new(&s),
+        Err(e) => ruby.str_from_slice(e.as_bytes()),
+    };
+    let _: MagnusValue = io.funcall("write", (chunk,))?;
+    Ok(())
+}
+
+impl Write for RubyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= MIN_CHUNK_SIZE {
+            self.send(false)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send(true)
+    }
+}
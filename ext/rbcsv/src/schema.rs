@@ -0,0 +1,190 @@
+use crate::converter::ColumnKey;
+use crate::error::CsvError;
+use crate::header::HeaderOptions;
+use crate::value::CsvValue;
+
+/// スキーマで指定できるメンバーの型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+    Integer,
+    Float,
+    String,
+    Boolean,
+}
+
+impl FieldType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "integer" => Some(FieldType::Integer),
+            "float" => Some(FieldType::Float),
+            "string" => Some(FieldType::String),
+            "boolean" => Some(FieldType::Boolean),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldType::Integer => "integer",
+            FieldType::Float => "float",
+            FieldType::String => "string",
+            FieldType::Boolean => "boolean",
+        }
+    }
+
+    /// フィールドを指定の型に変換する
+    /// 文字列以外の型では空文字列をnilとして扱う
+    pub fn coerce(&self, field: &str) -> Option<CsvValue> {
+        if field.is_empty() && *self != FieldType::String {
+            return Some(CsvValue::Nil);
+        }
+
+        match self {
+            FieldType::Integer => field.parse::<i64>().ok().map(CsvValue::Integer),
+            FieldType::Float => field
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(CsvValue::Float),
+            FieldType::String => Some(CsvValue::String(field.to_string())),
+            FieldType::Boolean => match field.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "1" => Some(CsvValue::Boolean(true)),
+                "false" | "no" | "0" => Some(CsvValue::Boolean(false)),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// ヘッダーとレコードクラスのメンバーの対応付け
+#[derive(Debug, Clone)]
+pub struct RecordSchema {
+    members: Vec<String>,
+    types: Vec<Option<FieldType>>,
+}
+
+impl RecordSchema {
+    pub fn new(members: Vec<String>) -> Self {
+        let types = vec![None; members.len()];
+        Self { members, types }
+    }
+
+    /// メンバーの型を指定する
+    pub fn set_type(&mut self, member: &str, field_type: FieldType) -> Result<(), CsvError> {
+        match self.members.iter().position(|m| m == member) {
+            Some(index) => {
+                self.types[index] = Some(field_type);
+                Ok(())
+            }
+            None => Err(CsvError::invalid_data(format!("Schema member not found: {}", member))),
+        }
+    }
+
+    /// メンバーごとに対応するヘッダーの列番号を求める（`ColumnKey::resolve` と同じ規則）
+    pub fn resolve(&self, headers: &[&str], header_options: &HeaderOptions) -> Result<Vec<usize>, CsvError> {
+        let mut indices = Vec::with_capacity(self.members.len());
+        let mut missing = Vec::new();
+
+        for member in &self.members {
+            let key = ColumnKey::Name(member.clone());
+            match key.resolve(Some(headers.iter().copied()), header_options, "Record") {
+                Ok(index) => indices.push(index),
+                Err(_) => missing.push(member.as_str()),
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(CsvError::invalid_data(format!(
+                "Headers missing for record members: {}",
+                missing.join(", ")
+            )));
+        }
+
+        Ok(indices)
+    }
+
+    /// 1行分のフィールドをメンバー順の値に変換する
+    ///
+    /// # Arguments
+    /// * `indices` - `resolve` で求めた列番号
+    /// * `row` - 行のフィールド
+    /// * `line_num` - エラーメッセージ用の行番号（1始まり）
+    pub fn convert_row<S: AsRef<str>>(
+        &self,
+        indices: &[usize],
+        row: &[S],
+        line_num: usize,
+    ) -> Result<Vec<CsvValue>, CsvError> {
+        indices
+            .iter()
+            .zip(&self.types)
+            .zip(&self.members)
+            .map(|((&index, field_type), member)| {
+                let field = row.get(index).map(AsRef::as_ref).unwrap_or("");
+                match field_type {
+                    Some(field_type) => field_type.coerce(field).ok_or_else(|| {
+                        CsvError::invalid_data(format!(
+                            "Cannot convert '{}' to {} for member '{}' at line {}",
+                            field,
+                            field_type.name(),
+                            member,
+                            line_num
+                        ))
+                    }),
+                    None => Ok(CsvValue::String(field.to_string())),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::HeaderConverter;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_field_type_coerce() {
+        assert_eq!(FieldType::Integer.coerce("42"), Some(CsvValue::Integer(42)));
+        assert_eq!(FieldType::Integer.coerce("4.2"), None);
+        assert_eq!(FieldType::Float.coerce("4.5"), Some(CsvValue::Float(4.5)));
+        assert_eq!(FieldType::Boolean.coerce("Yes"), Some(CsvValue::Boolean(true)));
+        assert_eq!(FieldType::Integer.coerce(""), Some(CsvValue::Nil));
+        assert_eq!(FieldType::String.coerce(""), Some(CsvValue::String(String::new())));
+    }
+
+    #[test]
+    fn test_resolve_and_convert_row() {
+        let mut schema = RecordSchema::new(strings(&["age", "name"]));
+        schema.set_type("age", FieldType::Integer).unwrap();
+        assert!(schema.set_type("city", FieldType::String).is_err());
+
+        let indices = schema.resolve(&["name", "age", "city"], &HeaderOptions::default()).unwrap();
+        assert_eq!(indices, vec![1, 0]);
+
+        let row = schema.convert_row(&indices, &strings(&["Alice", "25", "Tokyo"]), 2).unwrap();
+        assert_eq!(row, vec![CsvValue::Integer(25), CsvValue::String("Alice".to_string())]);
+
+        let err = schema.convert_row(&indices, &strings(&["Bob", "x", "Osaka"]), 3).unwrap_err();
+        assert!(err.to_string().contains("at line 3"));
+    }
+
+    #[test]
+    fn test_resolve_missing_headers() {
+        let schema = RecordSchema::new(strings(&["name", "email"]));
+        let err = schema.resolve(&["name", "age"], &HeaderOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("email"));
+    }
+
+    #[test]
+    fn test_resolve_normalized_headers() {
+        let schema = RecordSchema::new(strings(&["unit_price", "Name"]));
+        let options = HeaderOptions { converters: vec![HeaderConverter::SnakeCase], ..Default::default() };
+        assert_eq!(schema.resolve(&["Name", "Unit Price"], &options).unwrap(), vec![1, 0]);
+        assert!(schema.resolve(&["Name", "Unit Price"], &HeaderOptions::default()).is_err());
+    }
+}
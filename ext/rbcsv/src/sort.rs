@@ -0,0 +1,440 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use crate::converter::ColumnKey;
+use crate::error::CsvError;
+use crate::gvl::check_interrupt;
+use crate::parser::{dialect_writer, CsvParseOptions, Dialect, RecordReader, INTERRUPT_CHECK_INTERVAL};
+use crate::value::CsvValue;
+
+/// メモリ上に保持するレコードの大きさの既定の上限（バイト数、`memory_limit:`）
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// 一時ファイル名の重複を避けるための連番
+static SPILL_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// 並び順（`order:`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    /// Rubyのオプション名（`:asc`・`:desc`）から変換する
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "asc" => Some(SortOrder::Asc),
+            "desc" => Some(SortOrder::Desc),
+            _ => None,
+        }
+    }
+}
+
+/// 並べ替えのキー（`by:` の1列分）
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub column: ColumnKey,
+    pub order: SortOrder,
+}
+
+/// 並べ替えの設定
+#[derive(Debug, Clone)]
+pub struct SortOptions {
+    pub keys: Vec<SortKey>,
+    /// メモリ上に保持するレコードの大きさの上限。超えた分は並べ替えて一時ファイルに書き出す
+    pub memory_limit: usize,
+}
+
+impl Default for SortOptions {
+    fn default() -> Self {
+        Self { keys: Vec::new(), memory_limit: DEFAULT_MEMORY_LIMIT }
+    }
+}
+
+/// 列番号に解決したキー
+#[derive(Debug, Clone, Copy)]
+struct ResolvedKey {
+    index: usize,
+    order: SortOrder,
+}
+
+/// キーの値を計算済みのレコード
+struct Entry {
+    key: Vec<CsvValue>,
+    record: csv::StringRecord,
+}
+
+/// CSVを読み込んでキーの順に並べ替える
+///
+/// レコードの合計が `memory_limit` を超えた場合は、並べ替えた分を一時ファイルに書き出し（ラン）、
+/// 書き込み時にk-wayマージする。キーが等しいレコードは元の順序を保つ（安定ソート）。
+/// `typed` が真の場合は `CsvValue` に変換して比較し（数値は数値として、数値は文字列より前）、
+/// 空の値は並び順にかかわらず末尾にする。
+///
+/// # Arguments
+/// * `input` - CSVの読み込み元
+/// * `parse` - 書式・ヘッダー・trim・型変換の設定（`headers` が真の場合は1行目をヘッダーとして先頭に残す）
+/// * `options` - 並べ替えの設定
+pub fn sort_reader<R: Read>(
+    input: R,
+    parse: &CsvParseOptions,
+    options: &SortOptions,
+) -> Result<SortedRecords, CsvError> {
+    // 空の入力（ヘッダー行もない）は何も書き込まない
+    let Some((mut records, header)) = RecordReader::open(input, parse)? else {
+        return Ok(SortedRecords { header: None, keys: Vec::new(), runs: Vec::new(), buffer: Vec::new(), typed: parse.typed });
+    };
+    let keys = options
+        .keys
+        .iter()
+        .map(|key| Ok(ResolvedKey { index: key.column.resolve(header.as_ref(), &parse.header_options, "Sort")?, order: key.order }))
+        .collect::<Result<Vec<_>, CsvError>>()?;
+    let mut record = csv::StringRecord::new();
+    let mut buffer = Vec::new();
+    let mut buffered = 0;
+    let mut runs = Vec::new();
+
+    while records.read(&mut record)?.is_some() {
+        // レコードはバッファに移し、同じ大きさの空のレコードで読み込みを続ける
+        let next = csv::StringRecord::with_capacity(record.as_slice().len(), record.len());
+        let entry = Entry {
+            key: sort_key(&keys, &record, parse.typed),
+            record: mem::replace(&mut record, next),
+        };
+        buffered += entry_size(&entry);
+        buffer.push(entry);
+
+        if buffered > options.memory_limit {
+            sort_entries(&mut buffer, &keys);
+            runs.push(SpillFile::write(&mem::take(&mut buffer))?);
+            buffered = 0;
+        }
+    }
+    sort_entries(&mut buffer, &keys);
+
+    Ok(SortedRecords { header, keys, runs, buffer, typed: parse.typed })
+}
+
+/// 並べ替えたレコード（一時ファイルのランとメモリ上の残り）
+pub struct SortedRecords {
+    header: Option<csv::StringRecord>,
+    keys: Vec<ResolvedKey>,
+    runs: Vec<SpillFile>,
+    buffer: Vec<Entry>,
+    typed: bool,
+}
+
+impl SortedRecords {
+    /// 一時ファイルに書き出したランの数
+    #[cfg(test)]
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// ヘッダー行に続けて、並べ替えたレコードを書き込む
+    ///
+    /// # Returns
+    /// * `Result<usize, CsvError>` - 書き込んだデータ行の数（ヘッダー行を除く）またはエラー
+    pub fn write_to<W: Write>(self, output: W, dialect: &Dialect) -> Result<usize, CsvError> {
+        let mut writer = dialect_writer(output, dialect);
+        if let Some(header) = &self.header {
+            writer.write_record(header)?;
+        }
+
+        // メモリ上の残りは最後に読み込んだ分なので、キーが等しい場合はランより後ろにする
+        let mut sources: Vec<Box<dyn Iterator<Item = Result<Entry, CsvError>>>> = Vec::new();
+        for run in &self.runs {
+            sources.push(Box::new(run.entries(&self.keys, self.typed)?));
+        }
+        sources.push(Box::new(self.buffer.into_iter().map(Ok)));
+
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        for (source, entries) in sources.iter_mut().enumerate() {
+            if let Some(entry) = entries.next().transpose()? {
+                heap.push(HeapItem { entry, source, keys: &self.keys });
+            }
+        }
+
+        let mut rows: usize = 0;
+        while let Some(HeapItem { entry, source, .. }) = heap.pop() {
+            if rows.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+                check_interrupt()?;
+            }
+            writer.write_record(&entry.record)?;
+            rows += 1;
+
+            if let Some(entry) = sources[source].next().transpose()? {
+                heap.push(HeapItem { entry, source, keys: &self.keys });
+            }
+        }
+
+        writer.flush().map_err(|e| CsvError::io(e.to_string()))?;
+        Ok(rows)
+    }
+}
+
+/// マージ中の各ランの先頭のレコード
+/// BinaryHeapは最大ヒープのため、比較を逆にして最小のキー（同じキーなら前のラン）から取り出す
+struct HeapItem<'a> {
+    entry: Entry,
+    source: usize,
+    keys: &'a [ResolvedKey],
+}
+
+impl Ord for HeapItem<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_keys(&self.entry.key, &other.entry.key, self.keys)
+            .then_with(|| self.source.cmp(&other.source))
+            .reverse()
+    }
+}
+
+impl PartialOrd for HeapItem<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapItem<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapItem<'_> {}
+
+/// 並べ替えたレコードを書き出した一時ファイル（削除は `Drop` で行う）
+struct SpillFile {
+    path: PathBuf,
+}
+
+impl SpillFile {
+    /// レコードを一時ディレクトリのファイルに書き出す
+    /// 書式は読み込み時の書式によらず標準のCSV（`,`・`"`）にする
+    fn write(entries: &[Entry]) -> Result<Self, CsvError> {
+        let sequence = SPILL_SEQUENCE.fetch_add(1, AtomicOrdering::Relaxed);
+        let path = std::env::temp_dir().join(format!("rbcsv-sort-{}-{}.csv", std::process::id(), sequence));
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| CsvError::io(format!("Failed to create temporary file '{}': {}", path.display(), e)))?;
+        let spill = Self { path };
+
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_writer(BufWriter::new(file));
+        for entry in entries {
+            writer.write_record(&entry.record)?;
+        }
+        writer.flush().map_err(|e| spill.error(e))?;
+
+        Ok(spill)
+    }
+
+    /// ランのレコードを先頭から読み込む（キーは読み込みながら計算し直す）
+    fn entries<'a>(
+        &self,
+        keys: &'a [ResolvedKey],
+        typed: bool,
+    ) -> Result<impl Iterator<Item = Result<Entry, CsvError>> + 'a, CsvError> {
+        let file = fs::File::open(&self.path).map_err(|e| self.error(e))?;
+        let reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(BufReader::new(file));
+
+        Ok(reader.into_records().map(move |record| {
+            let record = record?;
+            Ok(Entry { key: sort_key(keys, &record, typed), record })
+        }))
+    }
+
+    fn error(&self, e: std::io::Error) -> CsvError {
+        CsvError::io(format!("Failed to access temporary file '{}': {}", self.path.display(), e))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// レコードのキーの値を取り出す（列がない場合はNil）
+fn sort_key(keys: &[ResolvedKey], record: &csv::StringRecord, typed: bool) -> Vec<CsvValue> {
+    keys.iter()
+        .map(|key| match record.get(key.index) {
+            Some(field) if typed => CsvValue::from_str(field),
+            Some(field) => CsvValue::String(field.to_string()),
+            None => CsvValue::Nil,
+        })
+        .collect()
+}
+
+/// レコードが使うメモリの概算（`memory_limit` との比較に使う）
+fn entry_size(entry: &Entry) -> usize {
+    let key: usize = entry
+        .key
+        .iter()
+        .map(|value| match value {
+            CsvValue::String(s) => s.len(),
+            _ => 0,
+        })
+        .sum();
+
+    mem::size_of::<Entry>()
+        + entry.key.len() * mem::size_of::<CsvValue>()
+        + key
+        + entry.record.as_slice().len()
+        + entry.record.len() * mem::size_of::<usize>()
+}
+
+fn sort_entries(entries: &mut [Entry], keys: &[ResolvedKey]) {
+    // sort_byは安定ソートのため、キーが等しいレコードは読み込んだ順になる
+    entries.sort_by(|a, b| compare_keys(&a.key, &b.key, keys));
+}
+
+/// キーを先頭の列から順に比較する
+/// 空の値（空文字列・列がない）は並び順にかかわらず空でない値より後ろにする
+fn compare_keys(a: &[CsvValue], b: &[CsvValue], keys: &[ResolvedKey]) -> Ordering {
+    for ((a, b), key) in a.iter().zip(b).zip(keys) {
+        let ordering = match (is_empty(a), is_empty(b)) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => match key.order {
+                SortOrder::Asc => compare_values(a, b),
+                SortOrder::Desc => compare_values(a, b).reverse(),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+fn is_empty(value: &CsvValue) -> bool {
+    match value {
+        CsvValue::String(s) => s.is_empty(),
+        CsvValue::Nil => true,
+        _ => false,
+    }
+}
+
+/// 空でない値を比較する
+/// 数値同士は数値として比較し、数値は文字列より前にする。文字列はバイト順で比較する
+pub fn compare_values(a: &CsvValue, b: &CsvValue) -> Ordering {
+    match (a, b) {
+        (CsvValue::Integer(a), CsvValue::Integer(b)) => a.cmp(b),
+        (CsvValue::Integer(a), CsvValue::Float(b)) => (*a as f64).total_cmp(b),
+        (CsvValue::Float(a), CsvValue::Integer(b)) => a.total_cmp(&(*b as f64)),
+        (CsvValue::Float(a), CsvValue::Float(b)) => a.total_cmp(b),
+        (CsvValue::String(a), CsvValue::String(b)) => a.cmp(b),
+        (CsvValue::Integer(_) | CsvValue::Float(_), _) => Ordering::Less,
+        (_, CsvValue::Integer(_) | CsvValue::Float(_)) => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort(input: &str, parse: &CsvParseOptions, options: &SortOptions) -> (String, usize) {
+        let sorted = sort_reader(input.as_bytes(), parse, options).unwrap();
+        let mut output = Vec::new();
+        let rows = sorted.write_to(&mut output, &parse.dialect).unwrap();
+        (String::from_utf8(output).unwrap(), rows)
+    }
+
+    fn key(column: ColumnKey, order: SortOrder) -> SortKey {
+        SortKey { column, order }
+    }
+
+    #[test]
+    fn test_sort_typed_and_untyped() {
+        let input = "name,score\nb,10\na,9\nc,\nd,100\n";
+        let parse = CsvParseOptions { headers: true, typed: true, ..Default::default() };
+        let options = SortOptions { keys: vec![key(ColumnKey::Name("score".to_string()), SortOrder::Asc)], ..Default::default() };
+        let (output, _) = sort(input, &parse, &options);
+        assert_eq!(output, "name,score\na,9\nb,10\nd,100\nc,\n");
+
+        // 文字列として比較すると "10" < "100" < "9"
+        let parse = CsvParseOptions { headers: true, ..Default::default() };
+        let (output, _) = sort(input, &parse, &options);
+        assert_eq!(output, "name,score\nb,10\nd,100\na,9\nc,\n");
+
+        // 降順でも空の値は末尾
+        let parse = CsvParseOptions { headers: true, typed: true, ..Default::default() };
+        let options = SortOptions { keys: vec![key(ColumnKey::Index(1), SortOrder::Desc)], ..Default::default() };
+        let (output, _) = sort(input, &parse, &options);
+        assert_eq!(output, "name,score\nd,100\nb,10\na,9\nc,\n");
+    }
+
+    #[test]
+    fn test_sort_multiple_keys_is_stable() {
+        let input = "1,b,x\n2,a,y\n1,a,z\n2,a,w\n";
+        let parse = CsvParseOptions::default();
+        let options = SortOptions {
+            keys: vec![key(ColumnKey::Index(0), SortOrder::Desc), key(ColumnKey::Index(1), SortOrder::Asc)],
+            ..Default::default()
+        };
+        let (output, rows) = sort(input, &parse, &options);
+        assert_eq!(output, "2,a,y\n2,a,w\n1,a,z\n1,b,x\n");
+        assert_eq!(rows, 4);
+    }
+
+    #[test]
+    fn test_sort_spills_to_temporary_files() {
+        let mut input = String::from("id,note\n");
+        for i in (0..500).rev() {
+            input.push_str(&format!("{},\"line\nbreak {}\"\n", i % 250, i));
+        }
+        let parse = CsvParseOptions { headers: true, typed: true, ..Default::default() };
+        let options = SortOptions { keys: vec![key(ColumnKey::Name("id".to_string()), SortOrder::Asc)], memory_limit: 4096 };
+
+        let sorted = sort_reader(input.as_bytes(), &parse, &options).unwrap();
+        assert!(sorted.spilled_runs() > 1);
+        let paths: Vec<PathBuf> = sorted.runs.iter().map(|run| run.path.clone()).collect();
+
+        let mut output = Vec::new();
+        assert_eq!(sorted.write_to(&mut output, &parse.dialect).unwrap(), 500);
+        assert!(paths.iter().all(|path| !path.exists()));
+
+        let mut reader = csv::ReaderBuilder::new().from_reader(output.as_slice());
+        let rows: Vec<(i64, String)> = reader
+            .records()
+            .map(|record| {
+                let record = record.unwrap();
+                (record[0].parse().unwrap(), record[1].to_string())
+            })
+            .collect();
+        assert_eq!(rows.len(), 500);
+        assert!(rows.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        // 同じキーのレコードは入力の順（iの降順）のまま
+        assert_eq!(rows[0], (0, "line\nbreak 250".to_string()));
+        assert_eq!(rows[1], (0, "line\nbreak 0".to_string()));
+    }
+
+    #[test]
+    fn test_sort_unknown_column() {
+        let parse = CsvParseOptions { headers: true, ..Default::default() };
+        let options = SortOptions { keys: vec![key(ColumnKey::Name("missing".to_string()), SortOrder::Asc)], ..Default::default() };
+        let error = sort_reader("a,b\n".as_bytes(), &parse, &options).err().unwrap();
+        assert!(error.to_string().contains("Sort column not found in headers: missing"));
+
+        let parse = CsvParseOptions::default();
+        let error = sort_reader("a,b\n".as_bytes(), &parse, &options).err().unwrap();
+        assert!(error.to_string().contains("requires a header row"));
+    }
+}
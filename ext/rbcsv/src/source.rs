@@ -0,0 +1,126 @@
+use crate::compression::{decompress, open_csv_file, Compression};
+use crate::error::CsvError;
+use memmap2::Mmap;
+use std::io::Read;
+use std::str;
+
+/// CSVファイルの内容
+///
+/// 非圧縮の通常ファイルはメモリマップし、マップしたバイト列から直接パースする。
+/// 文字列へのコピーが発生しないため、大きなファイルでもピークメモリはファイルサイズ分増えるだけで済む。
+/// 圧縮ファイル、名前付きパイプ・デバイス等のマップできないファイルは展開・読み込みしてバッファに保持する。
+///
+/// マップしている間に他のプロセスがファイルを切り詰めると、失われた範囲を読んだ時点で
+/// SIGBUSが発生してプロセスが終了する（Rubyの例外にはならない）。読み込み中に書き換えられ得るファイルは
+/// IOとして渡すこと（`File.open(path) { |f| RbCsv.read(f) }`）。
+pub struct FileSource {
+    content: Content,
+}
+
+enum Content {
+    Mapped(Mmap),
+    Buffered(Vec<u8>),
+}
+
+impl FileSource {
+    /// ファイルを開いて内容を用意する（UTF-8として妥当かも検証する）
+    pub fn open(file_path: &str) -> Result<Self, CsvError> {
+        let file = open_csv_file(file_path)?;
+        let read_error = |e: std::io::Error| CsvError::io(format!("Failed to read file '{}': {}", file_path, e));
+
+        let metadata = file.metadata().map_err(read_error)?;
+        let mapped = if metadata.is_file() && metadata.len() > 0 {
+            // マップに失敗した場合（対応していないファイルシステム等）は通常の読み込みにフォールバックする
+            // マップ中のファイルの切り詰めはSIGBUSになる（型のドキュメントを参照）
+            unsafe { Mmap::map(&file) }.ok()
+        } else {
+            None
+        };
+
+        let content = match mapped {
+            Some(mmap) if Compression::detect(&mmap) == Compression::None => Content::Mapped(mmap),
+            Some(mmap) => Content::Buffered(read_all(&mmap[..]).map_err(read_error)?),
+            None => Content::Buffered(read_all(file).map_err(read_error)?),
+        };
+
+        str::from_utf8(content.as_bytes())
+            .map_err(|e| CsvError::encoding(format!("Failed to read file '{}': {}", file_path, e)))?;
+
+        Ok(Self { content })
+    }
+
+    /// メモリマップしたファイルから読み込んでいるか
+    #[cfg(test)]
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.content, Content::Mapped(_))
+    }
+
+    pub fn as_str(&self) -> &str {
+        // `open` でUTF-8として検証済み
+        unsafe { str::from_utf8_unchecked(self.content.as_bytes()) }
+    }
+}
+
+impl Content {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Content::Mapped(mmap) => mmap,
+            Content::Buffered(buffer) => buffer,
+        }
+    }
+}
+
+/// 入力を（圧縮されていれば展開して）すべて読み込む
+fn read_all<R: Read + Send>(input: R) -> std::io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    decompress(input)?.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::CompressedWriter;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn test_open_maps_plain_files() {
+        let path = "/tmp/rbcsv_source_plain.csv";
+        fs::write(path, "a,b\n1,2\n").unwrap();
+
+        let source = FileSource::open(path).unwrap();
+        assert!(source.is_mapped());
+        assert_eq!(source.as_str(), "a,b\n1,2\n");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_buffers_compressed_and_empty_files() {
+        let path = "/tmp/rbcsv_source_compressed.csv.gz";
+        let mut writer = CompressedWriter::new(Vec::new(), Compression::Gzip).unwrap();
+        writer.write_all(b"a,b\n1,2\n").unwrap();
+        fs::write(path, writer.finish().unwrap()).unwrap();
+
+        let source = FileSource::open(path).unwrap();
+        assert!(!source.is_mapped());
+        assert_eq!(source.as_str(), "a,b\n1,2\n");
+
+        fs::write(path, "").unwrap();
+        assert_eq!(FileSource::open(path).unwrap().as_str(), "");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_rejects_invalid_utf8() {
+        let path = "/tmp/rbcsv_source_invalid.csv";
+        fs::write(path, b"a,\xff\n").unwrap();
+
+        let err = FileSource::open(path).err().unwrap();
+        assert!(err.to_string().contains("Encoding Error"));
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,496 @@
+use magnus::{
+    gc::Marker,
+    scan_args::scan_args,
+    typed_data::Obj,
+    value::{Opaque, ReprValue},
+    DataTypeFunctions, Error as MagnusError, Integer, RArray, RHash, RString, Ruby, Symbol, TypedData,
+    Value as MagnusValue,
+};
+use crate::error::CsvError;
+use crate::filter::{RowFilter, RowSelector};
+use crate::gvl::{check_interrupt, without_gvl};
+use crate::header::{DuplicateHeaders, Headers};
+use crate::options::{read_options_from_ruby, TABLE_OPTIONS};
+use crate::parser::{typed_record, CsvParseOptions, INTERRUPT_CHECK_INTERVAL};
+use crate::rows::Rows;
+use crate::ruby_api::{csv_error_to_ruby, header_keys, input_from_ruby, read_rows, StringMode};
+use crate::value::CsvValue;
+
+/// 1列分の値
+///
+/// 列のすべての値が整数（または浮動小数点数）の場合は `i64`・`f64` の配列として保持し、
+/// アクセスされるまでRubyのオブジェクトを作らない。空のセルはNoneとして保持し、nilを返す。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Integer(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    /// 型変換しない（`typed: false`）列。フィールドを1つのバッファに続けて格納し、`ends` に各フィールドの終端を持つ
+    Text { buffer: String, ends: Vec<usize> },
+    /// 文字列や型の混在した列
+    Values(Vec<CsvValue>),
+}
+
+impl Column {
+    /// 型変換しない列を作る
+    pub fn text() -> Self {
+        Column::Text { buffer: String::new(), ends: Vec::new() }
+    }
+
+    /// 型変換しない列にフィールドを追加する
+    pub fn push_text(&mut self, field: &str) {
+        if let Column::Text { buffer, ends } = self {
+            buffer.push_str(field);
+            ends.push(buffer.len());
+        }
+    }
+
+    /// 型認識した値を追加する
+    ///
+    /// 数値の列は同じ型の値と空のセルを受け付け、それ以外の値が来たら `Values` に切り替える。
+    pub fn push(&mut self, value: CsvValue) {
+        match (&mut *self, value) {
+            (Column::Integer(values), CsvValue::Integer(i)) => values.push(Some(i)),
+            (Column::Float(values), CsvValue::Float(f)) => values.push(Some(f)),
+            (Column::Integer(values), CsvValue::String(s)) if s.is_empty() => values.push(None),
+            (Column::Float(values), CsvValue::String(s)) if s.is_empty() => values.push(None),
+            (Column::Values(values), value) => values.push(value),
+            (column, value) => {
+                let mut values = column.take_values();
+                values.push(value);
+                *column = Column::Values(values);
+            }
+        }
+    }
+
+    /// 列の値を `CsvValue` の配列として取り出す（空のセルは空文字列に戻す）
+    fn take_values(&mut self) -> Vec<CsvValue> {
+        let blank = || CsvValue::String(String::new());
+        match std::mem::replace(self, Column::Values(Vec::new())) {
+            Column::Integer(values) => values.into_iter().map(|v| v.map_or_else(blank, CsvValue::Integer)).collect(),
+            Column::Float(values) => values.into_iter().map(|v| v.map_or_else(blank, CsvValue::Float)).collect(),
+            Column::Text { buffer, ends } => {
+                let mut start = 0;
+                ends.into_iter()
+                    .map(|end| {
+                        let value = CsvValue::String(buffer[start..end].to_string());
+                        start = end;
+                        value
+                    })
+                    .collect()
+            }
+            Column::Values(values) => values,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Integer(values) => values.len(),
+            Column::Float(values) => values.len(),
+            Column::Text { ends, .. } => ends.len(),
+            Column::Values(values) => values.len(),
+        }
+    }
+}
+
+/// 型認識した値から列を組み立てる
+///
+/// 先頭から空のセルが続く間は型が決まらないため、その数だけ数えておく。
+/// 最後まで空のセルしかない列は空文字列の列にする。
+#[derive(Debug, Default)]
+struct ColumnBuilder {
+    blanks: usize,
+    column: Option<Column>,
+}
+
+impl ColumnBuilder {
+    fn push(&mut self, value: CsvValue) {
+        let column = match &mut self.column {
+            Some(column) => column,
+            None if value == CsvValue::String(String::new()) => {
+                self.blanks += 1;
+                return;
+            }
+            None => self.column.insert(match value {
+                CsvValue::Integer(_) => Column::Integer(vec![None; self.blanks]),
+                CsvValue::Float(_) => Column::Float(vec![None; self.blanks]),
+                _ => Column::Values(vec![CsvValue::String(String::new()); self.blanks]),
+            }),
+        };
+        column.push(value);
+    }
+
+    fn finish(self) -> Column {
+        self.column
+            .unwrap_or_else(|| Column::Values(vec![CsvValue::String(String::new()); self.blanks]))
+    }
+}
+
+/// 列ごとに値を保持する表（`RbCsv::Table`）
+///
+/// パース結果を列単位でRust側に保持し、行・列の単位でRubyの値に変換して返す。
+/// 数値の列はアクセスされるまでRubyのオブジェクトにしない。
+#[derive(TypedData)]
+#[magnus(class = "RbCsv::Table", free_immediately, mark)]
+pub struct Table {
+    /// ヘッダーとHashのキー（`headers: false` の場合はNone）
+    headers: Option<(Headers, Opaque<RArray>)>,
+    columns: Vec<Column>,
+    /// 行数（ヘッダー行を除く）
+    rows: usize,
+    strings: StringMode,
+    /// `converters:` のProc一覧（`CsvValue::Deferred` の変換に使う）
+    procs: Vec<Opaque<MagnusValue>>,
+}
+
+impl DataTypeFunctions for Table {
+    fn mark(&self, marker: &Marker) {
+        if let Some((_, keys)) = &self.headers {
+            marker.mark(*keys);
+        }
+        for proc in &self.procs {
+            marker.mark(*proc);
+        }
+    }
+}
+
+impl Table {
+    /// CSV文字列をパースして表を作成する
+    ///
+    /// # Arguments
+    /// * `ruby` - Ruby VMの参照
+    /// * `args` - CSV文字列（またはIO）と `headers:`（デフォルトtrue）・`typed:`（デフォルトtrue）等のキーワード引数
+    ///
+    /// # Returns
+    /// * `Result<Table, MagnusError>` - 表またはエラー
+    pub fn parse(ruby: &Ruby, args: &[MagnusValue]) -> Result<Self, MagnusError> {
+        Self::load(ruby, args, false)
+    }
+
+    /// CSVファイルを読み込んで表を作成する
+    ///
+    /// # Arguments
+    /// * `ruby` - Ruby VMの参照
+    /// * `args` - CSVファイルのパス（またはIO）と `headers:`（デフォルトtrue）・`typed:`（デフォルトtrue）等のキーワード引数
+    ///
+    /// # Returns
+    /// * `Result<Table, MagnusError>` - 表またはエラー
+    pub fn read(ruby: &Ruby, args: &[MagnusValue]) -> Result<Self, MagnusError> {
+        Self::load(ruby, args, true)
+    }
+
+    fn load(ruby: &Ruby, args: &[MagnusValue], from_file: bool) -> Result<Self, MagnusError> {
+        let args = scan_args::<(MagnusValue,), (), (), (), RHash, ()>(args)?;
+        let input = input_from_ruby(ruby, args.required.0)?;
+        let defaults = CsvParseOptions { headers: true, typed: true, ..CsvParseOptions::default() };
+        let options = read_options_from_ruby(ruby, args.keywords, TABLE_OPTIONS, defaults)?;
+        if options.parse.header_options.duplicates == DuplicateHeaders::Array {
+            return Err(MagnusError::new(
+                ruby.exception_arg_error(),
+                "duplicate_headers: :array is not supported by RbCsv::Table",
+            ));
+        }
+
+        // 型変換する場合は変換後の値で `where:` を評価するため、絞り込みは列を組み立てる際に行う
+        let mut parse = options.parse.clone();
+        let filter = if parse.typed { parse.filter.take() } else { None };
+        let rows = read_rows(ruby, input, from_file, &parse, options.threads)?;
+
+        let headers = match rows.iter().next() {
+            Some(record) if parse.headers => {
+                let raw_headers = record.iter().collect::<Vec<&str>>();
+                let headers = parse.header_options.process(&raw_headers).map_err(|e| csv_error_to_ruby(ruby, e))?;
+                let keys = header_keys(ruby, &headers)?;
+                Some((headers, Opaque::from(keys)))
+            }
+            _ => None,
+        };
+
+        let (mut columns, row_count) = without_gvl(ruby, || build_columns(&rows, &parse, filter))?;
+        // ヘッダー行しかない場合も列名ごとに（空の）列を持たせる
+        if let Some((headers, _)) = &headers {
+            columns.resize_with(headers.names.len(), || Column::Values(Vec::new()));
+        }
+        Ok(Self {
+            headers,
+            columns,
+            rows: row_count,
+            strings: options.strings,
+            procs: options.procs.into_iter().map(Opaque::from).collect(),
+        })
+    }
+
+    /// ヘッダー（列名の配列）を返す（`headers: false` の場合はnil）
+    pub fn headers(ruby: &Ruby, rb_self: &Self) -> Option<RArray> {
+        rb_self.headers.as_ref().map(|(_, keys)| ruby.get_inner(*keys).dup())
+    }
+
+    /// 行数（ヘッダー行を除く）
+    pub fn size(&self) -> usize {
+        self.rows
+    }
+
+    /// 整数を渡した場合は行、列名（String/Symbol）を渡した場合は列を返す（範囲外・存在しない列はnil）
+    pub fn aref(ruby: &Ruby, rb_self: &Self, key: MagnusValue) -> Result<Option<MagnusValue>, MagnusError> {
+        if let Some(index) = Integer::from_value(key) {
+            return Self::row(ruby, rb_self, index.to_i64()?);
+        }
+        match rb_self.column_index(ruby, key)? {
+            Some(column) => Ok(Some(rb_self.column_to_ruby(ruby, column)?.as_value())),
+            None => Ok(None),
+        }
+    }
+
+    /// 行を返す（`headers: true` の場合はHash、それ以外は配列。負の値は末尾から数える）
+    pub fn row(ruby: &Ruby, rb_self: &Self, index: i64) -> Result<Option<MagnusValue>, MagnusError> {
+        match resolve_index(index, rb_self.rows) {
+            Some(row) => rb_self.row_to_ruby(ruby, row).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 列を配列で返す（列名または列番号で指定する。存在しない場合はKeyError）
+    pub fn column(ruby: &Ruby, rb_self: &Self, key: MagnusValue) -> Result<RArray, MagnusError> {
+        let column = match Integer::from_value(key) {
+            Some(index) => resolve_index(index.to_i64()?, rb_self.columns.len()),
+            None => rb_self.column_index(ruby, key)?,
+        };
+        let Some(column) = column else {
+            return Err(MagnusError::new(
+                ruby.exception_key_error(),
+                format!("unknown column: {}", key.inspect()),
+            ));
+        };
+
+        rb_self.column_to_ruby(ruby, column)
+    }
+
+    /// 行を1行ずつブロックに渡す（ブロックなしの場合はEnumeratorを返す）
+    pub fn each_row(ruby: &Ruby, rb_self: Obj<Self>) -> Result<MagnusValue, MagnusError> {
+        if !ruby.block_given() {
+            return Ok(rb_self.enumeratorize("each_row", ()).as_value());
+        }
+
+        for row in 0..rb_self.rows {
+            let _: MagnusValue = ruby.yield_value(rb_self.row_to_ruby(ruby, row)?)?;
+        }
+
+        Ok(rb_self.as_value())
+    }
+
+    /// 配列の配列に変換する（`headers: true` の場合は1行目がヘッダー。`RbCsv.parse` と同じ形）
+    pub fn to_a(ruby: &Ruby, rb_self: &Self) -> Result<RArray, MagnusError> {
+        let array = ruby.ary_new_capa(rb_self.rows + 1);
+        if let Some((_, keys)) = &rb_self.headers {
+            array.push(ruby.get_inner(*keys).dup())?;
+        }
+        for row in 0..rb_self.rows {
+            let fields = ruby.ary_new_capa(rb_self.columns.len());
+            for column in &rb_self.columns {
+                fields.push(rb_self.value_to_ruby(ruby, column, row)?)?;
+            }
+            array.push(fields)?;
+        }
+
+        Ok(array)
+    }
+
+    /// 列名（`headers: false` の場合は列番号）をキー、列の配列を値とするHashに変換する
+    pub fn to_h(ruby: &Ruby, rb_self: &Self) -> Result<RHash, MagnusError> {
+        let hash = ruby.hash_new_capa(rb_self.columns.len());
+        for (index, _) in rb_self.columns.iter().enumerate() {
+            let key = match &rb_self.headers {
+                Some((_, keys)) => ruby.get_inner(*keys).entry::<MagnusValue>(index as isize)?,
+                None => ruby.integer_from_i64(index as i64).as_value(),
+            };
+            hash.aset(key, rb_self.column_to_ruby(ruby, index)?)?;
+        }
+
+        Ok(hash)
+    }
+
+    /// 列名（String/Symbol）から列番号を求める
+    fn column_index(&self, ruby: &Ruby, key: MagnusValue) -> Result<Option<usize>, MagnusError> {
+        let Some((headers, _)) = &self.headers else {
+            return Ok(None);
+        };
+        let name = if let Some(symbol) = Symbol::from_value(key) {
+            symbol.name()?.into_owned()
+        } else if let Some(string) = RString::from_value(key) {
+            string.to_string()?
+        } else {
+            return Err(MagnusError::new(
+                ruby.exception_type_error(),
+                format!("expected a column name or index, got {}", key.inspect()),
+            ));
+        };
+
+        Ok(headers.names.iter().position(|header| *header == name))
+    }
+
+    fn row_to_ruby(&self, ruby: &Ruby, row: usize) -> Result<MagnusValue, MagnusError> {
+        let Some((_, keys)) = &self.headers else {
+            let fields = ruby.ary_new_capa(self.columns.len());
+            for column in &self.columns {
+                fields.push(self.value_to_ruby(ruby, column, row)?)?;
+            }
+            return Ok(fields.as_value());
+        };
+
+        let keys = ruby.get_inner(*keys);
+        let hash = ruby.hash_new_capa(self.columns.len());
+        for (index, column) in self.columns.iter().enumerate() {
+            hash.aset(keys.entry::<MagnusValue>(index as isize)?, self.value_to_ruby(ruby, column, row)?)?;
+        }
+        Ok(hash.as_value())
+    }
+
+    fn column_to_ruby(&self, ruby: &Ruby, index: usize) -> Result<RArray, MagnusError> {
+        let column = &self.columns[index];
+        let array = ruby.ary_new_capa(column.len());
+        for row in 0..column.len() {
+            array.push(self.value_to_ruby(ruby, column, row)?)?;
+        }
+
+        Ok(array)
+    }
+
+    fn value_to_ruby(&self, ruby: &Ruby, column: &Column, row: usize) -> Result<MagnusValue, MagnusError> {
+        match column {
+            Column::Integer(values) => Ok(values[row].map_or_else(|| ruby.qnil().as_value(), |i| ruby.integer_from_i64(i).as_value())),
+            Column::Float(values) => Ok(values[row].map_or_else(|| ruby.qnil().as_value(), |f| ruby.float_from_f64(f).as_value())),
+            Column::Text { buffer, ends } => {
+                let start = if row == 0 { 0 } else { ends[row - 1] };
+                Ok(self.strings.str_new(ruby, &buffer[start..ends[row]]).as_value())
+            }
+            Column::Values(values) => match &values[row] {
+                CsvValue::Deferred { converter, raw } => {
+                    ruby.get_inner(self.procs[*converter]).funcall("call", (raw.as_str(),))
+                }
+                value => Ok(self.strings.value_to_ruby(ruby, value)),
+            },
+        }
+    }
+}
+
+/// 読み込んだ行から1行ずつ列に値を追加し、列と行数を返す（ヘッダー行は含めない）
+///
+/// `typed` の場合は各行を型認識して追加し、`filter`（`where:`）はコンバーターで変換した値で評価する。
+/// それ以外の場合は `rows` は絞り込み済みで、フィールドをそのまま追加する。
+fn build_columns(rows: &Rows, parse: &CsvParseOptions, filter: Option<RowFilter>) -> Result<(Vec<Column>, usize), CsvError> {
+    let width = rows.iter().next().map_or(0, |record| record.len());
+    let mut records = rows.iter().enumerate();
+    if parse.headers {
+        records.next();
+    }
+
+    if !parse.typed {
+        let mut columns = vec![Column::text(); width];
+        let mut count = 0;
+        for (line_num, record) in records {
+            if line_num.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+                check_interrupt()?;
+            }
+            for (column, field) in columns.iter_mut().zip(record.iter()) {
+                column.push_text(field);
+            }
+            count += 1;
+        }
+        return Ok((columns, count));
+    }
+
+    let trim_fields = parse.trim.trims_fields();
+    let mut selector = RowSelector::new(filter);
+    let header = rows.iter().next().filter(|_| parse.reads_header_row());
+    let converters = parse.converters.resolve(header, &parse.header_options)?;
+    if let Some(header) = header {
+        selector.select(0, header)?;
+    }
+
+    let mut builders: Vec<ColumnBuilder> = (0..width).map(|_| ColumnBuilder::default()).collect();
+    let mut count = 0;
+    for (line_num, record) in records {
+        if line_num.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+            check_interrupt()?;
+        }
+        let values = if line_num == 0 && header.is_some() {
+            // `headers: false` でもヘッダー名で列を指定した場合、先頭行は変換せずに残す
+            record.iter().map(|field| CsvValue::String(field.to_string())).collect()
+        } else if selector.select_converted(line_num, record, &converters, trim_fields)? {
+            typed_record(record, &converters, trim_fields)
+        } else {
+            continue;
+        };
+        for (builder, value) in builders.iter_mut().zip(values) {
+            builder.push(value);
+        }
+        count += 1;
+    }
+
+    Ok((builders.into_iter().map(ColumnBuilder::finish).collect(), count))
+}
+
+/// 負の値（末尾から数える）を含むインデックスを範囲内の位置に変換する
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_csv_rows;
+
+    fn column(values: Vec<CsvValue>) -> Column {
+        let mut builder = ColumnBuilder::default();
+        for value in values {
+            builder.push(value);
+        }
+        builder.finish()
+    }
+
+    fn blank() -> CsvValue {
+        CsvValue::String(String::new())
+    }
+
+    #[test]
+    fn test_column_builder() {
+        assert_eq!(column(vec![CsvValue::Integer(1), CsvValue::Integer(-2)]), Column::Integer(vec![Some(1), Some(-2)]));
+        assert_eq!(column(vec![CsvValue::Float(1.5), CsvValue::Float(2.0)]), Column::Float(vec![Some(1.5), Some(2.0)]));
+
+        // 空のセルは数値の列のままNoneとして保持する
+        let sparse = column(vec![blank(), CsvValue::Integer(1), blank()]);
+        assert_eq!(sparse, Column::Integer(vec![None, Some(1), None]));
+        assert_eq!(column(vec![blank(), blank()]), Column::Values(vec![blank(), blank()]));
+
+        // 整数と浮動小数点数・文字列が混在する列は値をそのまま保持する
+        let mixed = vec![CsvValue::Integer(1), blank(), CsvValue::Float(2.5)];
+        assert_eq!(column(mixed.clone()), Column::Values(mixed));
+    }
+
+    #[test]
+    fn test_build_columns() {
+        let rows = parse_csv_rows("name,price\na,1\nb,\nc,3\n", &CsvParseOptions::default()).unwrap();
+        let typed = CsvParseOptions { headers: true, typed: true, ..Default::default() };
+        let (columns, count) = build_columns(&rows, &typed, None).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(columns[1], Column::Integer(vec![Some(1), None, Some(3)]));
+
+        let mut filter = RowFilter::compile("price > 1").unwrap();
+        filter.header_row = true;
+        let (columns, count) = build_columns(&rows, &typed, Some(filter)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(columns[0], column(vec![CsvValue::String("c".to_string())]));
+
+        let (columns, count) = build_columns(&rows, &CsvParseOptions::default(), None).unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(columns[1], Column::Text { buffer: "price13".to_string(), ends: vec![5, 6, 6, 7] });
+    }
+
+    #[test]
+    fn test_resolve_index() {
+        assert_eq!(resolve_index(0, 3), Some(0));
+        assert_eq!(resolve_index(-1, 3), Some(2));
+        assert_eq!(resolve_index(3, 3), None);
+        assert_eq!(resolve_index(-4, 3), None);
+    }
+}
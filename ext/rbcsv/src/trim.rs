@@ -0,0 +1,152 @@
+/// 前後の空白を除去する行
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrimMode {
+    #[default]
+    None,
+    /// ヘッダー行（先頭行）のみ
+    Headers,
+    /// ヘッダー行以外のすべての行
+    Fields,
+    All,
+}
+
+impl TrimMode {
+    /// Rubyのオプション名（`:all`・`:headers`・`:fields`・`:none`）から変換する
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(TrimMode::None),
+            "headers" => Some(TrimMode::Headers),
+            "fields" => Some(TrimMode::Fields),
+            "all" => Some(TrimMode::All),
+            _ => None,
+        }
+    }
+}
+
+/// 前後の空白の除去の設定
+///
+/// csvクレートの `Trim` と同じUnicodeの空白（全角スペース U+3000、ノーブレークスペースを含む）に加えて、
+/// `chars` の文字（ゼロ幅スペース U+200B、BOM U+FEFF 等）も除去する。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrimConfig {
+    pub mode: TrimMode,
+    /// 空白以外に除去する文字
+    pub chars: Vec<char>,
+}
+
+impl TrimConfig {
+    pub fn new(mode: TrimMode) -> Self {
+        Self { mode, chars: Vec::new() }
+    }
+
+    /// すべての行を除去の対象にする（`parse!` 等の既定値）
+    pub fn all() -> Self {
+        Self::new(TrimMode::All)
+    }
+
+    /// 先頭行をヘッダーとして扱わない場合の設定にする
+    /// `:headers` は何も除去せず、`:fields` は先頭行を含むすべての行を対象にする
+    pub fn without_header_row(self) -> Self {
+        let mode = match self.mode {
+            TrimMode::Headers => TrimMode::None,
+            TrimMode::Fields => TrimMode::All,
+            mode => mode,
+        };
+        Self { mode, ..self }
+    }
+
+    pub fn trims_headers(&self) -> bool {
+        matches!(self.mode, TrimMode::Headers | TrimMode::All)
+    }
+
+    pub fn trims_fields(&self) -> bool {
+        matches!(self.mode, TrimMode::Fields | TrimMode::All)
+    }
+
+    /// フィールドの前後の空白を除去する
+    pub fn trim<'a>(&self, field: &'a str) -> &'a str {
+        field.trim_matches(|c: char| c.is_whitespace() || self.chars.contains(&c))
+    }
+
+    /// レコードの各フィールドの前後の空白を除去する（`header` は先頭行か）
+    /// 除去する文字がない場合はレコードを作り直さない
+    pub fn apply(&self, record: &mut csv::StringRecord, header: bool) {
+        let enabled = if header { self.trims_headers() } else { self.trims_fields() };
+        if !enabled || record.iter().all(|field| self.trim(field).len() == field.len()) {
+            return;
+        }
+        // 空白のみを除去する場合はcsvクレートの `StringRecord::trim` に任せる
+        if self.chars.is_empty() {
+            record.trim();
+            return;
+        }
+
+        let position = record.position().cloned();
+        let mut trimmed: csv::StringRecord = record.iter().map(|field| self.trim(field)).collect();
+        trimmed.set_position(position);
+        *record = trimmed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fields: &[&str]) -> csv::StringRecord {
+        csv::StringRecord::from(fields.to_vec())
+    }
+
+    #[test]
+    fn test_trim_whitespace_and_extra_chars() {
+        let config = TrimConfig { mode: TrimMode::All, chars: vec!['\u{200b}', '\u{feff}'] };
+        assert_eq!(config.trim(" \t東京\u{200b}\u{3000}"), "東京");
+        assert_eq!(config.trim("\u{feff}Alice \u{a0}"), "Alice");
+        assert_eq!(config.trim("a\u{200b}b"), "a\u{200b}b");
+
+        // 既定ではcsvクレートと同じくUnicodeの空白のみ
+        assert_eq!(TrimConfig::all().trim("\u{a0}東京\u{3000}"), "東京");
+        assert_eq!(TrimConfig::all().trim(" 東京\u{200b}"), "東京\u{200b}");
+    }
+
+    #[test]
+    fn test_apply_keeps_position() {
+        let mut position = csv::Position::new();
+        position.set_line(3);
+        let apply = |config: TrimConfig| {
+            let mut trimmed = record(&[" a\u{3000}", "b\u{200b}"]);
+            trimmed.set_position(Some(position.clone()));
+            config.apply(&mut trimmed, false);
+            assert_eq!(trimmed.position().map(|p| p.line()), Some(3));
+            trimmed
+        };
+
+        assert_eq!(apply(TrimConfig::all()), record(&["a", "b\u{200b}"]));
+        assert_eq!(apply(TrimConfig { mode: TrimMode::All, chars: vec!['\u{200b}'] }), record(&["a", "b"]));
+    }
+
+    #[test]
+    fn test_apply_by_mode() {
+        let headers = TrimConfig::new(TrimMode::Headers);
+        let mut first = record(&[" name ", " age "]);
+        let mut body = record(&[" Alice ", " 25 "]);
+        headers.apply(&mut first, true);
+        headers.apply(&mut body, false);
+        assert_eq!(first, record(&["name", "age"]));
+        assert_eq!(body, record(&[" Alice ", " 25 "]));
+
+        let fields = TrimConfig::new(TrimMode::Fields);
+        let mut first = record(&[" name "]);
+        let mut body = record(&[" Alice "]);
+        fields.apply(&mut first, true);
+        fields.apply(&mut body, false);
+        assert_eq!(first, record(&[" name "]));
+        assert_eq!(body, record(&["Alice"]));
+    }
+
+    #[test]
+    fn test_without_header_row() {
+        assert_eq!(TrimConfig::new(TrimMode::Headers).without_header_row().mode, TrimMode::None);
+        assert_eq!(TrimConfig::new(TrimMode::Fields).without_header_row().mode, TrimMode::All);
+        assert_eq!(TrimConfig::all().without_header_row().mode, TrimMode::All);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::converter::ColumnKey;
+use crate::error::CsvError;
+use crate::header::HeaderOptions;
+use crate::value::CsvValue;
+use regex::Regex;
+use std::collections::HashSet;
+
+/// セルに対する検証ルール
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// 空でないこと
+    NotEmpty,
+    /// 正規表現にマッチすること
+    Pattern(Regex),
+    /// 数値が指定値以上であること
+    Min(f64),
+    /// 数値が指定値以下であること
+    Max(f64),
+    /// 候補のいずれかに一致すること
+    OneOf(Vec<String>),
+    /// 列内で一意であること
+    Unique,
+    /// 文字数が指定値以上であること
+    MinLength(usize),
+    /// 文字数が指定値以下であること
+    MaxLength(usize),
+}
+
+impl Rule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::NotEmpty => "not_empty",
+            Rule::Pattern(_) => "pattern",
+            Rule::Min(_) => "min",
+            Rule::Max(_) => "max",
+            Rule::OneOf(_) => "in",
+            Rule::Unique => "unique",
+            Rule::MinLength(_) => "min_length",
+            Rule::MaxLength(_) => "max_length",
+        }
+    }
+}
+
+/// ルール違反
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// 行番号（1始まり、ヘッダー行を含む）
+    pub row: usize,
+    /// 列番号（0始まり）
+    pub column: usize,
+    /// 列のヘッダー名（ヘッダーがある場合）
+    pub header: Option<String>,
+    /// 違反したルール名
+    pub rule: &'static str,
+    /// セルの値
+    pub value: String,
+    pub message: String,
+}
+
+/// 検証結果
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    /// ヘッダー行（ヘッダーがある場合）
+    pub headers: Option<Vec<CsvValue>>,
+    /// すべてのルールを満たした行
+    pub clean_rows: Vec<Vec<CsvValue>>,
+    pub violations: Vec<Violation>,
+}
+
+/// 列ごとのルールをまとめた検証器
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    columns: Vec<(ColumnKey, Vec<Rule>)>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_rules(&mut self, key: ColumnKey, rules: Vec<Rule>) {
+        self.columns.push((key, rules));
+    }
+
+    /// 全行を検証し、違反をすべて収集する（最初の違反で止めない）
+    ///
+    /// # Arguments
+    /// * `rows` - 型認識済みの行
+    /// * `has_headers` - 1行目をヘッダーとして扱うか（ヘッダー名でのルール指定に必要）
+    pub fn validate(&self, rows: Vec<Vec<CsvValue>>, has_headers: bool) -> Result<ValidationReport, CsvError> {
+        let mut rows = rows.into_iter();
+        let headers = if has_headers { rows.next() } else { None };
+        let header_names: Option<Vec<String>> = headers
+            .as_ref()
+            .map(|headers| headers.iter().map(|header| header.to_string()).collect());
+
+        let mut resolved = Vec::with_capacity(self.columns.len());
+        for (key, rules) in &self.columns {
+            let names = header_names.as_ref().map(|names| names.iter().map(String::as_str));
+            resolved.push((key.resolve(names, &HeaderOptions::default(), "Validation")?, rules));
+        }
+
+        let mut seen: Vec<HashSet<String>> = vec![HashSet::new(); resolved.len()];
+        let mut clean_rows = Vec::new();
+        let mut violations = Vec::new();
+
+        // 重複したヘッダー名は例外にせず、ヘッダー行（1行目）の違反として報告する
+        if let Some(names) = &header_names {
+            for (column, name) in names.iter().enumerate() {
+                if names[..column].contains(name) {
+                    violations.push(Violation {
+                        row: 1,
+                        column,
+                        header: Some(name.clone()),
+                        rule: "unique_header",
+                        value: name.clone(),
+                        message: "must be unique within the header row".to_string(),
+                    });
+                }
+            }
+        }
+        let first_row = if has_headers { 2 } else { 1 };
+
+        for (offset, row) in rows.enumerate() {
+            let row_num = first_row + offset;
+            let violations_before = violations.len();
+
+            for ((column, rules), seen) in resolved.iter().zip(seen.iter_mut()) {
+                let value = row.get(*column).unwrap_or(&CsvValue::Nil);
+                let text = value.to_string();
+
+                for rule in rules.iter() {
+                    if let Some(message) = check(rule, value, &text, seen) {
+                        violations.push(Violation {
+                            row: row_num,
+                            column: *column,
+                            header: header_names.as_ref().and_then(|names| names.get(*column).cloned()),
+                            rule: rule.name(),
+                            value: text.clone(),
+                            message,
+                        });
+                    }
+                }
+            }
+
+            if violations.len() == violations_before {
+                clean_rows.push(row);
+            }
+        }
+
+        Ok(ValidationReport {
+            headers,
+            clean_rows,
+            violations,
+        })
+    }
+}
+
+/// 1つのセルを検証し、違反していればメッセージを返す
+/// 空のセルは `not_empty` 以外のルールでは検証しない（値を必須にする場合は `not_empty` と組み合わせる）
+fn check(rule: &Rule, value: &CsvValue, text: &str, seen: &mut HashSet<String>) -> Option<String> {
+    if text.trim().is_empty() && !matches!(rule, Rule::NotEmpty) {
+        return None;
+    }
+    match rule {
+        Rule::NotEmpty => text.trim().is_empty().then(|| "must not be empty".to_string()),
+        Rule::Pattern(re) => (!re.is_match(text)).then(|| format!("must match /{}/", re.as_str())),
+        Rule::Min(min) => match numeric(value) {
+            Some(n) if n >= *min => None,
+            Some(_) => Some(format!("must be greater than or equal to {}", min)),
+            None => Some("must be a number".to_string()),
+        },
+        Rule::Max(max) => match numeric(value) {
+            Some(n) if n <= *max => None,
+            Some(_) => Some(format!("must be less than or equal to {}", max)),
+            None => Some("must be a number".to_string()),
+        },
+        Rule::OneOf(candidates) => (!candidates.iter().any(|candidate| candidate == text))
+            .then(|| format!("must be one of: {}", candidates.join(", "))),
+        Rule::Unique => (!seen.insert(text.to_string())).then(|| "must be unique within the column".to_string()),
+        Rule::MinLength(min) => {
+            (text.chars().count() < *min).then(|| format!("must be at least {} characters", min))
+        }
+        Rule::MaxLength(max) => {
+            (text.chars().count() > *max).then(|| format!("must be at most {} characters", max))
+        }
+    }
+}
+
+fn numeric(value: &CsvValue) -> Option<f64> {
+    match value {
+        CsvValue::Integer(i) => Some(*i as f64),
+        CsvValue::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_csv_typed, CsvParseOptions};
+
+    fn rows(input: &str) -> Vec<Vec<CsvValue>> {
+        parse_csv_typed(input, &CsvParseOptions { headers: true, typed: true, ..Default::default() }).unwrap()
+    }
+
+    #[test]
+    fn test_collects_all_violations() {
+        let mut validator = Validator::new();
+        validator.add_rules(ColumnKey::Name("id".to_string()), vec![Rule::Unique]);
+        validator.add_rules(ColumnKey::Name("age".to_string()), vec![Rule::Min(0.0), Rule::Max(150.0)]);
+        validator.add_rules(
+            ColumnKey::Name("status".to_string()),
+            vec![Rule::NotEmpty, Rule::OneOf(vec!["active".to_string(), "inactive".to_string()])],
+        );
+
+        let report = validator
+            .validate(rows("id,age,status\n1,20,active\n1,-5,\n2,abc,deleted\n3,40,inactive"), true)
+            .unwrap();
+
+        assert_eq!(report.clean_rows.len(), 2);
+        let found: Vec<(usize, &str)> = report.violations.iter().map(|v| (v.row, v.rule)).collect();
+        assert_eq!(
+            found,
+            vec![(3, "unique"), (3, "min"), (3, "not_empty"), (4, "min"), (4, "max"), (4, "in")]
+        );
+        assert_eq!(report.violations[0].header.as_deref(), Some("id"));
+    }
+
+    #[test]
+    fn test_pattern_and_length_rules() {
+        let mut validator = Validator::new();
+        validator.add_rules(
+            ColumnKey::Index(0),
+            vec![Rule::Pattern(Regex::new("@").unwrap()), Rule::MinLength(5), Rule::MaxLength(10)],
+        );
+
+        let report = validator.validate(rows("email\na@b.jp\nabc\nlong_address@example.com"), true).unwrap();
+        assert_eq!(report.clean_rows.len(), 1);
+        assert_eq!(report.violations.len(), 3);
+        assert_eq!(report.violations[0].column, 0);
+    }
+
+    #[test]
+    fn test_duplicate_headers_and_empty_unique_cells() {
+        let mut validator = Validator::new();
+        validator.add_rules(ColumnKey::Index(1), vec![Rule::Unique]);
+
+        let report = validator.validate(rows("id,code,id\n1,,a\n2,,b\n3,x,c\n4,x,d"), true).unwrap();
+        let found: Vec<(usize, usize, &str)> = report.violations.iter().map(|v| (v.row, v.column, v.rule)).collect();
+        assert_eq!(found, vec![(1, 2, "unique_header"), (5, 1, "unique")]);
+        assert_eq!(report.clean_rows.len(), 3);
+    }
+
+    #[test]
+    fn test_empty_cells_only_checked_by_not_empty() {
+        let mut validator = Validator::new();
+        validator.add_rules(
+            ColumnKey::Name("price".to_string()),
+            vec![Rule::Min(0.0), Rule::Max(100.0), Rule::Pattern(Regex::new(r"^\d").unwrap()), Rule::MinLength(1)],
+        );
+        validator.add_rules(ColumnKey::Name("status".to_string()), vec![Rule::NotEmpty, Rule::OneOf(vec!["ok".to_string()])]);
+
+        let report = validator.validate(rows("price,status\n10,ok\n,ok\n50,\n"), true).unwrap();
+        let found: Vec<(usize, &str)> = report.violations.iter().map(|v| (v.row, v.rule)).collect();
+        assert_eq!(found, vec![(4, "not_empty")]);
+        assert_eq!(report.clean_rows.len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let mut validator = Validator::new();
+        validator.add_rules(ColumnKey::Name("missing".to_string()), vec![Rule::NotEmpty]);
+        assert!(validator.validate(rows("a\n1"), true).is_err());
+    }
+}
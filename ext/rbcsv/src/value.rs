@@ -1,10 +1,31 @@
-use magnus::{Ruby, Value as MagnusValue, value::ReprValue};
+use std::fmt;
+
+use magnus::{Error as MagnusError, IntoValue, Float, Integer, RString, Ruby, Symbol, Value as MagnusValue, value::ReprValue};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CsvValue {
     Integer(i64),
     Float(f64),
     String(String),
+    Boolean(bool),
+    Symbol(String),
+    Nil,
+    /// Ruby側のコンバーター（Proc）で変換する生の値
+    /// `converter` はコンバーター一覧のインデックス
+    Deferred { converter: usize, raw: String },
+}
+
+impl fmt::Display for CsvValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvValue::Integer(i) => write!(f, "{}", i),
+            CsvValue::Float(x) => write!(f, "{}", x),
+            CsvValue::String(s) | CsvValue::Symbol(s) => write!(f, "{}", s),
+            CsvValue::Boolean(b) => write!(f, "{}", b),
+            CsvValue::Nil => Ok(()),
+            CsvValue::Deferred { raw, .. } => write!(f, "{}", raw),
+        }
+    }
 }
 
 impl CsvValue {
@@ -37,7 +58,42 @@ impl CsvValue {
             CsvValue::Integer(i) => ruby.integer_from_i64(*i).as_value(),
             CsvValue::Float(f) => ruby.float_from_f64(*f).as_value(),
             CsvValue::String(s) => ruby.str_new(s).as_value(),
+            CsvValue::Boolean(b) => b.into_value_with(ruby),
+            CsvValue::Symbol(s) => ruby.to_symbol(s).as_value(),
+            CsvValue::Nil => ruby.qnil().as_value(),
+            // コンバーターが適用されなかった場合は生の文字列として返す
+            CsvValue::Deferred { raw, .. } => ruby.str_new(raw).as_value(),
+        }
+    }
+
+    /// Rubyの値からCsvValueへの変換（コンバーターの対応表などで使用）
+    pub fn from_ruby(ruby: &Ruby, value: MagnusValue) -> Result<Self, MagnusError> {
+        if value.is_nil() {
+            return Ok(CsvValue::Nil);
+        }
+        if value.is_kind_of(ruby.class_true_class()) {
+            return Ok(CsvValue::Boolean(true));
+        }
+        if value.is_kind_of(ruby.class_false_class()) {
+            return Ok(CsvValue::Boolean(false));
+        }
+        if let Some(i) = Integer::from_value(value) {
+            return Ok(CsvValue::Integer(i.to_i64()?));
         }
+        if let Some(f) = Float::from_value(value) {
+            return Ok(CsvValue::Float(f.to_f64()));
+        }
+        if let Some(s) = Symbol::from_value(value) {
+            return Ok(CsvValue::Symbol(s.name()?.into_owned()));
+        }
+        if let Some(s) = RString::from_value(value) {
+            return Ok(CsvValue::String(s.to_string()?));
+        }
+
+        Err(MagnusError::new(
+            ruby.exception_type_error(),
+            format!("unsupported value for CSV conversion: {}", value.inspect()),
+        ))
     }
 }
 
@@ -0,0 +1,305 @@
+use magnus::{
+    gc::Marker,
+    scan_args::{get_kwargs, scan_args},
+    typed_data::Obj,
+    value::{Opaque, ReprValue},
+    DataTypeFunctions, Error as MagnusError, RArray, RHash, RString, Ruby, Symbol, TypedData, Value as MagnusValue,
+};
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::{self, Write};
+use crate::compression::{CompressedWriter, Compression};
+use crate::error::CsvError;
+use crate::options::{row_sep_from_ruby, single_byte};
+use crate::parser::create_csv_file;
+use crate::ruby_api::{compression_from_ruby, csv_error_to_ruby};
+use crate::ruby_io::write_bytes;
+
+/// RubyのIOへ書き出す前にためておくバイト数
+const IO_CHUNK_SIZE: usize = 64 * 1024;
+
+/// 書き込み先
+enum Sink {
+    /// ファイルに直接書き込む
+    File(csv::Writer<CompressedWriter<fs::File>>),
+    /// バッファにためて、一定量ごとにRubyのIOの `write` に渡す
+    Buffer(csv::Writer<CompressedWriter<ChunkBuffer>>),
+}
+
+/// RubyのIOに渡す前のバイト列
+/// csv::Writerが所有したまま中身を取り出せるよう、内部可変性を持たせる
+///
+/// `RubyWriter` はcsv::Writerへの書き込み中にIOを呼び出すため、sinkを借用したままRubyに制御が戻る。
+/// ここではレコードをためるだけにし、借用を解いてから `write_bytes` でIOに渡す。
+#[derive(Default)]
+struct ChunkBuffer(RefCell<Vec<u8>>);
+
+impl ChunkBuffer {
+    fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    fn take(&self) -> Vec<u8> {
+        self.0.take()
+    }
+}
+
+impl Write for ChunkBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.get_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 1行ずつCSVを書き込むライター（`RbCsv::Writer`）
+///
+/// ファイルパスまたは `write` に応答するIOに書き込む。行は順次書き出されるため、
+/// 何百万行の出力でもメモリ使用量は一定になる。
+#[derive(TypedData)]
+#[magnus(class = "RbCsv::Writer", free_immediately, mark)]
+pub struct Writer {
+    /// 書き込み先のIO（ファイルパス指定時はNone）
+    io: Option<Opaque<MagnusValue>>,
+    /// closeした後はNone
+    sink: RefCell<Option<Sink>>,
+    /// 書き込んだ行数
+    lineno: Cell<usize>,
+}
+
+impl DataTypeFunctions for Writer {
+    fn mark(&self, marker: &Marker) {
+        if let Some(io) = self.io {
+            marker.mark(io);
+        }
+    }
+}
+
+impl Writer {
+    /// ライターを作成する
+    ///
+    /// # Arguments
+    /// * `ruby` - Ruby VMの参照
+    /// * `args` - 書き込み先のパス（またはIO）と `col_sep:`・`quote_char:`・`row_sep:`・`headers:`・`compress:` のキーワード引数
+    ///
+    /// # Returns
+    /// * `Result<Writer, MagnusError>` - ライターまたはエラー
+    pub fn new(ruby: &Ruby, args: &[MagnusValue]) -> Result<Self, MagnusError> {
+        let args = scan_args::<(MagnusValue,), (), (), (), RHash, ()>(args)?;
+        let (target,) = args.required;
+        let kwargs = get_kwargs::<
+            _,
+            (),
+            (Option<String>, Option<String>, Option<MagnusValue>, Option<RArray>, Option<Symbol>),
+            (),
+        >(args.keywords, &[], &["col_sep", "quote_char", "row_sep", "headers", "compress"])?;
+        let (col_sep, quote_char, row_sep, headers, compress) = kwargs.optional;
+        let compress = compress.map(|name| compression_from_ruby(ruby, name)).transpose()?;
+
+        let mut builder = csv::WriterBuilder::new();
+        builder.has_headers(false);
+        if let Some(col_sep) = col_sep {
+            builder.delimiter(single_byte(ruby, "col_sep", &col_sep)?);
+        }
+        if let Some(quote_char) = quote_char {
+            builder.quote(single_byte(ruby, "quote_char", &quote_char)?);
+        }
+        // `:auto` は既定（`\n`）のまま
+        if let Some(terminator) = row_sep.map(|row_sep| row_sep_from_ruby(ruby, row_sep)).transpose()?.flatten() {
+            builder.terminator(terminator);
+        }
+
+        // パス指定時は拡張子（.gz / .zst）から圧縮形式を判定する
+        let (io, sink) = match write_target(ruby, target)? {
+            WriteTarget::Path(path) => {
+                let file = create_csv_file(&path).map_err(|e| csv_error_to_ruby(ruby, e))?;
+                let compression = compress.unwrap_or_else(|| Compression::from_path(&path));
+                let output = CompressedWriter::new(file, compression).map_err(|e| io_error(ruby, e))?;
+                (None, Sink::File(builder.from_writer(output)))
+            }
+            WriteTarget::Io(io) => {
+                let output = CompressedWriter::new(ChunkBuffer::default(), compress.unwrap_or(Compression::None))
+                    .map_err(|e| io_error(ruby, e))?;
+                (Some(Opaque::from(io)), Sink::Buffer(builder.from_writer(output)))
+            }
+        };
+
+        let writer = Self {
+            io,
+            sink: RefCell::new(Some(sink)),
+            lineno: Cell::new(0),
+        };
+        if let Some(headers) = headers {
+            writer.write_row(ruby, headers)?;
+        }
+
+        Ok(writer)
+    }
+
+    /// 1行書き込む（`writer << row`）
+    pub fn push(ruby: &Ruby, rb_self: Obj<Self>, row: RArray) -> Result<Obj<Self>, MagnusError> {
+        rb_self.write_row(ruby, row)?;
+        Ok(rb_self)
+    }
+
+    /// バッファの内容を書き込み先に送る
+    pub fn flush(ruby: &Ruby, rb_self: Obj<Self>) -> Result<Obj<Self>, MagnusError> {
+        rb_self.flush_sink(ruby, true)?;
+        Ok(rb_self)
+    }
+
+    /// フラッシュしてライターを閉じる
+    /// 圧縮している場合はここでストリームを終端する。書き込み先がIOの場合、IO自体は閉じない
+    pub fn close(ruby: &Ruby, rb_self: Obj<Self>) -> Result<(), MagnusError> {
+        let Some(sink) = rb_self.sink.borrow_mut().take() else {
+            return Ok(());
+        };
+
+        let rest = match sink {
+            Sink::File(writer) => {
+                writer
+                    .into_inner()
+                    .map_err(|e| e.into_error())
+                    .and_then(|output| output.finish())
+                    .map_err(|e| io_error(ruby, e))?;
+                return Ok(());
+            }
+            Sink::Buffer(writer) => writer
+                .into_inner()
+                .map_err(|e| e.into_error())
+                .and_then(|output| output.finish())
+                .map_err(|e| io_error(ruby, e))?
+                .take(),
+        };
+
+        rb_self.write_to_io(ruby, rest, true)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sink.borrow().is_none()
+    }
+
+    pub fn lineno(&self) -> usize {
+        self.lineno.get()
+    }
+
+    /// 行をフィールドの文字列に変換して書き込む
+    /// nilは空文字列、String以外の値は `to_s` で変換する
+    fn write_row(&self, ruby: &Ruby, row: RArray) -> Result<(), MagnusError> {
+        let mut fields = Vec::with_capacity(row.len());
+        for value in row {
+            let field = if value.is_nil() {
+                String::new()
+            } else if let Some(s) = RString::from_value(value) {
+                s.to_string()?
+            } else {
+                value.funcall::<_, _, RString>("to_s", ())?.to_string()?
+            };
+            fields.push(field);
+        }
+
+        let line_num = self.lineno.get() + 1;
+        let buffered = {
+            let mut sink = self.sink.borrow_mut();
+            let result = match sink.as_mut() {
+                Some(Sink::File(writer)) => writer.write_record(&fields).map(|_| 0),
+                Some(Sink::Buffer(writer)) => writer.write_record(&fields).map(|_| writer.get_ref().get_ref().len()),
+                None => return Err(MagnusError::new(ruby.exception_io_error(), "closed writer")),
+            };
+            result.map_err(|e| csv_error_to_ruby(ruby, write_error(line_num, e)))?
+        };
+        self.lineno.set(line_num);
+
+        if buffered >= IO_CHUNK_SIZE {
+            self.flush_sink(ruby, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// 書き込み先にデータを送る
+    /// バッファはレコードの区切りでまとめて送る。`flush_io` が真の場合はIOの `flush` も呼び出す
+    fn flush_sink(&self, ruby: &Ruby, flush_io: bool) -> Result<(), MagnusError> {
+        let chunk = {
+            let mut sink = self.sink.borrow_mut();
+            match sink.as_mut() {
+                Some(Sink::File(writer)) => {
+                    writer.flush().map_err(|e| io_error(ruby, e))?;
+                    return Ok(());
+                }
+                Some(Sink::Buffer(writer)) => {
+                    writer.flush().map_err(|e| io_error(ruby, e))?;
+                    writer.get_ref().get_ref().take()
+                }
+                None => return Err(MagnusError::new(ruby.exception_io_error(), "closed writer")),
+            }
+        };
+
+        self.write_to_io(ruby, chunk, flush_io)
+    }
+
+    /// バイト列をRubyのIOの `write` に渡す
+    /// RubyのIOを呼び出す間はsinkを借用しない（IO側から再入されても安全なように）
+    fn write_to_io(&self, ruby: &Ruby, chunk: Vec<u8>, flush_io: bool) -> Result<(), MagnusError> {
+        let Some(io) = self.io.map(|io| ruby.get_inner(io)) else {
+            return Ok(());
+        };
+        if !chunk.is_empty() {
+            // 非圧縮の場合、フィールドはすべてStringから書き込んでいるためレコード区切りのバッファは有効なUTF-8になる
+            write_bytes(ruby, io, chunk)?;
+        }
+        if flush_io && io.respond_to("flush", false)? {
+            let _: MagnusValue = io.funcall("flush", ())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 書き込み先（ファイルパスまたはRubyのIOオブジェクト）
+pub enum WriteTarget {
+    Path(String),
+    Io(MagnusValue),
+}
+
+/// 書き込み先を判定する
+/// IOのインスタンスはIO、Pathnameのように `to_path` に応答するオブジェクトはパス、
+/// それ以外で `write` に応答するオブジェクト（StringIO等）はIOとして扱う
+pub fn write_target(ruby: &Ruby, target: MagnusValue) -> Result<WriteTarget, MagnusError> {
+    if let Some(path) = RString::from_value(target) {
+        return Ok(WriteTarget::Path(path.to_string()?));
+    }
+    if target.is_kind_of(ruby.class_io()) {
+        return Ok(WriteTarget::Io(target));
+    }
+    if target.respond_to("to_path", false)? {
+        return Ok(WriteTarget::Path(target.funcall("to_path", ())?));
+    }
+    if target.respond_to("write", false)? {
+        return Ok(WriteTarget::Io(target));
+    }
+
+    Err(MagnusError::new(
+        ruby.exception_type_error(),
+        format!("expected a file path or an IO responding to write: {}", target.inspect()),
+    ))
+}
+
+fn io_error(ruby: &Ruby, e: io::Error) -> MagnusError {
+    csv_error_to_ruby(ruby, CsvError::io(format!("Failed to write data: {}", e)))
+}
+
+/// 書き込み時のcsvクレートのエラーを行番号付きのCsvErrorに変換する
+fn write_error(line_num: usize, e: csv::Error) -> CsvError {
+    if let csv::ErrorKind::UnequalLengths { expected_len, len, .. } = e.kind() {
+        return CsvError::invalid_data(format!(
+            "Field count mismatch at line {}: expected {} fields, got {} fields",
+            line_num, expected_len, len
+        ));
+    }
+
+    CsvError::from(e)
+}
@@ -5,4 +5,41 @@ require_relative "rbcsv/rbcsv"
 
 module RbCsv
   class Error < StandardError; end
+  class HeaderError < Error; end
+
+  class Reader
+    include Enumerable
+
+    # Opens a reader on a file path or IO. With a block, yields the reader
+    # and closes it afterwards, returning the block's value.
+    def self.open(source, **options)
+      reader = new(source, **options)
+      return reader unless block_given?
+
+      begin
+        yield reader
+      ensure
+        reader.close
+      end
+    end
+  end
+
+  class Table
+    include Enumerable
+  end
+
+  class Writer
+    # Opens a writer on a file path or IO. With a block, yields the writer
+    # and closes it afterwards, returning the block's value.
+    def self.open(target, **options)
+      writer = new(target, **options)
+      return writer unless block_given?
+
+      begin
+        yield writer
+      ensure
+        writer.close
+      end
+    end
+  end
 end
@@ -0,0 +1,229 @@
+# frozen_string_literal: true
+
+require "csv"
+require "stringio"
+require_relative "../rbcsv"
+
+module RbCsv
+  # Drop-in replacement for the class methods of Ruby's standard CSV, backed by
+  # the Rust parser. Accepts the stdlib option names and returns CSV::Row and
+  # CSV::Table objects, so existing code can switch with `CSV = RbCsv::Compat`
+  # or by replacing the receiver.
+  #
+  # Differences from the stdlib:
+  # - blank lines are always skipped (`skip_blanks: false` raises)
+  # - stray quotes never raise (`liberal_parsing: false` raises)
+  # - empty fields are returned as nil whether or not they were quoted
+  #   (`empty_value:` and `nil_value:`, which rely on telling them apart, raise)
+  # - `generate` writes "" and nil alike as an empty field (the stdlib writes "" as `""`)
+  # - `col_sep:`, `quote_char:` and `row_sep:` (other than "\r\n") must be a single
+  #   byte; longer separators such as "||" raise ArgumentError
+  # - `foreach` only reads UTF-8: its mode may add "b", "t" and "bom|" to "r"
+  #   ("r:bom|utf-8" skips a BOM), other modes and encodings raise ArgumentError
+  module Compat
+    # Options handled by the Rust reader
+    PARSER_OPTIONS = %i[col_sep quote_char row_sep].freeze
+    # Options interpreted here on top of the parsed fields
+    ROW_OPTIONS = %i[headers return_headers header_converters converters skip_blanks liberal_parsing].freeze
+    # Options accepted by generate/generate_line
+    WRITER_OPTIONS = %i[col_sep quote_char row_sep headers write_headers].freeze
+    # Row options that are only accepted when truthy, since the reader always behaves that way
+    ALWAYS_ON_OPTIONS = %i[skip_blanks liberal_parsing].freeze
+    # Stdlib options that distinguish quoted empty fields from empty ones
+    EMPTY_FIELD_OPTIONS = %i[empty_value nil_value].freeze
+    # File modes accepted by foreach (reading UTF-8, optionally skipping a BOM)
+    READ_MODE = /\Ar[bt]?(:(bom\|)?utf-8)?\z/i
+
+    class << self
+      # Parses a CSV string. Returns an array of arrays, or a CSV::Table when
+      # `headers:` is given. With a block, yields each row instead.
+      def parse(str, **options, &block)
+        rows(StringIO.new(str), options, &block)
+      end
+
+      # Parses a single line and returns its first row
+      def parse_line(line, **options)
+        parse(line, **options).first
+      end
+
+      # Yields each row of a CSV file (or IO). Returns an Enumerator without a block.
+      def foreach(path, mode = "r", **options, &block)
+        return to_enum(__method__, path, mode, **options) unless block
+        raise ArgumentError, "mode #{mode.inspect} is not supported (only UTF-8 reading modes)" unless READ_MODE.match?(mode)
+
+        parser = Parser.new(options)
+        return parser.each(path, &block) if mode == "r" || !path.is_a?(String)
+
+        File.open(path, mode) { |file| parser.each(file, &block) }
+      end
+
+      # Reads a whole CSV file (or IO) like `parse`
+      def read(path, **options)
+        rows(path, options)
+      end
+      alias readlines read
+
+      # Reads a CSV file as a table, with the same defaults as CSV.table
+      def table(path, **options)
+        read(path, headers: true, converters: :numeric, header_converters: :symbol, **options)
+      end
+
+      # Builds a CSV string. Rows are appended to `str` itself when given, like the stdlib.
+      def generate(str = nil, **options)
+        unknown = options.keys - WRITER_OPTIONS
+        raise ArgumentError, "unknown keyword: #{unknown.first.inspect}" unless unknown.empty?
+
+        output = str || +""
+        io = StringIO.new(output)
+        io.seek(0, IO::SEEK_END)
+        headers = options[:headers]
+        headers = parse_line(headers) if headers.is_a?(String)
+        writer_options = options.slice(:col_sep, :quote_char, :row_sep)
+        writer_options[:headers] = headers if headers && options[:write_headers]
+
+        Writer.open(io, **writer_options) { |writer| yield Generator.new(writer, headers) }
+        output
+      end
+
+      # Builds a single CSV line from an array of fields
+      def generate_line(row, **options)
+        generate(**options) { |csv| csv << row }
+      end
+
+      private
+
+      def rows(source, options, &block)
+        parser = Parser.new(options)
+        return parser.each(source, &block) if block
+
+        rows = []
+        parser.each(source) { |row| rows << row }
+        parser.headers? ? CSV::Table.new(rows, headers: parser.headers || []) : rows
+      end
+    end
+
+    # Converts the fields read by RbCsv::Reader into stdlib-compatible rows
+    class Parser
+      attr_reader :headers
+
+      def initialize(options)
+        check_options(options)
+
+        @reader_options = options.slice(*PARSER_OPTIONS).merge(flexible: true)
+        @header_option = options[:headers]
+        @return_headers = options[:return_headers]
+        @header_converters = resolve(options[:header_converters], CSV::HeaderConverters)
+        @converters = resolve(options[:converters], CSV::Converters)
+        @headers = initial_headers
+      end
+
+      def headers?
+        !!@header_option
+      end
+
+      def each(source)
+        Reader.open(source, **@reader_options) do |reader|
+          reader.each do |fields|
+            row = build(fields.map { |field| field.empty? ? nil : field }, reader.line)
+            yield row if row
+          end
+        end
+        nil
+      end
+
+      private
+
+      def check_options(options)
+        empty_field = options.keys & EMPTY_FIELD_OPTIONS
+        unless empty_field.empty?
+          raise ArgumentError, "#{empty_field.first}: is not supported (quoted empty fields are read as nil too)"
+        end
+
+        unknown = options.keys - PARSER_OPTIONS - ROW_OPTIONS
+        raise ArgumentError, "unknown keyword: #{unknown.first.inspect}" unless unknown.empty?
+
+        ALWAYS_ON_OPTIONS.each do |name|
+          next if !options.key?(name) || options[name]
+
+          raise ArgumentError, "#{name}: #{options[name].inspect} is not supported (always enabled)"
+        end
+      end
+
+      def initial_headers
+        case @header_option
+        when Array then convert_headers(@header_option)
+        when String then convert_headers(Compat.parse_line(@header_option, **@reader_options.except(:flexible)))
+        end
+      end
+
+      # Returns nil for the header row unless `return_headers: true`
+      def build(fields, line)
+        return convert(fields, line) unless headers?
+
+        if @headers.nil?
+          @headers = convert_headers(fields)
+          return @return_headers ? CSV::Row.new(@headers, fields, true) : nil
+        end
+
+        CSV::Row.new(@headers, convert(fields, line))
+      end
+
+      def convert_headers(fields)
+        fields.each_with_index.map do |field, index|
+          apply(@header_converters, field, CSV::FieldInfo.new(index, 1, nil))
+        end
+      end
+
+      def convert(fields, line)
+        return fields if @converters.empty?
+
+        fields.each_with_index.map do |field, index|
+          apply(@converters, field, CSV::FieldInfo.new(index, line, @headers && @headers[index]))
+        end
+      end
+
+      # Applies converters in order until the field is no longer a String
+      def apply(converters, field, info)
+        converters.each do |converter|
+          break unless field.is_a?(String)
+
+          field = converter.arity == 1 ? converter.call(field) : converter.call(field, info)
+        end
+        field
+      end
+
+      # Expands converter names (including lists such as :all) into callables
+      def resolve(converters, registry)
+        Array(converters).flat_map do |converter|
+          next [converter] if converter.respond_to?(:call)
+
+          entry = registry.fetch(converter) { raise ArgumentError, "unknown converter name: #{converter.inspect}" }
+          entry.respond_to?(:call) ? [entry] : resolve(entry, registry)
+        end
+      end
+    end
+
+    # The object yielded by `generate`; accepts arrays, CSV::Row and Hash rows
+    class Generator
+      def initialize(writer, headers)
+        @writer = writer
+        @headers = headers
+      end
+
+      def <<(row)
+        fields = case row
+                 when CSV::Row then row.fields
+                 when Hash
+                   raise ArgumentError, "headers: is required to write Hash rows" unless @headers
+
+                   @headers.map { |header| row[header] }
+                 else row
+                 end
+        @writer << fields
+        self
+      end
+      alias add_row <<
+      alias puts <<
+    end
+  end
+end
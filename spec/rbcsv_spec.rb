@@ -1,6 +1,10 @@
 # frozen_string_literal: true
 
 require 'fileutils'
+require 'pathname'
+require 'stringio'
+require 'zlib'
+require 'rbcsv/csv'
 
 RSpec.describe RbCsv do
   it "has a version number" do
@@ -20,6 +24,13 @@ RSpec.describe RbCsv do
       expect(result).to eq([["name", "description"], ["John Doe", "Software Engineer"]])
     end
 
+    it "returns independent UTF-8 strings for every field" do
+      result = RbCsv.parse("都市,,x\n東京,\"a\nb\",x")
+      expect(result).to eq([["都市", "", "x"], ["東京", "a\nb", "x"]])
+      expect(result.flatten.map(&:encoding).uniq).to eq([Encoding::UTF_8])
+      expect(result[0][2]).not_to equal(result[1][2])
+    end
+
     it "handles literal backslash characters" do
       csv = "a,b\\\\n1,2"  # literal backslash followed by literal n
       result = RbCsv.parse(csv)
@@ -41,6 +52,42 @@ RSpec.describe RbCsv do
     end
   end
 
+  describe "trim modes" do
+    let(:csv_data) { " name , age \n Alice , 25 \n" }
+
+    it "trims only the header row with trim: :headers" do
+      expect(RbCsv.parse(csv_data, headers: true, trim: :headers)).to eq([{ "name" => " Alice ", "age" => " 25 " }])
+    end
+
+    it "trims only the data rows with trim: :fields" do
+      expect(RbCsv.parse(csv_data, headers: true, trim: :fields)).to eq([{ " name " => "Alice", " age " => "25" }])
+      expect(RbCsv.foreach(StringIO.new(csv_data), headers: true, trim: :fields).first).to eq({ " name " => "Alice", " age " => "25" })
+    end
+
+    it "treats every row as a data row without headers" do
+      expect(RbCsv.parse(csv_data, trim: :fields)).to eq([["name", "age"], ["Alice", "25"]])
+      expect(RbCsv.parse(csv_data, trim: :headers)).to eq(RbCsv.parse(csv_data))
+    end
+
+    it "accepts :all and :none as aliases of true and false" do
+      expect(RbCsv.parse(csv_data, trim: :all)).to eq(RbCsv.parse!(csv_data))
+      expect(RbCsv.parse!(csv_data, trim: :none)).to eq(RbCsv.parse(csv_data))
+    end
+
+    it "trims Unicode whitespace and additional characters given in trim_chars:" do
+      csv = "\u3000名前\u200B,年齢\n\u00A0山田 \u3000,\uFEFF30\n"
+      expect(RbCsv.parse!(csv)).to eq([["名前\u200B", "年齢"], ["山田", "\uFEFF30"]])
+      expect(RbCsv.parse(csv, trim: true, trim_chars: "\u200B\uFEFF")).to eq([["名前", "年齢"], ["山田", "30"]])
+      expect(RbCsv.parse(csv, headers: true, typed: true, trim: :all, trim_chars: "\u200B\uFEFF")).to eq([{ "名前" => "山田", "年齢" => 30 }])
+    end
+
+    it "rejects unknown modes and trim_chars: without trimming" do
+      expect { RbCsv.parse(csv_data, trim: :both) }.to raise_error(ArgumentError, /unknown trim mode: :both/)
+      expect { RbCsv.parse(csv_data, trim: "all") }.to raise_error(TypeError, /trim: expected true, false or a Symbol/)
+      expect { RbCsv.parse(csv_data, trim_chars: "\u3000") }.to raise_error(ArgumentError, /trim_chars: requires trim:/)
+    end
+  end
+
   describe ".read" do
     let(:test_file_path) { File.join(__dir__, "fixtures", "test.csv") }
 
@@ -65,6 +112,28 @@ RSpec.describe RbCsv do
         RbCsv.read(__dir__)
       }.to raise_error(RuntimeError, /Path is not a file/)
     end
+
+    it "reads from a named pipe" do
+      fifo = File.join(__dir__, "tmp", "test_fifo.csv")
+      FileUtils.mkdir_p(File.dirname(fifo))
+      File.mkfifo(fifo)
+      writer = Thread.new { File.write(fifo, "a,b\n1,2\n") }
+
+      expect(RbCsv.read(fifo)).to eq([["a", "b"], ["1", "2"]])
+      writer.join
+    ensure
+      FileUtils.rm_f(fifo)
+    end
+
+    it "raises error for invalid UTF-8" do
+      path = File.join(__dir__, "tmp", "test_invalid_utf8.csv")
+      FileUtils.mkdir_p(File.dirname(path))
+      File.binwrite(path, "a,\xFF\n".b)
+
+      expect { RbCsv.read(path) }.to raise_error(RuntimeError, /Encoding Error/)
+    ensure
+      FileUtils.rm_f(path)
+    end
   end
 
   describe ".read!" do
@@ -220,4 +289,1037 @@ RSpec.describe RbCsv do
       expect(result[0]).to eq(["name", "age", "city"])
     end
   end
+
+  describe "typed converters" do
+    it "applies Ruby procs to columns by header name" do
+      csv = "code,flag\nA1,Y\nB2,N"
+      result = RbCsv.parse_typed(csv, converters: { "code" => ->(v) { v.downcase.to_sym } })
+      expect(result).to eq([["code", "flag"], [:a1, "Y"], [:b2, "N"]])
+    end
+
+    it "applies lookup tables" do
+      csv = "name,active\nAlice,Y\nBob,N"
+      result = RbCsv.parse_typed(csv, converters: { active: { lookup: { "Y" => true, "N" => false } } })
+      expect(result).to eq([["name", "active"], ["Alice", true], ["Bob", false]])
+    end
+
+    it "applies regex captures and prefix stripping by column index" do
+      csv = "10kg,$1.5\n25kg,$30"
+      result = RbCsv.parse_typed(csv, converters: { 0 => { regex: /(\d+)kg/ }, 1 => { strip_prefix: "$" } })
+      expect(result).to eq([[10, 1.5], [25, 30]])
+    end
+
+    it "applies converters after trimming" do
+      csv = " price \n $10 "
+      result = RbCsv.parse_typed!(csv, converters: { "price" => { strip_prefix: "$" } })
+      expect(result).to eq([["price"], [10]])
+    end
+
+    it "raises error for unknown header name" do
+      expect {
+        RbCsv.parse_typed("a,b\n1,2", converters: { "c" => ->(v) { v } })
+      }.to raise_error(RuntimeError, /Converter column not found/)
+    end
+
+    it "raises error for invalid converter spec" do
+      expect {
+        RbCsv.parse_typed("a,b\n1,2", converters: { "a" => { unknown: 1 } })
+      }.to raise_error(ArgumentError, /unknown converter type/)
+    end
+  end
+
+  describe "record mapping" do
+    let(:csv) { "name,age,city\nAlice,25,Tokyo\nBob,30,Osaka" }
+
+    it "builds Struct instances from header names" do
+      record = Struct.new(:age, :name)
+      result = RbCsv.parse(csv, as: record)
+      expect(result).to eq([record.new("25", "Alice"), record.new("30", "Bob")])
+    end
+
+    it "builds Data instances with schema conversion" do
+      record = Data.define(:name, :age)
+      result = RbCsv.parse(csv, as: record, schema: { age: :integer })
+      expect(result).to eq([record.new(name: "Alice", age: 25), record.new(name: "Bob", age: 30)])
+    end
+
+    it "supports keyword_init structs" do
+      record = Struct.new(:name, :city, keyword_init: true)
+      result = RbCsv.read(File.join(__dir__, "fixtures", "test.csv"), as: record)
+      expect(result.first).to eq(record.new(name: "Alice", city: "Tokyo"))
+    end
+
+    it "raises error when a member has no matching header" do
+      record = Struct.new(:name, :email)
+      expect {
+        RbCsv.parse(csv, as: record)
+      }.to raise_error(RuntimeError, /Headers missing for record members: email/)
+    end
+
+    it "raises error when schema conversion fails" do
+      record = Data.define(:name, :age)
+      expect {
+        RbCsv.parse("name,age\nAlice,unknown", as: record, schema: { age: :integer })
+      }.to raise_error(RuntimeError, /Cannot convert 'unknown' to integer/)
+    end
+  end
+
+  describe "header handling" do
+    it "returns hashes with headers: true" do
+      result = RbCsv.parse("name,age\nAlice,25\nBob,30", headers: true)
+      expect(result).to eq([{ "name" => "Alice", "age" => "25" }, { "name" => "Bob", "age" => "30" }])
+    end
+
+    it "normalizes headers with header_converters" do
+      csv = " First Name ,OrderID\nAlice,1"
+      result = RbCsv.parse_typed(csv, headers: true, header_converters: [:strip, :snake_case, :symbolize])
+      expect(result).to eq([{ first_name: "Alice", order_id: 1 }])
+    end
+
+    it "raises error for duplicate headers by default" do
+      expect {
+        RbCsv.parse("Amount,amount\n1,2", headers: true, header_converters: :downcase)
+      }.to raise_error(RuntimeError, /Duplicate header: amount/)
+    end
+
+    it "suffixes duplicate headers" do
+      result = RbCsv.parse("amount,amount\n1,2", headers: true, duplicate_headers: :suffix)
+      expect(result).to eq([{ "amount" => "1", "amount_2" => "2" }])
+    end
+
+    it "collects duplicate header values into arrays" do
+      result = RbCsv.parse_typed("id,amount,amount\n1,2,3", headers: true, duplicate_headers: :array)
+      expect(result).to eq([{ "id" => 1, "amount" => [2, 3] }])
+    end
+
+    it "matches normalized headers to record members" do
+      record = Struct.new(:first_name)
+      result = RbCsv.parse("First Name\nAlice", as: record, header_converters: :snake_case)
+      expect(result).to eq([record.new("Alice")])
+    end
+
+    it "raises error for header options without headers" do
+      expect {
+        RbCsv.parse("a,b\n1,2", header_converters: :downcase)
+      }.to raise_error(ArgumentError, /require headers: true/)
+    end
+  end
+
+  describe "header validation" do
+    let(:csv) { "id,name,amount\n1,Alice,100" }
+
+    it "passes when required headers are present" do
+      result = RbCsv.parse(csv, required_headers: ["amount", "id"])
+      expect(result.length).to eq(2)
+    end
+
+    it "raises HeaderError listing missing columns" do
+      expect {
+        RbCsv.parse(csv, required_headers: ["id", "email", "phone"])
+      }.to raise_error(RbCsv::HeaderError, /missing \[email, phone\]/)
+    end
+
+    it "reports unexpected and reordered columns in strict mode" do
+      expect {
+        RbCsv.read_typed(File.join(__dir__, "fixtures", "test.csv"),
+          required_headers: %w[age name], strict_headers: true)
+      }.to raise_error(RbCsv::HeaderError, /unexpected \[city\]; reordered \[name, age\]/)
+    end
+
+    it "compares normalized headers" do
+      result = RbCsv.parse(" ID ,Name\n1,Alice", headers: true, header_converters: [:strip, :downcase],
+        required_headers: [:id, :name], strict_headers: true)
+      expect(result).to eq([{ "id" => "1", "name" => "Alice" }])
+    end
+
+    it "is a subclass of RbCsv::Error" do
+      expect(RbCsv::HeaderError.ancestors).to include(RbCsv::Error)
+    end
+  end
+
+  describe "validation" do
+    let(:csv) { "id,age,status\n1,20,active\n1,-5,\n2,abc,deleted\n3,40,inactive" }
+    let(:rules) do
+      {
+        "id" => { unique: true },
+        "age" => { min: 0, max: 150 },
+        status: { not_empty: true, in: %w[active inactive] }
+      }
+    end
+
+    it "returns clean rows and every violation" do
+      report = RbCsv.validate(csv, rules: rules)
+
+      expect(report[:valid]).to be false
+      expect(report[:rows]).to eq([
+        { "id" => 1, "age" => 20, "status" => "active" },
+        { "id" => 3, "age" => 40, "status" => "inactive" }
+      ])
+      expect(report[:violations].map { |v| [v[:row], v[:rule]] }).to eq([
+        [3, :unique], [3, :min], [3, :not_empty], [4, :min], [4, :max], [4, :in]
+      ])
+      expect(report[:violations].first).to include(column: 0, header: "id", value: "1")
+    end
+
+    it "reports valid input" do
+      report = RbCsv.validate("email\na@example.com", rules: { 0 => { pattern: /@/, max_length: 20 } })
+      expect(report).to eq(valid: true, rows: [{ "email" => "a@example.com" }], violations: [])
+    end
+
+    it "validates rows without headers by column index" do
+      report = RbCsv.validate("abc\nx", rules: { 0 => { min_length: 2 } }, headers: false)
+      expect(report[:rows]).to eq([["abc"]])
+      expect(report[:violations].first).to include(row: 2, column: 0, header: nil, rule: :min_length)
+    end
+
+    it "reports duplicate headers and skips empty cells for unique:" do
+      report = RbCsv.validate("id,code,id\n1,,a\n2,,b\n", rules: { "code" => { unique: true } })
+      expect(report[:valid]).to be false
+      expect(report[:violations]).to eq([
+        { row: 1, column: 2, header: "id", rule: :unique_header, value: "id", message: "must be unique within the header row" }
+      ])
+      expect(report[:rows]).to eq([
+        { "id" => 1, "code" => "", "id_2" => "a" },
+        { "id" => 2, "code" => "", "id_2" => "b" }
+      ])
+    end
+
+    it "checks only not_empty: on empty cells" do
+      csv = "name,price\nApple,100\nBanana,\n,abc\n"
+      report = RbCsv.validate(csv, rules: { "price" => { min: 0, max: 500, pattern: /\A\d+\z/ }, "name" => { not_empty: true, in: %w[Apple Banana] } })
+      expect(report[:violations].map { |v| [v[:row], v[:rule]] }).to eq([[4, :min], [4, :max], [4, :pattern], [4, :not_empty]])
+      expect(report[:rows]).to eq([{ "name" => "Apple", "price" => 100 }, { "name" => "Banana", "price" => "" }])
+    end
+
+    it "validates files" do
+      report = RbCsv.validate_file(File.join(__dir__, "fixtures", "test.csv"), rules: { "age" => { min: 26 } })
+      expect(report[:violations].map { |v| v[:value] }).to eq(["25"])
+    end
+
+    it "raises error for unknown rules" do
+      expect {
+        RbCsv.validate(csv, rules: { "id" => { positive: true } })
+      }.to raise_error(ArgumentError, /unknown validation rule: positive/)
+    end
+  end
+
+  describe ".profile" do
+    let(:csv) { "id,price,name,note\n1,1.5,Apple,\n2,2.5,Banana,\n3,,Apple,\n" }
+
+    it "summarizes every column" do
+      id, price, name, note = RbCsv.profile(StringIO.new(csv))
+
+      expect(id).to eq(
+        index: 0, name: "id", type: :integer, count: 3, nulls: 0, distinct: 3,
+        min: 1, max: 3, mean: 2.0, stddev: 1.0, max_length: 1, top: [[1, 1], [2, 1], [3, 1]]
+      )
+      expect(price).to include(type: :float, count: 2, nulls: 1, min: 1.5, max: 2.5, mean: 2.0)
+      expect(name).to include(type: :string, distinct: 2, min: "Apple", max: "Banana", mean: nil, max_length: 6)
+      expect(name[:top]).to eq([["Apple", 2], ["Banana", 1]])
+      expect(note).to include(type: :nil, count: 0, nulls: 3, distinct: 0, min: nil, stddev: nil, top: [])
+    end
+
+    it "profiles files without headers" do
+      columns = RbCsv.profile(File.join(__dir__, "fixtures", "test.csv"), headers: false, top: 1)
+      expect(columns.map { |column| column[:name] }).to eq([nil, nil, nil])
+      expect(columns[1]).to include(type: :string, count: 4, top: [["25", 1]])
+    end
+
+    it "estimates distinct counts with distinct: :approximate" do
+      data = "id\n" + (1..5000).map(&:to_s).join("\n")
+      column = RbCsv.profile(StringIO.new(data), distinct: :approximate).first
+      expect(column[:distinct]).to be_within(150).of(5000)
+      expect(column[:max]).to eq(5000)
+    end
+
+    it "accepts dialect and trim options" do
+      column = RbCsv.profile(StringIO.new(" a ; b \n x ;1\n"), col_sep: ";", trim: true).first
+      expect(column).to include(name: "a", min: "x")
+    end
+
+    it "rejects invalid options" do
+      expect { RbCsv.profile(StringIO.new(csv), distinct: :fuzzy) }.to raise_error(ArgumentError, /unknown distinct mode/)
+      expect { RbCsv.profile(StringIO.new(csv), top: -1) }.to raise_error(ArgumentError, /non-negative/)
+      expect { RbCsv.profile(StringIO.new(csv), typed: true) }.to raise_error(ArgumentError, /unknown keyword/)
+    end
+  end
+
+  describe ".sort" do
+    let(:output_path) { File.join(__dir__, "tmp", "test_sort.csv") }
+    let(:csv) { "name,score\nb,10\na,9\nc,\nd,100\n" }
+
+    before do
+      FileUtils.mkdir_p(File.dirname(output_path))
+    end
+
+    after do
+      FileUtils.rm_f(output_path)
+    end
+
+    it "sorts rows by header name and keeps the header first" do
+      output = StringIO.new
+      expect(RbCsv.sort(StringIO.new(csv), output, by: "name")).to eq(4)
+      expect(output.string).to eq("name,score\na,9\nb,10\nc,\nd,100\n")
+    end
+
+    it "compares numbers with typed: true and puts empty values last" do
+      output = StringIO.new
+      RbCsv.sort(StringIO.new(csv), output, by: :score, typed: true)
+      expect(output.string).to eq("name,score\na,9\nb,10\nd,100\nc,\n")
+
+      output = StringIO.new
+      RbCsv.sort(StringIO.new(csv), output, by: :score, order: :desc, typed: true)
+      expect(output.string).to eq("name,score\nd,100\nb,10\na,9\nc,\n")
+    end
+
+    it "sorts by several columns with per-column order and keeps ties stable" do
+      output = StringIO.new
+      RbCsv.sort(StringIO.new("1,b,x\n2,a,y\n1,a,z\n2,a,w\n"), output, by: [0, 1], order: [:desc, :asc], headers: false)
+      expect(output.string).to eq("2,a,y\n2,a,w\n1,a,z\n1,b,x\n")
+    end
+
+    it "spills to temporary files and keeps quoted newlines intact" do
+      rows = (0...2000).map { |i| [(i * 7919) % 1000, "line\n#{i}"] }
+      RbCsv.write(output_path, [["id", "note"]] + rows.map { |id, note| [id.to_s, note] })
+
+      RbCsv.sort(output_path, output_path, by: "id", typed: true, memory_limit: 4096)
+      result = RbCsv.read_typed(output_path, headers: true)
+      expect(result.map { |row| row["id"] }).to eq(rows.map(&:first).sort)
+      expect(result.first["note"]).to start_with("line\n")
+    end
+
+    it "compresses the output by extension" do
+      gz_path = "#{output_path}.gz"
+      RbCsv.sort(StringIO.new(csv), gz_path, by: "name")
+      expect(RbCsv.read(gz_path).last).to eq(["d", "100"])
+    ensure
+      FileUtils.rm_f(gz_path)
+    end
+
+    it "rejects unknown columns and invalid options" do
+      expect { RbCsv.sort(StringIO.new(csv), StringIO.new, by: "missing") }.to raise_error(RuntimeError, /Sort column not found/)
+      expect { RbCsv.sort(StringIO.new(csv), StringIO.new) }.to raise_error(ArgumentError, /missing keyword: :by/)
+      expect { RbCsv.sort(StringIO.new(csv), StringIO.new, by: "name", order: :up) }.to raise_error(ArgumentError, /unknown sort order/)
+      expect { RbCsv.sort(StringIO.new(csv), StringIO.new, by: "name", memory_limit: 0) }.to raise_error(ArgumentError, /memory_limit/)
+    end
+  end
+
+  describe ".dedup" do
+    let(:output_path) { File.join(__dir__, "tmp", "test_dedup.csv") }
+    let(:csv) { "id,name\n1,a\n2,b\n1,c\n3,d\n2,e\n" }
+
+    before do
+      FileUtils.mkdir_p(File.dirname(output_path))
+    end
+
+    after do
+      FileUtils.rm_f(output_path)
+    end
+
+    it "keeps the first row per key and reports dropped duplicates" do
+      output = StringIO.new
+      expect(RbCsv.dedup(StringIO.new(csv), output, keys: ["id"])).to eq(rows: 3, duplicates: 2)
+      expect(output.string).to eq("id,name\n1,a\n2,b\n3,d\n")
+    end
+
+    it "keeps the last row per key with keep: :last" do
+      output = StringIO.new
+      RbCsv.dedup(StringIO.new(csv), output, keys: :id, keep: :last)
+      expect(output.string).to eq("id,name\n1,c\n3,d\n2,e\n")
+    end
+
+    it "compares whole rows by default, with typed and trimmed values" do
+      input = "1, x\n1.0,x\n01,x \n"
+      output = StringIO.new
+      expect(RbCsv.dedup(StringIO.new(input), output, headers: false)).to eq(rows: 3, duplicates: 0)
+
+      output = StringIO.new
+      expect(RbCsv.dedup(StringIO.new(input), output, headers: false, typed: true, trim: true)).to eq(rows: 1, duplicates: 2)
+      expect(output.string).to eq("1,x\n")
+    end
+
+    it "writes files and can rewrite its input in place" do
+      input_path = File.join(__dir__, "tmp", "test_dedup_input.csv")
+      File.write(input_path, csv)
+      RbCsv.dedup(input_path, output_path, keys: [0])
+      expect(RbCsv.read(output_path)).to eq([["id", "name"], ["1", "a"], ["2", "b"], ["3", "d"]])
+
+      expect(RbCsv.dedup(input_path, input_path, keys: [0], keep: :last)).to eq(rows: 3, duplicates: 2)
+      expect(RbCsv.read(input_path)).to eq([["id", "name"], ["1", "c"], ["3", "d"], ["2", "e"]])
+    ensure
+      FileUtils.rm_f(input_path)
+    end
+
+    it "rejects unknown columns and invalid options" do
+      expect { RbCsv.dedup(StringIO.new(csv), StringIO.new, keys: ["missing"]) }.to raise_error(RuntimeError, /Dedup column not found/)
+      expect { RbCsv.dedup(StringIO.new(csv), StringIO.new, keep: :middle) }.to raise_error(ArgumentError, /unknown keep/)
+      expect { RbCsv.dedup(StringIO.new(csv), StringIO.new, keys: [1.5]) }.to raise_error(TypeError, /keys: expected a column index or header name/)
+    end
+  end
+
+  describe ".aggregate" do
+    let(:csv) { "category,amount,id\nA,10,1\nB,2.5,2\nA,5,3\nB,,4\nA,1,5\n" }
+
+    it "aggregates per group and returns hashes" do
+      result = RbCsv.aggregate(StringIO.new(csv), group_by: [:category], aggregations: { amount: [:sum, :mean], id: :count })
+      expect(result).to eq([
+        { "category" => "A", "amount_sum" => 16, "amount_mean" => 16.0 / 3, "id_count" => 3 },
+        { "category" => "B", "amount_sum" => 2.5, "amount_mean" => 2.5, "id_count" => 2 }
+      ])
+    end
+
+    it "returns arrays with a header row with format: :arrays" do
+      result = RbCsv.aggregate(StringIO.new(csv), group_by: "category", aggregations: { "amount" => [:min, :max] }, format: :arrays)
+      expect(result).to eq([["category", "amount_min", "amount_max"], ["A", 1, 10], ["B", 2.5, 2.5]])
+    end
+
+    it "aggregates the whole file without group_by: and filters with where:" do
+      result = RbCsv.aggregate(StringIO.new(csv), aggregations: { amount: :sum, id: :count }, where: "category == 'A'")
+      expect(result).to eq([{ "amount_sum" => 16, "id_count" => 3 }])
+    end
+
+    it "uses column indexes without headers" do
+      result = RbCsv.aggregate(File.join(__dir__, "fixtures", "test.csv"), headers: false, aggregations: { 0 => :count })
+      expect(result).to eq([{ "$0_count" => 4 }])
+    end
+
+    it "raises for non-numeric values and invalid options" do
+      expect { RbCsv.aggregate(StringIO.new("a\nx\n"), aggregations: { a: :sum }) }.to raise_error(RuntimeError, /non-numeric value "x"/)
+      expect { RbCsv.aggregate(StringIO.new(csv), aggregations: { amount: :median }) }.to raise_error(ArgumentError, /unknown aggregation/)
+      expect { RbCsv.aggregate(StringIO.new(csv), group_by: :category) }.to raise_error(ArgumentError, /missing keyword: :aggregations/)
+      expect { RbCsv.aggregate(StringIO.new(csv), aggregations: { missing: :count }) }.to raise_error(RuntimeError, /Aggregate column not found/)
+      expect { RbCsv.aggregate(StringIO.new(csv), aggregations: { 0 => :count, "category" => :count }) }
+        .to raise_error(ArgumentError, /duplicate aggregate column: category_count/)
+    end
+  end
+
+  describe "IO input" do
+    let(:fixture) { File.join(__dir__, "fixtures", "test.csv") }
+
+    it "parses a StringIO" do
+      result = RbCsv.parse(StringIO.new("a,b\n1,2\n"))
+      expect(result).to eq([["a", "b"], ["1", "2"]])
+    end
+
+    it "reads an open File with options" do
+      result = File.open(fixture) { |io| RbCsv.read_typed(io, headers: true) }
+      expect(result.first).to eq({ "name" => "Alice", "age" => 25, "city" => "Tokyo" })
+    end
+
+    it "accepts a Pathname as a path" do
+      result = RbCsv.read(Pathname.new(fixture))
+      expect(result.length).to eq(4)
+    end
+
+    it "reads from a pipe" do
+      result = IO.popen(["cat", fixture]) { |io| RbCsv.parse!(io) }
+      expect(result[1]).to eq(["Alice", "25", "Tokyo"])
+    end
+
+    it "propagates errors raised by the IO" do
+      io = Object.new
+      def io.read(_len) = raise(IOError, "connection reset")
+
+      expect { RbCsv.parse(io) }.to raise_error(IOError, "connection reset")
+    end
+
+    it "raises TypeError for other objects" do
+      expect { RbCsv.parse(42) }.to raise_error(TypeError)
+    end
+  end
+
+  describe ".foreach" do
+    let(:fixture) { File.join(__dir__, "fixtures", "test.csv") }
+
+    it "yields each row of a file" do
+      rows = []
+      RbCsv.foreach(fixture) { |row| rows << row }
+      expect(rows).to eq(RbCsv.read(fixture))
+    end
+
+    it "yields hashes with typed values from an IO" do
+      rows = []
+      RbCsv.foreach(StringIO.new("id, price\n1, 9.5\n"), headers: true, typed: true, trim: true) { |row| rows << row }
+      expect(rows).to eq([{ "id" => 1, "price" => 9.5 }])
+    end
+
+    it "stops reading when the block breaks" do
+      io = StringIO.new("a\n1\n2\n3\n")
+      first = RbCsv.foreach(io) { |row| break row }
+      expect(first).to eq(["a"])
+    end
+
+    it "returns an Enumerator without a block" do
+      enum = RbCsv.foreach(fixture)
+      expect(enum).to be_a(Enumerator)
+      expect(enum.to_a).to eq(RbCsv.read(fixture))
+      expect(enum.first(2)).to eq(RbCsv.read(fixture).first(2))
+    end
+
+    it "supports lazy enumeration with early termination" do
+      io = StringIO.new("id,name\n" + (1..100_000).map { |i| "#{i},user#{i}\n" }.join)
+      rows = RbCsv.foreach(io, headers: true, typed: true).lazy.select { |row| row["id"].even? }.first(2)
+      expect(rows).to eq([{ "id" => 2, "name" => "user2" }, { "id" => 4, "name" => "user4" }])
+      expect(io.pos).to be < io.size
+    end
+
+    it "closes the file when enumeration stops early" do
+      skip "requires /proc" unless File.directory?("/proc/self/fd")
+
+      before = Dir.children("/proc/self/fd").size
+      row = RbCsv.foreach(fixture).find { |r| r.first == "Alice" }
+      expect(row).not_to be_nil
+      expect(Dir.children("/proc/self/fd").size).to eq(before)
+    end
+  end
+
+  describe ".each_slice" do
+    let(:csv_data) { "id,name\n" + (1..10).map { |i| "#{i},user#{i}\n" }.join }
+
+    it "yields batches of rows" do
+      batches = []
+      RbCsv.each_slice(StringIO.new(csv_data), 4) { |batch| batches << batch }
+      expect(batches.map(&:size)).to eq([4, 4, 3])
+      expect(batches.flatten(1)).to eq(RbCsv.parse(csv_data))
+    end
+
+    it "yields batches of typed hashes" do
+      batches = []
+      RbCsv.each_slice(StringIO.new(csv_data), 5, headers: true, typed: true) { |batch| batches << batch }
+      expect(batches.map(&:size)).to eq([5, 5])
+      expect(batches.first.first).to eq({ "id" => 1, "name" => "user1" })
+      expect(batches.last.last).to eq({ "id" => 10, "name" => "user10" })
+    end
+
+    it "reads files" do
+      fixture = File.join(__dir__, "fixtures", "test.csv")
+      batches = []
+      RbCsv.each_slice(fixture, 2) { |batch| batches << batch }
+      expect(batches.flatten(1)).to eq(RbCsv.read(fixture))
+    end
+
+    it "stops reading when the block breaks" do
+      io = StringIO.new(csv_data)
+      first = RbCsv.each_slice(io, 3) { |batch| break batch }
+      expect(first).to eq([["id", "name"], ["1", "user1"], ["2", "user2"]])
+    end
+
+    it "raises error for an invalid slice size" do
+      expect { RbCsv.each_slice(StringIO.new(csv_data), 0) { } }.to raise_error(ArgumentError, /invalid slice size/)
+    end
+
+    it "returns an Enumerator without a block" do
+      enum = RbCsv.each_slice(StringIO.new(csv_data), 4)
+      expect(enum).to be_a(Enumerator)
+      expect(enum.first).to eq(RbCsv.parse(csv_data).first(4))
+    end
+  end
+
+  describe "RbCsv::Reader" do
+    let(:fixture) { File.join(__dir__, "fixtures", "test.csv") }
+
+    it "reads rows from a file" do
+      reader = RbCsv::Reader.new(fixture)
+      expect(reader.read_all).to eq(RbCsv.read(fixture))
+      reader.close
+      expect(reader).to be_closed
+    end
+
+    it "yields hashes with typed values and exposes the headers" do
+      reader = RbCsv::Reader.new(StringIO.new("id, price\n1, 9.5\n2, 3\n"), headers: true, typed: true, trim: true)
+      expect(reader.headers).to eq(["id", "price"])
+      rows = []
+      reader.each { |row| rows << row }
+      expect(rows).to eq([{ "id" => 1, "price" => 9.5 }, { "id" => 2, "price" => 3 }])
+    end
+
+    it "applies header and converter options" do
+      csv = "Unit Price,Code\n$1.50,A\n"
+      rows = RbCsv::Reader.new(StringIO.new(csv), headers: true, typed: true, header_converters: :snake_case,
+                                                  converters: { "Unit Price" => { strip_prefix: "$" } }).read_all
+      expect(rows).to eq([{ "unit_price" => 1.5, "code" => "A" }])
+    end
+
+    it "continues from the current position and tracks the line" do
+      reader = RbCsv::Reader.new(StringIO.new("a,b\n1,2\n\"x\ny\",3\n4,5\n"))
+      expect(reader.line).to eq(0)
+      expect(reader.first).to eq(["a", "b"])
+      expect(reader.line).to eq(1)
+      expect(reader.first).to eq(["1", "2"])
+      expect(reader.first).to eq(["x\ny", "3"])
+      expect(reader.line).to eq(3)
+      expect(reader.read_all).to eq([["4", "5"]])
+      expect(reader.line).to eq(5)
+    end
+
+    it "is Enumerable and returns an Enumerator without a block" do
+      reader = RbCsv::Reader.new(fixture, headers: true)
+      expect(reader.each).to be_a(Enumerator)
+      expect(reader.map { |row| row["name"] }).to eq(RbCsv.read(fixture, headers: true).map { |row| row["name"] })
+    end
+
+    it "closes the reader after an open block" do
+      reader = nil
+      rows = RbCsv::Reader.open(fixture) { |r| (reader = r).first(2) }
+      expect(rows.size).to eq(2)
+      expect(reader).to be_closed
+      expect { reader.read_all }.to raise_error(IOError, /closed reader/)
+    end
+
+    it "propagates exceptions raised by the IO" do
+      io = Object.new
+      def io.read(_len = nil) = raise(IOError, "boom")
+      expect { RbCsv::Reader.new(io).read_all }.to raise_error(IOError, "boom")
+    end
+
+    it "raises error for unknown options" do
+      expect { RbCsv::Reader.new(fixture, unknown: true) }.to raise_error(ArgumentError, /unknown/)
+    end
+  end
+
+  describe "RbCsv::Table" do
+    let(:csv_data) { "name,price,rate\nApple,100,0.5\nBanana,80,1.5\nCherry,,2.0\n" }
+    let(:table) { RbCsv::Table.parse(csv_data) }
+
+    it "exposes headers, size and column access" do
+      expect(table.headers).to eq(["name", "price", "rate"])
+      expect(table.size).to eq(3)
+      expect(table[:rate]).to eq([0.5, 1.5, 2.0])
+      expect(table["name"]).to eq(["Apple", "Banana", "Cherry"])
+      expect(table.column(1)).to eq([100, 80, nil])
+      expect(table[:missing]).to be_nil
+      expect { table.column(:missing) }.to raise_error(KeyError, /unknown column/)
+    end
+
+    it "returns rows as hashes by index" do
+      expect(table[0]).to eq({ "name" => "Apple", "price" => 100, "rate" => 0.5 })
+      expect(table.row(-1)).to eq({ "name" => "Cherry", "price" => nil, "rate" => 2.0 })
+      expect(table[3]).to be_nil
+    end
+
+    it "iterates rows and is Enumerable" do
+      expect(table.each_row).to be_a(Enumerator)
+      expect(table.map { |row| row["name"] }).to eq(["Apple", "Banana", "Cherry"])
+      expect(table.each_row.first).to eq(table[0])
+    end
+
+    it "converts back to arrays and hashes" do
+      expect(table.to_a).to eq(RbCsv.parse_typed(csv_data).map { |row| row.map { |value| value == "" ? nil : value } })
+      expect(table.to_h).to eq({ "name" => ["Apple", "Banana", "Cherry"], "price" => [100, 80, nil], "rate" => [0.5, 1.5, 2.0] })
+    end
+
+    it "keeps empty cells as empty strings in text and mixed columns" do
+      mixed = RbCsv::Table.parse("a,b,c\n1,,x\n,,2.5\n")
+      expect(mixed.to_h).to eq({ "a" => [1, nil], "b" => ["", ""], "c" => ["x", 2.5] })
+      expect(RbCsv::Table.parse("a\n1\n\"\"\n", typed: false)[:a]).to eq(["1", ""])
+    end
+
+    it "accepts the parse options" do
+      plain = RbCsv::Table.read(File.join(__dir__, "fixtures", "test.csv"), headers: false, typed: false)
+      expect(plain.headers).to be_nil
+      expect(plain[0]).to eq(["name", "age", "city"])
+      expect(plain.column(1)).to eq(["age", "25", "30", "35"])
+
+      symbols = RbCsv::Table.parse(" Name , Age \n Alice , 25 \n", trim: true, header_converters: [:downcase, :symbolize])
+      expect(symbols.headers).to eq([:name, :age])
+      expect(symbols[0]).to eq({ name: "Alice", age: 25 })
+      expect(RbCsv::Table.parse("a,b\n").to_h).to eq({ "a" => [], "b" => [] })
+    end
+  end
+
+  describe "RbCsv::Compat" do
+    let(:csv_data) { "name;age\nAlice;25\n\nBob;\n" }
+
+    it "returns arrays like CSV.parse" do
+      expect(RbCsv::Compat.parse("a,b\n1,2\n")).to eq(CSV.parse("a,b\n1,2\n"))
+      expect(RbCsv::Compat.parse_line("x,,z")).to eq(["x", nil, "z"])
+    end
+
+    it "returns a CSV::Table of CSV::Row with headers: true" do
+      table = RbCsv::Compat.parse(csv_data, col_sep: ";", headers: true)
+      expect(table).to be_a(CSV::Table)
+      expect(table.headers).to eq(["name", "age"])
+      expect(table.first).to be_a(CSV::Row)
+      expect(table.map(&:to_h)).to eq([{ "name" => "Alice", "age" => "25" }, { "name" => "Bob", "age" => nil }])
+      expect(table["name"]).to eq(["Alice", "Bob"])
+    end
+
+    it "matches the stdlib for converters and header converters" do
+      options = { col_sep: ";", headers: true, converters: :numeric, header_converters: :symbol, skip_blanks: true }
+      expect(RbCsv::Compat.parse(csv_data, **options).map(&:to_h)).to eq(CSV.parse(csv_data, **options).map(&:to_h))
+
+      upcase = ->(field, info) { info.index.zero? ? field.upcase : field }
+      expect(RbCsv::Compat.parse("a,b\n", converters: upcase)).to eq([["A", "b"]])
+    end
+
+    it "accepts headers given as an array and quote_char/row_sep" do
+      rows = RbCsv::Compat.parse("'a|b',1|c,2|", headers: %w[key value], quote_char: "'", row_sep: "|")
+      expect(rows.map(&:fields)).to eq([["a|b", "1"], ["c", "2"]])
+    end
+
+    it "allows rows with different numbers of fields like the stdlib" do
+      expect(RbCsv::Compat.parse("a,b\n1\n1,2,3\n")).to eq([["a", "b"], ["1"], ["1", "2", "3"]])
+    end
+
+    it "streams files with foreach and reads tables" do
+      path = File.join(__dir__, "fixtures", "test.csv")
+      expect(RbCsv::Compat.foreach(path, headers: true).first).to eq(CSV.foreach(path, headers: true).first)
+      expect(RbCsv::Compat.table(path).headers).to eq(CSV.table(path).headers)
+    end
+
+    it "honours reading modes in foreach and rejects the others" do
+      path = File.join(__dir__, "tmp", "compat_bom.csv")
+      FileUtils.mkdir_p(File.dirname(path))
+      File.binwrite(path, "\xEF\xBB\xBFname,age\nAlice,25\n")
+      expect(RbCsv::Compat.foreach(path, "r:bom|utf-8", headers: true).first.to_h).to eq({ "name" => "Alice", "age" => "25" })
+      expect(RbCsv::Compat.foreach(path, "rb").to_a.size).to eq(2)
+      expect { RbCsv::Compat.foreach(path, "w") { } }.to raise_error(ArgumentError, /mode "w" is not supported/)
+      expect { RbCsv::Compat.foreach(path, "r:iso-8859-1:utf-8") { } }.to raise_error(ArgumentError, /not supported/)
+      expect(File.size(path)).to be > 0
+    ensure
+      FileUtils.rm_f(path)
+    end
+
+    it "generates CSV strings" do
+      csv = RbCsv::Compat.generate(col_sep: ";", headers: %w[name age], write_headers: true) do |out|
+        out << ["Alice", 25]
+        out << { "name" => "Bob", "age" => nil }
+      end
+      expect(csv).to eq("name;age\nAlice;25\nBob;\n")
+      expect(RbCsv::Compat.generate_line(["a", nil, "c,d"])).to eq(CSV.generate_line(["a", nil, "c,d"]))
+    end
+
+    it "appends to the given string in generate" do
+      str = +"id\n"
+      result = RbCsv::Compat.generate(str) { |out| out << [1] }
+      expect(result).to equal(str)
+      expect(str).to eq("id\n1\n")
+    end
+
+    it "documents the remaining differences from the stdlib" do
+      expect(RbCsv::Compat.generate_line(["", nil])).to eq(",\n")
+      expect { RbCsv::Compat.parse("a||b", col_sep: "||") }.to raise_error(ArgumentError, /single byte/)
+    end
+
+    it "rejects unknown options" do
+      expect { RbCsv::Compat.parse("a", strip: true) }.to raise_error(ArgumentError, /unknown keyword: :strip/)
+      expect { RbCsv::Compat.parse("a\n\nb\n", skip_blanks: false) }.to raise_error(ArgumentError, /skip_blanks: false is not supported/)
+      expect { RbCsv::Compat.parse("a", liberal_parsing: false) }.to raise_error(ArgumentError, /liberal_parsing: false/)
+      expect { RbCsv::Compat.parse("a,\"\"", empty_value: "") }.to raise_error(ArgumentError, /empty_value: is not supported/)
+      expect { RbCsv::Compat.parse("a,", nil_value: "") }.to raise_error(ArgumentError, /nil_value: is not supported/)
+      expect(RbCsv::Compat.parse("a\n\nb\n", skip_blanks: true, liberal_parsing: true)).to eq([["a"], ["b"]])
+    end
+  end
+
+  describe "RbCsv::Writer" do
+    let(:output_path) { File.join(__dir__, "tmp", "test_writer.csv") }
+
+    before do
+      FileUtils.mkdir_p(File.dirname(output_path))
+    end
+
+    after do
+      FileUtils.rm_f(output_path)
+    end
+
+    it "writes rows incrementally to a file" do
+      RbCsv::Writer.open(output_path, headers: ["id", "name"]) do |writer|
+        writer << [1, "Alice"] << [2, nil]
+        expect(writer.lineno).to eq(3)
+      end
+
+      expect(File.read(output_path)).to eq("id,name\n1,Alice\n2,\n")
+    end
+
+    it "writes to an IO without closing it" do
+      io = StringIO.new
+      RbCsv::Writer.open(io, col_sep: "\t") { |writer| writer << ["a", "b c"] }
+
+      expect(io.string).to eq("a\tb c\n")
+      expect(io).not_to be_closed
+    end
+
+    it "writes with a custom quote character and row separator" do
+      io = StringIO.new
+      RbCsv::Writer.open(io, quote_char: "'", row_sep: "\r\n") { |writer| writer << ["a,b", "c"] }
+
+      expect(io.string).to eq("'a,b',c\r\n")
+    end
+
+    it "flushes large outputs in chunks" do
+      io = StringIO.new
+      writer = RbCsv::Writer.new(io)
+      20_000.times { |i| writer << [i, "row #{i}"] }
+      expect(io.string).not_to be_empty
+      writer.close
+
+      expect(io.string.lines.length).to eq(20_000)
+      expect(writer).to be_closed
+    end
+
+    it "raises error for rows with a different field count" do
+      RbCsv::Writer.open(StringIO.new) do |writer|
+        writer << ["a", "b"]
+        expect { writer << ["c"] }.to raise_error(RuntimeError, /Field count mismatch at line 2/)
+      end
+    end
+
+    it "raises error after close" do
+      writer = RbCsv::Writer.new(StringIO.new)
+      writer.close
+      expect { writer << ["a"] }.to raise_error(IOError, /closed writer/)
+    end
+
+    it "lets RbCsv.write target an IO" do
+      io = StringIO.new
+      RbCsv.write(io, [["a", "b"], ["1", "2"]])
+      expect(io.string).to eq("a,b\n1,2\n")
+    end
+  end
+
+  describe "compression" do
+    let(:data) { [["id", "name"], ["1", "Alice"], ["2", "Bob"]] }
+    let(:tmp_dir) { File.join(__dir__, "tmp") }
+
+    before do
+      FileUtils.mkdir_p(tmp_dir)
+    end
+
+    after do
+      FileUtils.rm_f(Dir.glob(File.join(tmp_dir, "compressed*")))
+    end
+
+    %w[gz zst].each do |ext|
+      it "writes and reads .csv.#{ext} files by extension" do
+        path = File.join(tmp_dir, "compressed.csv.#{ext}")
+        RbCsv.write(path, data)
+
+        expect(File.binread(path, 2)).not_to eq("id")
+        expect(RbCsv.read(path)).to eq(data)
+        expect(RbCsv.read_typed(path, headers: true)).to eq([{ "id" => 1, "name" => "Alice" }, { "id" => 2, "name" => "Bob" }])
+      end
+    end
+
+    it "detects compression by magic bytes regardless of extension" do
+      path = File.join(tmp_dir, "compressed_data")
+      RbCsv.write(path, data, compress: :zstd)
+      expect(RbCsv.read(path)).to eq(data)
+    end
+
+    it "streams compressed files and IO" do
+      path = File.join(tmp_dir, "compressed_stream.csv.gz")
+      RbCsv::Writer.open(path) { |writer| data.each { |row| writer << row } }
+
+      rows = []
+      RbCsv.foreach(path) { |row| rows << row }
+      expect(rows).to eq(data)
+
+      expect(File.open(path, "rb") { |io| RbCsv.parse(io) }).to eq(data)
+    end
+
+    it "compresses writer output to an IO" do
+      io = StringIO.new("".b)
+      RbCsv::Writer.open(io, compress: :gzip) { |writer| writer << ["a", "b"] }
+      expect(Zlib.gunzip(io.string)).to eq("a,b\n")
+    end
+
+    it "raises error for unknown compression" do
+      expect {
+        RbCsv.write(File.join(tmp_dir, "compressed.csv"), data, compress: :bz2)
+      }.to raise_error(ArgumentError, /unknown compression/)
+    end
+  end
+
+  describe "string deduplication" do
+    let(:csv_data) { "country,status\nJP,active\nUS,active\nJP,closed\n" }
+
+    it "shares one frozen string per value with dedup: true" do
+      rows = RbCsv.parse(csv_data, dedup: true)
+      expect(rows.flatten).to all(be_frozen)
+      expect(rows[1][0]).to equal(rows[3][0])
+      expect(rows[1][1]).to equal(rows[2][1])
+    end
+
+    it "dedups hash values and typed strings" do
+      rows = RbCsv.parse_typed(csv_data, headers: true, dedup: true)
+      expect(rows[0]["status"]).to equal(rows[1]["status"])
+
+      streamed = []
+      RbCsv.foreach(StringIO.new(csv_data), dedup: true) { |row| streamed << row }
+      expect(streamed[1][0]).to equal(streamed[3][0])
+    end
+
+    it "freezes without sharing with freeze: true" do
+      rows = RbCsv.parse(csv_data, freeze: true)
+      expect(rows.flatten).to all(be_frozen)
+      expect(rows[1][0]).to eq(rows[3][0])
+      expect(rows[1][0]).not_to equal(rows[3][0])
+    end
+
+    it "returns mutable strings by default" do
+      expect(RbCsv.parse(csv_data).flatten).to all(satisfy { |field| !field.frozen? })
+    end
+  end
+
+  describe "where: filtering" do
+    let(:csv_data) { "id,status,amount,name\n1,active,150,Alice\n2,inactive,300,Bob\n3,active,20,Carol\n4,active,,Dave\n" }
+
+    it "keeps only the rows matching the expression" do
+      expect(RbCsv.parse(csv_data, headers: true, typed: true, where: "status == 'active' && amount > 100"))
+        .to eq([{ "id" => 1, "status" => "active", "amount" => 150, "name" => "Alice" }])
+      expect(RbCsv.parse(csv_data, headers: true, where: "amount >= 150 || name =~ /^c/i").map { |row| row["id"] }).to eq(["1", "2", "3"])
+      expect(RbCsv.parse_typed(csv_data, headers: true, where: "!(amount != nil)"))
+        .to eq([{ "id" => 4, "status" => "active", "amount" => "", "name" => "Dave" }])
+    end
+
+    it "refers to columns by index without headers" do
+      expect(RbCsv.parse("1,a\n2,b\n3,c\n", where: "$0 > 1 && $1 != 'c'")).to eq([["2", "b"]])
+      expect { RbCsv.parse("1,a\n", where: "id > 1") }.to raise_error(RuntimeError, /requires a header row/)
+    end
+
+    it "works with the streaming APIs and RbCsv::Table" do
+      io = -> { StringIO.new(csv_data) }
+      expect(RbCsv.foreach(io.call, headers: true, where: "id > 2").map { |row| row["id"] }).to eq(["3", "4"])
+      expect(RbCsv.each_slice(io.call, 1, where: "$1 == 'inactive'").to_a).to eq([[["2", "inactive", "300", "Bob"]]])
+      expect(RbCsv::Reader.new(io.call, typed: true, headers: true, where: "amount < 100").read_all.map { |row| row["name"] }).to eq(["Carol"])
+      expect(RbCsv::Table.parse(csv_data, where: "status == 'active'")[:name]).to eq(["Alice", "Carol", "Dave"])
+    end
+
+    it "compares the values produced by converters: when typed" do
+      csv = "id,amount\n1,$150\n2,$20\n3,$300\n"
+      options = { headers: true, converters: { "amount" => { strip_prefix: "$" } }, where: "amount > 100" }
+      expect(RbCsv.parse_typed(csv, **options).map { |row| row["id"] }).to eq([1, 3])
+      expect(RbCsv.foreach(StringIO.new(csv), typed: true, **options).map { |row| row["amount"] }).to eq([150, 300])
+      expect(RbCsv.parse(csv, **options.except(:converters))).to eq([])
+    end
+
+    it "resolves normalized header names" do
+      rows = RbCsv.parse("Unit Price,Qty\n5,1\n50,2\n", headers: true, header_converters: :snake_case, where: "unit_price > 10")
+      expect(rows).to eq([{ "unit_price" => "50", "qty" => "2" }])
+    end
+
+    it "resolves converter columns by the same header names as where:" do
+      options = { headers: true, header_converters: :downcase, where: "amount > 1", converters: { "amount" => { strip_prefix: "$" } } }
+      expect(RbCsv.parse_typed("Amount\n$1\n$5\n", **options)).to eq([{ "amount" => 5 }])
+    end
+
+    it "rejects invalid expressions" do
+      expect { RbCsv.parse(csv_data, where: "amount >") }.to raise_error(ArgumentError, /invalid where: expression/)
+      expect { RbCsv.parse(csv_data, where: 1) }.to raise_error(TypeError, /where: expected a String/)
+      expect { RbCsv.parse(csv_data, headers: true, where: "price > 1") }
+        .to raise_error(RuntimeError, /Filter column not found in headers: price/)
+    end
+  end
+
+  describe "keyword options" do
+    let(:csv_data) { " name , age \n Alice , 25 \n" }
+
+    it "combines trim:, typed: and headers: on a single method" do
+      expect(RbCsv.parse(csv_data, trim: true, typed: true, headers: true)).to eq([{ "name" => "Alice", "age" => 25 }])
+    end
+
+    it "treats the bang and typed variants as defaults that keywords override" do
+      expect(RbCsv.parse!(csv_data, trim: false)).to eq(RbCsv.parse(csv_data))
+      expect(RbCsv.parse_typed("a\n1\n", typed: false)).to eq([["a"], ["1"]])
+      expect(RbCsv.parse_typed!(csv_data)).to eq(RbCsv.parse(csv_data, trim: true, typed: true))
+    end
+
+    it "accepts nil as the default value" do
+      expect(RbCsv.parse(csv_data, trim: nil, headers: nil)).to eq(RbCsv.parse(csv_data))
+    end
+
+    it "reads other dialects in the streaming APIs" do
+      io = StringIO.new("a;'b;c'|1;2|")
+      expect(RbCsv.foreach(io, col_sep: ";", quote_char: "'", row_sep: "|").to_a).to eq([["a", "b;c"], ["1", "2"]])
+      expect(RbCsv::Reader.new(StringIO.new("a\n1,2\n"), flexible: true).read_all).to eq([["a"], ["1", "2"]])
+
+      expect { RbCsv.foreach(StringIO.new("a"), col_sep: "::").to_a }.to raise_error(ArgumentError, /col_sep must be a single byte/)
+      expect { RbCsv::Reader.new(StringIO.new("a"), headers: true, flexible: true) }.to raise_error(ArgumentError, /flexible/)
+    end
+
+    it "reads other dialects in parse and read" do
+      expect(RbCsv.parse("a\t'b\tc'\n1\t2\n", col_sep: "\t", quote_char: "'")).to eq([["a", "b\tc"], ["1", "2"]])
+      expect(RbCsv.parse_typed("name;age|Alice;25|", col_sep: ";", row_sep: "|", headers: true)).to eq([{ "name" => "Alice", "age" => 25 }])
+      expect(RbCsv.read(File.join(__dir__, "fixtures", "test.csv"), quote_char: "'")).to eq(RbCsv.read(File.join(__dir__, "fixtures", "test.csv")))
+      expect(RbCsv.parse("a\n1,2\n", flexible: true)).to eq([["a"], ["1", "2"]])
+      expect { RbCsv.parse("a\n1\n", headers: true, flexible: true) }.to raise_error(ArgumentError, /flexible/)
+    end
+
+    it "parses other dialects on a single thread when threads: is given" do
+      csv = "id;name\n" + (1..50_000).map { |i| "#{i};\"user;#{i}\"" }.join("\n")
+      expect(RbCsv.parse(csv, col_sep: ";", threads: 4)).to eq(RbCsv.parse(csv, col_sep: ";"))
+      expect(RbCsv.parse(csv, col_sep: ";", threads: 4).last).to eq(["50000", "user;50000"])
+    end
+
+    it "raises ArgumentError for unknown keywords" do
+      expect { RbCsv.parse(csv_data, trimm: true) }.to raise_error(ArgumentError, /unknown keyword: :trimm/)
+      expect { RbCsv.foreach(StringIO.new(csv_data), threads: 2) { } }.to raise_error(ArgumentError, /unknown keyword: :threads/)
+    end
+
+    it "raises TypeError for values of the wrong type" do
+      expect { RbCsv.parse(csv_data, trim: 1) }.to raise_error(TypeError, /trim: expected true, false or a Symbol, got 1/)
+      expect { RbCsv.parse(csv_data, converters: [1]) }.to raise_error(TypeError, /converters: expected a Hash/)
+    end
+
+    it "rejects contradictory combinations" do
+      expect { RbCsv.parse(csv_data, converters: { 0 => ->(v) { v } }) }
+        .to raise_error(ArgumentError, /converters: requires typed: true/)
+      expect { RbCsv.parse(csv_data, headers: true, as: Struct.new(:name, :age)) }
+        .to raise_error(ArgumentError, /headers: and as: cannot be used together/)
+    end
+  end
+
+  describe "concurrent parsing" do
+    let(:csv_data) { "id,name,score\n" + (1..20_000).map { |i| "#{i},user#{i},#{i * 1.5}\n" }.join }
+    let(:large_data) { csv_data + (1..50).map { csv_data.delete_prefix("id,name,score\n") }.join }
+
+    it "parses in multiple threads with the same results" do
+      expected = RbCsv.parse_typed(csv_data)
+      threads = 4.times.map { Thread.new { RbCsv.parse_typed(csv_data) } }
+      expect(threads.map(&:value)).to all(eq(expected))
+    end
+
+    it "lets other threads run while parsing" do
+      ticks = 0
+      ticker = Thread.new { loop { ticks += 1; Thread.pass } }
+      RbCsv.parse(large_data)
+      ticker.kill
+      expect(ticks).to be > 0
+    end
+
+    it "parses with threads: and returns the same rows" do
+      expect(RbCsv.parse(large_data, threads: 4)).to eq(RbCsv.parse(large_data))
+      expect(RbCsv.parse_typed(large_data, threads: 4, headers: true)).to eq(RbCsv.parse_typed(large_data, headers: true))
+    end
+
+    it "keeps quoted newlines intact across chunks" do
+      data = "id,note\n" + (1..50_000).map { |i| "#{i},\"line\n#{i}, \"\"q\"\"\"\n" }.join
+      rows = RbCsv.parse(data, threads: 8)
+      expect(rows.length).to eq(50_001)
+      expect(rows.last).to eq(["50000", "line\n50000, \"q\""])
+    end
+
+    it "reports field count errors with the line number" do
+      expect {
+        RbCsv.parse(large_data + "1,2\n", threads: 4)
+      }.to raise_error(RuntimeError, /Field count mismatch at line 1020002/)
+    end
+
+    it "raises error for non-positive threads" do
+      expect { RbCsv.parse("a,b", threads: 0) }.to raise_error(ArgumentError, /threads/)
+    end
+
+    it "rejects threads: for IO input" do
+      expect { RbCsv.read(StringIO.new("a,b\n"), threads: 2) }.to raise_error(ArgumentError, /not supported for IO/)
+      expect { RbCsv::Table.read(StringIO.new("a,b\n"), threads: 2) }.to raise_error(ArgumentError, /not supported for IO/)
+      expect(RbCsv.read(StringIO.new("a,b\n"), threads: 1)).to eq([["a", "b"]])
+    end
+
+    it "stops a parse when the thread is killed" do
+      thread = Thread.new { RbCsv.parse(large_data) }
+      sleep 0.01
+      thread.kill
+      expect(thread.join(5)).not_to be_nil
+    end
+  end
 end